@@ -2,7 +2,7 @@ extern crate criterion;
 
 use criterion::*;
 
-use globber::Pattern;
+use globber::{GlobSet, Pattern, PatternBuilder, SuffixGlobSet};
 
 fn glob_benchmark(c: &mut Criterion) {
     c.bench("",
@@ -17,18 +17,89 @@ fn glob_benchmark(c: &mut Criterion) {
                 b.iter(|| p.matches("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
             }).throughput(Throughput::Bytes("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".len() as u32)),
     );
+    let nested = format!("{}{}", "b".repeat(20), "a".repeat(12));
+    c.bench("",
+            Benchmark::new("@(a|b)*a*a*a*a*a*a*a*???????a no match", move |b| {
+                let p = Pattern::new("@(a|b)*a*a*a*a*a*a*a*???????a").unwrap();
+                b.iter(|| p.matches(&nested));
+            }).throughput(Throughput::Bytes(32)),
+    );
     c.bench("",
             Benchmark::new("*hello.txt", |b| {
                 let p = Pattern::new("*hello.txt").unwrap();
                 b.iter(|| p.matches("gareth_says_hello.txt"));
             }).throughput(Throughput::Bytes("gareth_says_hello.txt".len() as u32)),
     );
+    let other = format!("{}_says_goodbye.txt", "gareth".repeat(64));
+    c.bench("",
+            Benchmark::new("*hello.txt no match", move |b| {
+                let p = Pattern::new("*hello.txt").unwrap();
+                b.iter(|| p.matches(&other));
+            }).throughput(Throughput::Bytes(400)),
+    );
     c.bench("",
             Benchmark::new("!(+(secret|private)*+(.jpg|.gif))", |b| {
                 let p = Pattern::new("!(+(secret|private)*+(.jpg|.gif))").unwrap();
                 b.iter(|| p.matches("secret_image.png"));
             }).throughput(Throughput::Bytes("secret_image.png".len() as u32)),
     );
+    let long = format!("prefix{}", "x".repeat(4096));
+    c.bench("",
+            Benchmark::new("prefix*", move |b| {
+                let p = Pattern::new("prefix*").unwrap();
+                b.iter(|| p.matches(&long));
+            }).throughput(Throughput::Bytes(4102)),
+    );
+    let class = "[a-zA-Z0-9_-]".repeat(64);
+    let word = "aZ09_-".repeat(11)[..64].to_owned();
+    c.bench("",
+            Benchmark::new("[a-zA-Z0-9_-] x 64", move |b| {
+                let p = Pattern::new(&class).unwrap();
+                b.iter(|| p.matches(&word));
+            }).throughput(Throughput::Bytes(64)),
+    );
+    let alternatives = format!("src/+({}|main.rs)", (b'a'..=b'z').map(|c| format!("*.{}", c as char)).collect::<Vec<_>>().join("|"));
+    let reordered = alternatives.clone();
+    c.bench("",
+            Benchmark::new("+(*.a|...|*.z|main.rs)", move |b| {
+                let p = Pattern::new(&alternatives).unwrap();
+                b.iter(|| p.matches("src/main.rs"));
+            }).throughput(Throughput::Bytes("src/main.rs".len() as u32)),
+    );
+    c.bench("",
+            Benchmark::new("+(*.a|...|*.z|main.rs) reordered", move |b| {
+                let p = PatternBuilder::new(&reordered).reorder_alternatives(true).build().unwrap();
+                b.iter(|| p.matches("src/main.rs"));
+            }).throughput(Throughput::Bytes("src/main.rs".len() as u32)),
+    );
+    let haystack = "x".repeat(1000) + "somelongtoken_a.rs";
+    let input = haystack.clone();
+    c.bench("",
+            Benchmark::new("*somelongtoken?a.rs long input", move |b| {
+                let p = Pattern::new("*somelongtoken?a.rs").unwrap();
+                b.iter(|| p.matches(&input));
+            }).throughput(Throughput::Bytes(haystack.len() as u32)),
+    );
+    let input = haystack.clone();
+    c.bench("",
+            Benchmark::new("*somelong*token*.rs literal leading dot", move |b| {
+                let p = PatternBuilder::new("*somelong*token*.rs").require_literal_leading_dot(true).build().unwrap();
+                b.iter(|| p.matches(&input));
+            }).throughput(Throughput::Bytes(haystack.len() as u32)),
+    );
+    let extensions: Vec<String> = (0..2000).map(|i| format!("*.ext{}", i)).collect();
+    let general: GlobSet = extensions.iter().map(|p| Pattern::new(p).unwrap()).collect();
+    let suffixes: SuffixGlobSet = extensions.iter().map(|p| Pattern::new(p).unwrap()).collect();
+    c.bench("",
+            Benchmark::new("GlobSet 2000 x *.ext", move |b| {
+                b.iter(|| general.matches("src/archive.ext1999"));
+            }).throughput(Throughput::Bytes("src/archive.ext1999".len() as u32)),
+    );
+    c.bench("",
+            Benchmark::new("SuffixGlobSet 2000 x *.ext", move |b| {
+                b.iter(|| suffixes.matches("src/archive.ext1999"));
+            }).throughput(Throughput::Bytes("src/archive.ext1999".len() as u32)),
+    );
 }
 
 criterion_group!(benches, glob_benchmark);
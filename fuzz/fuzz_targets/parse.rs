@@ -0,0 +1,28 @@
+#![no_main]
+use std::time::{Duration, Instant};
+
+use libfuzzer_sys::fuzz_target;
+
+use globber::Pattern;
+
+// long enough to nest patterns past the limit many times over, short enough that parsing one
+// has no excuse to be slow, so a slow pattern is a bug and not just a big input
+const MAX_LEN: usize = 4096;
+
+// any input has to either parse or return an error, and whatever parses has to match without panicking
+fuzz_target!(|data: &[u8]| {
+    if data.len() > MAX_LEN {
+        return;
+    }
+
+    if let Ok(input) = std::str::from_utf8(data) {
+        let start = Instant::now();
+        let parsed = Pattern::new(input);
+        assert!(start.elapsed() < Duration::from_millis(100), "parsing took {:?}", start.elapsed());
+
+        if let Ok(pattern) = parsed {
+            pattern.matches(input);
+            pattern.canonical().matches(input);
+        }
+    }
+});
\ No newline at end of file
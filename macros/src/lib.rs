@@ -0,0 +1,139 @@
+//! Macros for [globber](https://docs.rs/globber) that check glob patterns while compiling.
+//!
+//! ```toml
+//! [dependencies]
+//! globber = "0.1"
+//! globber-macros = "0.1"
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Literal, Span, TokenStream, TokenTree};
+
+use globber::Pattern;
+
+/// Returns true if the input matches the pattern, like
+/// `globber::Pattern::new(pattern).unwrap().matches(input)` but a pattern with a syntax error doesn't compile.
+///
+/// The pattern has to be a string literal, the input anything that derefs to a `&str`.
+/// Handy in `if` conditions and `match` guards.
+/// ```rust
+/// # use globber_macros::glob_matches;
+/// let path = String::from("src/main.rs");
+/// assert!(glob_matches!("src/**/*.@(rs|toml)", path));
+///
+/// let kind = match "Cargo.toml" {
+///     name if glob_matches!("*.rs", name) => "source",
+///     name if glob_matches!("Cargo.@(toml|lock)", name) => "manifest",
+///     _ => "other",
+/// };
+/// assert_eq!(kind, "manifest");
+/// ```
+///
+/// The pattern is only checked while compiling, it is still parsed every time the macro runs.
+/// To match many inputs against the same pattern build a `globber::Pattern` once and keep it.
+/// ```rust,compile_fail
+/// # use globber_macros::glob_matches;
+/// glob_matches!("src/[a-z", "src/main.rs");
+/// ```
+#[proc_macro]
+pub fn glob_matches(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter();
+    let (literal, pattern) = match tokens.next() {
+        Some(TokenTree::Literal(literal)) => match string(&literal.to_string()) {
+            Some(pattern) => (literal, pattern),
+            None => return error("the pattern must be a string literal", literal.span()),
+        },
+        Some(token) => return error("the pattern must be a string literal", token.span()),
+        None => return error("expected a pattern and an input, e.g. glob_matches!(\"*.rs\", path)", Span::call_site()),
+    };
+
+    match tokens.next() {
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {}
+        Some(token) => return error("expected a `,` after the pattern", token.span()),
+        None => return error("expected an input after the pattern", literal.span()),
+    }
+
+    let mut input: Vec<TokenTree> = tokens.collect();
+    if matches!(input.last(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',') {
+        input.pop();
+    }
+    if input.is_empty() {
+        return error("expected an input after the pattern", literal.span());
+    }
+
+    if let Err(err) = Pattern::new(&pattern) {
+        return error(&err.to_string(), literal.span());
+    }
+
+    // ::globber::Pattern::new(literal).expect(..).matches(&(input))
+    let mut expanded: TokenStream = "::globber::Pattern::new".parse().unwrap();
+    expanded.extend(Some(group(Delimiter::Parenthesis, TokenTree::Literal(literal).into())));
+    expanded.extend(".expect".parse::<TokenStream>().unwrap());
+    expanded.extend(Some(group(Delimiter::Parenthesis, "\"the pattern was checked when compiling\"".parse().unwrap())));
+    expanded.extend(".matches".parse::<TokenStream>().unwrap());
+    let mut reference: TokenStream = "&".parse().unwrap();
+    reference.extend(Some(group(Delimiter::Parenthesis, input.into_iter().collect())));
+    expanded.extend(Some(group(Delimiter::Parenthesis, reference)));
+    expanded
+}
+
+fn group(delimiter: Delimiter, stream: TokenStream) -> TokenTree {
+    TokenTree::Group(Group::new(delimiter, stream))
+}
+
+// compile_error!(message) pointing at span
+fn error(message: &str, span: Span) -> TokenStream {
+    let message = TokenTree::Literal(Literal::string(message));
+    "::core::compile_error!".parse::<TokenStream>().unwrap()
+        .into_iter()
+        .chain(Some(group(Delimiter::Parenthesis, message.into())))
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+// the value of a string literal as written in the source, None for any other literal
+fn string(source: &str) -> Option<String> {
+    if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw.get(hashes..raw.len() - hashes)?;
+        return Some(body.strip_prefix('"')?.strip_suffix('"')?.to_string());
+    }
+
+    let body = source.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let c = match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                char::from(u8::from_str_radix(&hex, 16).ok()?)
+            }
+            'u' => {
+                let hex: String = chars.by_ref().skip(1).take_while(|c| *c != '}').filter(|c| *c != '_').collect();
+                core::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            // a line break escaped at the end of a line skips the whitespace that follows
+            '\n' | '\r' => {
+                while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+                    chars.next();
+                }
+                continue;
+            }
+            c => c,
+        };
+        value.push(c);
+    }
+    Some(value)
+}
\ No newline at end of file
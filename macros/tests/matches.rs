@@ -0,0 +1,28 @@
+use globber_macros::glob_matches;
+
+#[test]
+fn matches() {
+    let path = String::from("src/bin/main.rs");
+    assert!(glob_matches!("src/**/*.rs", path));
+    assert!(glob_matches!("src/**/*.rs", &path));
+    assert!(glob_matches!("src/**/*.rs", path.as_str(),));
+    assert!(!glob_matches!("tests/*.rs", path));
+
+    // escapes in the literal are Rust's, the ones left over are the pattern's
+    assert!(glob_matches!("a\\*\u{e9}\x41", "a*éA"));
+    assert!(!glob_matches!("a\\*", "ab"));
+    assert!(glob_matches!(r"a\?\t", "a?t"));
+    assert!(glob_matches!(r#"@("|')"#, "'"));
+    assert!(glob_matches!("tab\there", "tab\there"));
+    assert!(glob_matches!("long \
+                           line", "long line"));
+
+    let name = |n: &str| match n {
+        n if glob_matches!("*.@(rs|toml)", n) => 1,
+        n if glob_matches!("!(*.*)", n) => 2,
+        _ => 3,
+    };
+    assert_eq!(name("lib.rs"), 1);
+    assert_eq!(name("Makefile"), 2);
+    assert_eq!(name("a.c"), 3);
+}
\ No newline at end of file
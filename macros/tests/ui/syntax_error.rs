@@ -0,0 +1,8 @@
+use globber_macros::glob_matches;
+
+fn main() {
+    let path = "src/main.rs";
+    if glob_matches!("src/@(*.rs|", path) {}
+    if glob_matches!(r"[z-", path) {}
+    if glob_matches!(PATTERN, path) {}
+}
\ No newline at end of file
@@ -0,0 +1,419 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::matcher::{match_specifiers, Status};
+use crate::options::{is_any_separator, MatchOptions};
+use crate::syntax::{CharSpecifier, Token};
+
+/// A conservative estimate of how much backtracking matching a pattern can take,
+/// ordered from cheapest to most expensive.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum Complexity {
+    /// At most one wildcard, every input char is looked at a bounded number of times.
+    Linear,
+    /// Several wildcards or patterns, each one can retry the rest of the glob at every offset.
+    Polynomial,
+    /// Patterns containing wildcards or other patterns, retries nest into each other.
+    Exponential,
+}
+
+/// Counts describing the shape of a pattern, see [`Pattern::stats`](crate::Pattern::stats).
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default)]
+pub struct PatternStats {
+    /// Every char, wildcard, range and pattern, including everything inside patterns.
+    pub tokens: usize,
+    /// Every `*` and `**`, including the ones inside patterns.
+    pub wildcards: usize,
+    /// How deep patterns like `@(...)` nest, 0 without any and 2 for `!(!(a))`.
+    pub max_depth: usize,
+    /// The alternatives of all patterns added up, 3 for `@(a|b)?(c)`.
+    pub alternatives: usize,
+}
+
+/// Where a path component of a pattern is, see [`Pattern::literal_segments`](crate::Pattern::literal_segments).
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum SegmentPos {
+    /// The component at this index counted from the start, 0 being the first.
+    Start(usize),
+    /// The component at this index counted from the end, 0 being the last.
+    End(usize),
+    /// A `**` before and after it means it can be anywhere.
+    Floating,
+}
+
+pub(crate) fn stats(tokens: &[Token]) -> PatternStats {
+    let mut stats = PatternStats::default();
+    add_stats(tokens, 0, &mut stats);
+    stats
+}
+
+fn add_stats(tokens: &[Token], depth: usize, stats: &mut PatternStats) {
+    stats.max_depth = stats.max_depth.max(depth);
+
+    for token in tokens {
+        let token = match token {
+            Token::Named(_, pattern) => &**pattern,
+            t => t,
+        };
+
+        stats.tokens += 1;
+        match token {
+            Token::AnySequence | Token::AnyRecursive | Token::AtLeastOneSegment => stats.wildcards += 1,
+            Token::ZeroOrOne(p) | Token::ZeroOrMore(p) | Token::OneOrMore(p) | Token::ExactlyOne(p) | Token::NoneOf(p) => {
+                stats.alternatives += p.len();
+                for pattern in p {
+                    add_stats(pattern, depth + 1, stats);
+                }
+            }
+            Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_) | Token::Char(_) | Token::Named(..) => {}
+        }
+    }
+}
+
+pub(crate) fn complexity(tokens: &[Token]) -> Complexity {
+    let mut wildcards = 0;
+    let mut complexity = Complexity::Linear;
+
+    for token in tokens {
+        // a name doesn't change how the pattern matches
+        let token = match token {
+            Token::Named(_, pattern) => &**pattern,
+            t => t,
+        };
+
+        let patterns = match token {
+            Token::AnySequence | Token::AnyRecursive | Token::AtLeastOneSegment => {
+                wildcards += 1;
+                continue;
+            }
+            Token::ZeroOrOne(p) | Token::ZeroOrMore(p) | Token::OneOrMore(p) | Token::ExactlyOne(p) => p,
+            Token::NoneOf(p) => {
+                // scans the input just like a wildcard does
+                wildcards += 1;
+                p
+            }
+            Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_) | Token::Char(_) | Token::Named(..) => continue,
+        };
+
+        let nested = patterns.iter()
+            .any(|p| p.iter().any(|t| !matches!(t, Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_) | Token::Char(_))));
+        let group = if nested { Complexity::Exponential } else { Complexity::Polynomial };
+        complexity = complexity.max(group);
+    }
+
+    if wildcards > 1 {
+        complexity = complexity.max(Complexity::Polynomial);
+    }
+
+    complexity
+}
+
+// the fewest and most chars an input matching the tokens can have, None for no limit
+pub(crate) fn len_bounds(tokens: &[Token], is_separator: fn(char) -> bool) -> (usize, Option<usize>) {
+    suffix_bounds(tokens, is_separator).0[0]
+}
+
+// len_bounds(&tokens[j..]) for every j up to and including tokens.len()
+pub(crate) fn suffix_len_bounds(tokens: &[Token], is_separator: fn(char) -> bool) -> Vec<(usize, Option<usize>)> {
+    suffix_bounds(tokens, is_separator).0
+}
+
+// the suffix bounds worked out from the end, walking every token once, and whether any token contains a **
+fn suffix_bounds(tokens: &[Token], is_separator: fn(char) -> bool) -> (Vec<(usize, Option<usize>)>, bool) {
+    let mut bounds = vec![(0, Some(0)); tokens.len() + 1];
+    let mut any_recursion = false;
+
+    for (j, token) in tokens.iter().enumerate().rev() {
+        let (token_min, token_max, recursion) = token_bounds(token, is_separator);
+        any_recursion |= recursion;
+
+        // a ** matching nothing takes the separators after it along, also when it ends a pattern,
+        // so they add nothing to the fewest chars and whatever follows them adds as much as on its own
+        let skipped = if recursion {
+            tokens[j + 1..].iter().take_while(|t| matches!(t, Token::Char(c) if is_separator(*c))).count()
+        } else {
+            0
+        };
+        let (min, max) = bounds[j + 1 + skipped];
+        bounds[j] = (token_min + min, match (token_max, max) {
+            (Some(token_max), Some(max)) => Some(token_max + skipped + max),
+            _ => None,
+        });
+    }
+
+    (bounds, any_recursion)
+}
+
+// the bounds of a single token and whether it contains a **
+fn token_bounds(token: &Token, is_separator: fn(char) -> bool) -> (usize, Option<usize>, bool) {
+    match token {
+        Token::Char(_) | Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_) => (1, Some(1), false),
+        Token::AnySequence => (0, None, false),
+        Token::AnyRecursive => (0, None, true),
+        Token::AtLeastOneSegment => (1, None, false),
+        Token::ZeroOrMore(p) | Token::NoneOf(p) => (0, None, alternatives_bounds(p, is_separator).2),
+        Token::ZeroOrOne(p) => {
+            let (_, max, recursion) = alternatives_bounds(p, is_separator);
+            (0, max, recursion)
+        }
+        Token::OneOrMore(p) => {
+            let (min, _, recursion) = alternatives_bounds(p, is_separator);
+            (min, None, recursion)
+        }
+        Token::ExactlyOne(p) => alternatives_bounds(p, is_separator),
+        Token::Named(_, pattern) => token_bounds(pattern, is_separator),
+    }
+}
+
+fn alternatives_bounds(patterns: &[Vec<Token>], is_separator: fn(char) -> bool) -> (usize, Option<usize>, bool) {
+    // one walk through every alternative, nested patterns would otherwise be walked once more at every level
+    let mut bounds = patterns.iter().map(|p| {
+        let (bounds, recursion) = suffix_bounds(p, is_separator);
+        (bounds[0].0, bounds[0].1, recursion)
+    });
+    let first = match bounds.next() {
+        Some(first) => first,
+        None => return (0, Some(0), false),
+    };
+    bounds.fold(first, |(min, max, recursion), (p_min, p_max, p_recursion)| {
+        (min.min(p_min), max.and_then(|max| Some(max.max(p_max?))), recursion || p_recursion)
+    })
+}
+
+// the components without any wildcards, ranges or patterns, anchored to the start when nothing before them
+// can span several components and otherwise to the end if nothing after them can
+pub(crate) fn literal_segments(tokens: &[Token], is_separator: fn(char) -> bool) -> Vec<(SegmentPos, String)> {
+    let components: Vec<&[Token]> = tokens.split(|t| matches!(t, Token::Char(c) if is_separator(*c))).collect();
+    let spans = |component: &[Token]| component.iter().any(|t| match t {
+        Token::Char(_) => false,
+        t => has_separators(core::slice::from_ref(t), is_separator),
+    });
+    let first = components.iter().position(|c| spans(c));
+    let last = components.iter().rposition(|c| spans(c));
+
+    components.iter()
+        .enumerate()
+        .filter_map(|(i, component)| {
+            let literal = component.iter()
+                .map(|t| match t {
+                    Token::Char(c) => Some(*c),
+                    _ => None,
+                })
+                .collect::<Option<String>>()?;
+            if literal.is_empty() {
+                return None;
+            }
+
+            let pos = match (first, last) {
+                (Some(first), _) if i < first => SegmentPos::Start(i),
+                (_, Some(last)) if i > last => SegmentPos::End(components.len() - 1 - i),
+                (None, _) => SegmentPos::Start(i),
+                _ => SegmentPos::Floating,
+            };
+            Some((pos, literal))
+        })
+        .collect()
+}
+
+// the separators at the top level, None when a ** or a pattern containing separators can change how many there are
+pub(crate) fn depth(tokens: &[Token], is_separator: fn(char) -> bool) -> Option<usize> {
+    let mut depth = 0;
+    for token in tokens {
+        match token {
+            Token::Char(c) if is_separator(*c) => depth += 1,
+            Token::AnyRecursive | Token::AtLeastOneSegment => return None,
+            Token::ZeroOrOne(_) | Token::ZeroOrMore(_) | Token::OneOrMore(_) | Token::ExactlyOne(_) | Token::NoneOf(_)
+            | Token::Named(..) => {
+                if has_separators(core::slice::from_ref(token), is_separator) {
+                    return None;
+                }
+            }
+            Token::Char(_) | Token::AnyChar | Token::AnySequence | Token::AnyOf(_) | Token::NotAnyOf(_) => {}
+        }
+    }
+    Some(depth)
+}
+
+fn has_separators(tokens: &[Token], is_separator: fn(char) -> bool) -> bool {
+    tokens.iter().any(|token| match token {
+        Token::Char(c) => is_separator(*c),
+        Token::AnyRecursive | Token::AtLeastOneSegment => true,
+        Token::ZeroOrOne(p) | Token::ZeroOrMore(p) | Token::OneOrMore(p) | Token::ExactlyOne(p) | Token::NoneOf(p) => {
+            p.iter().any(|p| has_separators(p, is_separator))
+        }
+        Token::Named(_, pattern) => has_separators(core::slice::from_ref(&**pattern), is_separator),
+        Token::AnyChar | Token::AnySequence | Token::AnyOf(_) | Token::NotAnyOf(_) => false,
+    })
+}
+
+// false only when no input can match, patterns like !(...) are assumed to match something
+// unless they exclude everything with a lone wildcard
+pub(crate) fn is_satisfiable(tokens: &[Token]) -> bool {
+    tokens.iter().all(|token| match token {
+        Token::AnyOf(specifiers) => !specifiers.is_empty(),
+        Token::NotAnyOf(specifiers) => !covers_all_chars(specifiers),
+        Token::ZeroOrOne(_) | Token::ZeroOrMore(_) => true,
+        Token::OneOrMore(p) | Token::ExactlyOne(p) => p.iter().any(|p| is_satisfiable(p)),
+        Token::NoneOf(p) => !p.iter().any(|p| !p.is_empty() && p.iter().all(|t| matches!(t, Token::AnySequence | Token::AnyRecursive))),
+        Token::Named(_, pattern) => is_satisfiable(core::slice::from_ref(&**pattern)),
+        Token::AnyChar | Token::AnySequence | Token::AnyRecursive | Token::AtLeastOneSegment | Token::Char(_) => true,
+    })
+}
+
+// normalized specifiers leave a gap at the surrogates, which aren't chars
+fn covers_all_chars(specifiers: &[CharSpecifier]) -> bool {
+    let mut next = 0;
+    for specifier in specifiers {
+        let (start, end) = match *specifier {
+            CharSpecifier::Char(c) => (c as u32, c as u32),
+            CharSpecifier::Range(start, end) => (start as u32, end as u32),
+        };
+        let surrogates = next >= 0xD800 && start <= 0xE000;
+        if start > next && !surrogates {
+            return false;
+        }
+        next = end + 1;
+    }
+    next > char::MAX as u32
+}
+
+pub(crate) fn required_extension(tokens: &[Token], is_separator: fn(char) -> bool) -> Option<String> {
+    // only the literal chars after the last wildcard, range or pattern are fixed
+    let literal = tokens.iter().rev().take_while(|t| matches!(t, Token::Char(_))).count();
+    let suffix = &tokens[tokens.len() - literal..];
+
+    let name_start = suffix.iter()
+        .rposition(|t| matches!(t, Token::Char(c) if is_separator(*c)))
+        .map(|i| i + 1);
+    let name = &suffix[name_start.unwrap_or(0)..];
+    let dot = name.iter().rposition(|t| *t == Token::Char('.'))?;
+
+    // a fixed file name like .gitignore starts with a dot but has no extension
+    let whole_name = name_start.is_some() || literal == tokens.len();
+    if dot == 0 && whole_name {
+        return None;
+    }
+
+    let extension: String = name[dot + 1..].iter()
+        .filter_map(|t| match t {
+            Token::Char(c) => Some(*c),
+            _ => None,
+        })
+        .collect();
+    if extension.is_empty() {
+        None
+    } else {
+        Some(extension)
+    }
+}
+
+/// Lists up to `limit` strings the tokens match, going through the choices of every token in the order
+/// they are written, or `None` when there are infinitely many. Every string is checked with `accept`,
+/// which leaves out the ones the options rule out.
+pub(crate) fn enumerate(tokens: &[Token], is_separator: fn(char) -> bool, options: &MatchOptions, limit: usize,
+                        accept: &dyn Fn(&str) -> bool) -> Option<Vec<String>> {
+    if len_bounds(tokens, is_separator).1.is_none() || options.unanchored_start {
+        return None;
+    }
+
+    let mut walk = Walk { options, limit, accept, seen: BTreeSet::new(), found: Vec::new() };
+    // a dead token after a ? would otherwise be reached once for every char
+    if limit > 0 && is_satisfiable(tokens) {
+        walk.tokens(tokens, 0, &mut Vec::new(), &mut String::new());
+    }
+    Some(walk.found)
+}
+
+struct Walk<'a> {
+    options: &'a MatchOptions,
+    limit: usize,
+    accept: &'a dyn Fn(&str) -> bool,
+    seen: BTreeSet<String>,
+    found: Vec<String>,
+}
+
+impl<'t> Walk<'_> {
+    // extends the prefix by every choice of tokens[i..] and then of the tokens following the groups they are in,
+    // returns false once enough strings are found
+    fn tokens(&mut self, tokens: &'t [Token], i: usize, after: &mut Vec<&'t [Token]>, prefix: &mut String) -> bool {
+        let token = match tokens.get(i) {
+            Some(token) => token,
+            None => return match after.pop() {
+                Some(next) => {
+                    let more = self.tokens(next, 0, after, prefix);
+                    after.push(next);
+                    more
+                }
+                None => self.found(prefix),
+            },
+        };
+
+        let options = self.options;
+        let chars: &mut dyn Iterator<Item=char> = match token {
+            Token::Char(c) => {
+                let folded = Some(c.to_ascii_uppercase()).filter(|_| options.case_insensitive && c.is_ascii_lowercase())
+                    .or_else(|| Some(c.to_ascii_lowercase()).filter(|_| options.case_insensitive && c.is_ascii_uppercase()));
+                let other = ['/', '\\'].iter().copied().find(|o| options.normalize_separators && is_any_separator(*c) && o != c);
+                &mut core::iter::once(*c).chain(folded).chain(other)
+            }
+            Token::AnyChar => &mut ('\0'..=char::MAX),
+            Token::AnyOf(specifiers) => {
+                let contains = move |c: char| match_specifiers(specifiers, c) == Status::Match;
+                let folded = ('A'..='Z').chain('a'..='z')
+                    .filter(move |c| options.case_insensitive && !contains(*c) && contains(swap_case(*c)));
+                &mut specifiers.iter()
+                    .flat_map(|s| match *s {
+                        CharSpecifier::Char(c) => c..=c,
+                        CharSpecifier::Range(start, end) => start..=end,
+                    })
+                    .chain(folded)
+            }
+            Token::NotAnyOf(specifiers) => &mut ('\0'..=char::MAX).filter(move |c| match_specifiers(specifiers, *c) != Status::Match),
+            Token::ZeroOrOne(patterns) => {
+                // matching nothing comes first
+                return self.tokens(tokens, i + 1, after, prefix) && self.alternatives(patterns, &tokens[i + 1..], after, prefix);
+            }
+            Token::ExactlyOne(patterns) => return self.alternatives(patterns, &tokens[i + 1..], after, prefix),
+            Token::Named(_, pattern) => {
+                after.push(&tokens[i + 1..]);
+                let more = self.tokens(core::slice::from_ref(&**pattern), 0, after, prefix);
+                after.pop();
+                return more;
+            }
+            Token::AnySequence | Token::AnyRecursive | Token::AtLeastOneSegment
+            | Token::ZeroOrMore(_) | Token::OneOrMore(_) | Token::NoneOf(_) => unreachable!("infinite"),
+        };
+
+        let len = prefix.len();
+        for c in chars {
+            prefix.push(c);
+            let more = self.tokens(tokens, i + 1, after, prefix);
+            prefix.truncate(len);
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn alternatives(&mut self, patterns: &'t [Vec<Token>], rest: &'t [Token], after: &mut Vec<&'t [Token]>, prefix: &mut String) -> bool {
+        after.push(rest);
+        let more = patterns.iter().all(|p| self.tokens(p, 0, after, prefix));
+        after.pop();
+        more
+    }
+
+    fn found(&mut self, s: &str) -> bool {
+        if !self.seen.contains(s) && (self.accept)(s) {
+            self.seen.insert(s.into());
+            self.found.push(s.into());
+        }
+        self.found.len() < self.limit
+    }
+}
+
+fn swap_case(c: char) -> char {
+    if c.is_ascii_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() }
+}
\ No newline at end of file
@@ -0,0 +1,197 @@
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::{CharClass, Error, MatchOptions, Pattern};
+use crate::matcher::Matcher;
+use crate::options::is_separator;
+use crate::syntax::{parse_with, validate_recursive_placement, Error as SyntaxError, Token};
+use crate::token::token_error;
+
+/// The parsed form of a [`Pattern`], for assembling patterns from pieces without escaping them into a string.
+///
+/// Every method appends to the end of the pattern, literals are taken as is so `*` in a literal only matches a `*`.
+/// ```rust
+/// # use globber::Ast;
+/// let pattern = Ast::new()
+///     .literal("src/")
+///     .recursive()
+///     .literal("/")
+///     .any_sequence()
+///     .literal(".")
+///     .one_of(vec![Ast::from_literal("rs"), Ast::from_literal("toml")])
+///     .compile();
+/// assert!(pattern.matches("src/bin/main.rs"));
+/// assert!(pattern.matches("src/Cargo.toml"));
+/// assert!(!pattern.matches("src/main.c"));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct Ast {
+    tokens: Vec<Token>,
+}
+
+impl Ast {
+    /// Creates an empty pattern, which only matches empty input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a pattern like [`Pattern::new`] does.
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        pattern.parse()
+    }
+
+    pub fn from_literal(literal: &str) -> Self {
+        let mut ast = Self::new();
+        ast.literal(literal);
+        ast
+    }
+
+    /// Matches exactly these chars.
+    pub fn literal(&mut self, literal: &str) -> &mut Self {
+        self.tokens.extend(literal.chars().map(Token::Char));
+        self
+    }
+
+    /// Matches any single char, like `?`.
+    pub fn any_char(&mut self) -> &mut Self {
+        self.tokens.push(Token::AnyChar);
+        self
+    }
+
+    /// Matches any sequence of chars, like `*`.
+    pub fn any_sequence(&mut self) -> &mut Self {
+        self.tokens.push(Token::AnySequence);
+        self
+    }
+
+    /// Matches any number of path components, like `**`.
+    ///
+    /// Unlike in a parsed pattern it is not checked that it is a whole path component on its own,
+    /// see [`try_compile`](Ast::try_compile).
+    pub fn recursive(&mut self) -> &mut Self {
+        self.tokens.push(Token::AnyRecursive);
+        self
+    }
+
+    /// Matches a single char allowed by the class, like `[a-z]`.
+    pub fn class(&mut self, class: &CharClass) -> &mut Self {
+        self.tokens.push(class.to_token());
+        self
+    }
+
+    /// Matches exactly one of the alternatives, like `@(a|b)`.
+    pub fn one_of<I: IntoIterator<Item=Ast>>(&mut self, alternatives: I) -> &mut Self {
+        self.tokens.push(Token::ExactlyOne(alternatives.into_iter().map(|a| a.tokens).collect()));
+        self
+    }
+
+    /// Appends all of another pattern.
+    pub fn append(&mut self, other: &Ast) -> &mut Self {
+        self.tokens.extend_from_slice(&other.tokens);
+        self
+    }
+
+    pub fn compile(&self) -> Pattern {
+        self.clone().compile_with(is_separator)
+    }
+
+    /// Like [`compile`](Ast::compile) but first checks that every `**` is a whole path component like
+    /// a parsed pattern has to, the position of an error counts chars into the pattern written out as a string.
+    /// ```rust
+    /// # use globber::{Ast, ErrorKind};
+    /// assert!(Ast::new().literal("a/").recursive().try_compile().is_ok());
+    /// let err = Ast::new().literal("a").recursive().try_compile().unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::IllegalRecursion);
+    /// ```
+    pub fn try_compile(&self) -> Result<Pattern, Error> {
+        validate_recursive_placement(&self.tokens, is_separator)
+            .map_err(|i| token_error(&self.tokens, i, SyntaxError::IllegalRecursion(0)))?;
+        Ok(self.compile())
+    }
+
+    pub(crate) fn compile_with(self, is_separator: fn(char) -> bool) -> Pattern {
+        Pattern {
+            matcher: Matcher::new(self.tokens, is_separator),
+            options: MatchOptions::new(),
+        }
+    }
+
+    pub(crate) fn from_tokens(tokens: Vec<Token>) -> Self {
+        Self { tokens }
+    }
+}
+
+impl FromStr for Ast {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = parse_with(s, is_separator)
+            .map_err(|e| Error::new(e, s))?;
+        Ok(Self { tokens })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ast;
+    use crate::{CharClass, ErrorKind, Pattern};
+    use crate::syntax::Error;
+
+    #[test]
+    fn build() {
+        let pattern = Ast::new()
+            .literal("src/")
+            .any_sequence()
+            .literal(".rs")
+            .compile();
+        assert_eq!(pattern, Pattern::new("src/*.rs").unwrap());
+        assert!(pattern.matches("src/main.rs"));
+        assert!(!pattern.matches("src/main.c"));
+
+        let pattern = Ast::new()
+            .literal("[*]")
+            .class(&CharClass::new("[!0-9]").unwrap())
+            .any_char()
+            .compile();
+        assert_eq!(pattern, Pattern::new("\\[\\*\\][!0-9]?").unwrap());
+        assert!(pattern.matches("[*]ab"));
+        assert!(!pattern.matches("[*]1a"));
+        assert!(!pattern.matches("xxxab"));
+    }
+
+    #[test]
+    fn edit() {
+        let mut ast = Ast::parse("src/**").unwrap();
+        ast.literal("/").one_of(vec![Ast::from_literal("lib.rs"), Ast::parse("*.toml").unwrap()]);
+        let pattern = ast.compile();
+        assert_eq!(pattern, Pattern::new("src/**/@(lib.rs|*.toml)").unwrap());
+        assert!(pattern.matches("src/lib.rs"));
+        assert!(pattern.matches("src/a/b/Cargo.toml"));
+        assert!(!pattern.matches("src/main.rs"));
+
+        assert_eq!(pattern.ast().compile(), pattern);
+        assert_eq!(Ast::new().append(&ast).append(&Ast::from_literal("x")).compile(),
+                   Pattern::new("src/**/@(lib.rs|*.toml)x").unwrap());
+        assert!(Ast::parse("[a-").is_err());
+        assert!(Ast::new().compile().matches(""));
+    }
+    #[test]
+    fn recursive_placement() {
+        let string = Pattern::new("a**b").unwrap_err();
+        let ast = Ast::new().literal("a").recursive().literal("b").try_compile().unwrap_err();
+        assert_eq!(ast.inner, string.inner);
+        assert_eq!(ast.input(), "a**b");
+        assert_eq!(ast.kind(), ErrorKind::IllegalRecursion);
+
+        let err = Ast::new().literal("x/").one_of(vec![Ast::new().literal("a/").recursive().literal("b").clone()]).try_compile().unwrap_err();
+        assert_eq!(err.inner, Error::IllegalRecursion(2));
+        assert_eq!(err.input(), "x/@(a/**b)");
+
+        for p in ["**", "a/**", "**/b", "a/**/b", "@(**|a)/b", "a/**+/b"].iter() {
+            let ast = Ast::parse(p).unwrap();
+            assert_eq!(ast.try_compile().unwrap(), ast.compile(), "{}", p);
+        }
+        assert!(Ast::new().recursive().recursive().try_compile().is_err());
+        assert!(Ast::new().recursive().any_sequence().try_compile().is_err());
+    }
+}
\ No newline at end of file
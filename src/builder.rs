@@ -0,0 +1,567 @@
+use alloc::string::String;
+
+use crate::{Ast, Error, MatchOptions, Pattern};
+use crate::options::{is_any_separator, is_separator};
+use crate::syntax::{Syntax, MAX_NESTING};
+use crate::transform::reorder_alternatives;
+
+/// Builds a [`Pattern`] with non default settings, [`Pattern::new`] is the same as building with none of them set.
+///
+/// Some settings change how the pattern is read, like [`extended`](PatternBuilder::extended) or
+/// [`separators`](PatternBuilder::separators), the others are the [`MatchOptions`] the pattern keeps.
+/// Every method of the pattern that doesn't take options matches with those, like [`Pattern::matches`]
+/// or [`Pattern::captures`], while the ones that do, like [`Pattern::matches_with`], use the options
+/// they are given instead. Methods such as [`Pattern::matches_case_fold`] add their option to the kept ones.
+///
+/// Setting something twice keeps the last value. Where settings overlap:
+/// - [`raw`](PatternBuilder::raw) takes every char literally, so the settings for reading the pattern do nothing,
+///   while separators and match options still apply
+/// - [`separators`](PatternBuilder::separators) wins over the separators of
+///   [`backslash_is_separator`](PatternBuilder::backslash_is_separator), though `\` stays unable to escape
+/// - [`extended`](PatternBuilder::extended) turned off leaves [`postfix_quantifiers`](PatternBuilder::postfix_quantifiers)
+///   working, they don't need the `@(...)` syntax
+/// - [`match_options`](PatternBuilder::match_options) replaces every match option set before it
+/// ```rust
+/// # use globber::PatternBuilder;
+/// let pattern = PatternBuilder::new("std::**::Vec")
+///     .separators(|c| c == ':')
+///     .build()
+///     .unwrap();
+/// assert!(pattern.matches("std::vec::Vec"));
+/// assert!(pattern.matches("std::Vec"));
+///
+/// let pattern = PatternBuilder::new("src/*.RS")
+///     .case_insensitive(true)
+///     .require_literal_separator(true)
+///     .require_literal_leading_dot(true)
+///     .build()
+///     .unwrap();
+/// assert!(pattern.matches("src/main.rs"));
+/// assert!(!pattern.matches("src/bin/main.rs"));
+/// assert!(!pattern.matches("src/.hidden.rs"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PatternBuilder {
+    pattern: String,
+    options: MatchOptions,
+    extended: bool,
+    // the default ones depend on backslash_is_separator
+    is_separator: Option<fn(char) -> bool>,
+    reorder_alternatives: bool,
+    postfix_quantifiers: bool,
+    max_nesting: usize,
+    raw: bool,
+    backslash_is_separator: bool,
+}
+
+impl PatternBuilder {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.into(),
+            options: MatchOptions::new(),
+            extended: true,
+            is_separator: None,
+            reorder_alternatives: false,
+            postfix_quantifiers: false,
+            max_nesting: MAX_NESTING,
+            raw: false,
+            backslash_is_separator: false,
+        }
+    }
+
+    /// Appends to the pattern, which is parsed as a whole by [`build`](PatternBuilder::build), e.g. to extend
+    /// a pattern piece by piece while it is being typed and check it after every piece.
+    ///
+    /// The pieces are joined before parsing, so a pattern like `@(a|b)` can be split anywhere and an escape at the
+    /// end of one piece applies to the first char of the next, `a\` followed by `*` matches `a*`.
+    /// ```rust
+    /// # use globber::PatternBuilder;
+    /// let mut builder = PatternBuilder::new("src/");
+    /// assert!(builder.build().unwrap().matches("src/"));
+    /// let pattern = builder.push_str("*.rs").build().unwrap();
+    /// assert!(pattern.matches("src/main.rs"));
+    /// ```
+    pub fn push_str(&mut self, s: &str) -> &mut Self {
+        self.pattern.push_str(s);
+        self
+    }
+
+    /// Ignores the case of ASCII letters when matching, see [`MatchOptions::case_insensitive`].
+    ///
+    /// Defaults to `false`.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.options.case_insensitive = yes;
+        self
+    }
+
+    /// Keeps `*` and `?` from matching separators, see [`MatchOptions::require_literal_separator`].
+    ///
+    /// Defaults to `false`.
+    pub fn require_literal_separator(&mut self, yes: bool) -> &mut Self {
+        self.options.require_literal_separator = yes;
+        self
+    }
+
+    /// Keeps wildcards and ranges from matching a `.` at the start of a path component,
+    /// see [`MatchOptions::require_literal_leading_dot`].
+    ///
+    /// Defaults to `false`.
+    pub fn require_literal_leading_dot(&mut self, yes: bool) -> &mut Self {
+        self.options.require_literal_leading_dot = yes;
+        self
+    }
+
+    /// Sets the most path components a `**` may match, see [`MatchOptions::max_depth`].
+    ///
+    /// Defaults to no limit.
+    pub fn max_depth(&mut self, depth: usize) -> &mut Self {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets all match options at once, including the ones without a method of their own
+    /// like [`MatchOptions::unanchored_start`].
+    ///
+    /// Defaults to [`MatchOptions::new`].
+    pub fn match_options(&mut self, options: MatchOptions) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    /// Reads patterns like `@(...)` and `!(...)`, otherwise `(`, `)` and `|` are plain chars like in a basic glob,
+    /// so `@(a|b)` only matches itself. Named patterns are not read either.
+    ///
+    /// Defaults to `true`.
+    /// ```rust
+    /// # use globber::PatternBuilder;
+    /// let pattern = PatternBuilder::new("!(a).txt").extended(false).build().unwrap();
+    /// assert!(pattern.matches("!(a).txt"));
+    /// assert!(!pattern.matches("b.txt"));
+    /// ```
+    pub fn extended(&mut self, yes: bool) -> &mut Self {
+        self.extended = yes;
+        self
+    }
+
+    /// Sets which chars separate path components, this decides where `**` is allowed
+    /// and which separators it can skip when matching no segments at all.
+    ///
+    /// Defaults to [`std::path::is_separator`], or `/` and `\` without the `std` feature.
+    pub fn separators(&mut self, is_separator: fn(char) -> bool) -> &mut Self {
+        self.is_separator = Some(is_separator);
+        self
+    }
+
+    /// Tries the alternatives of patterns like `@(...)` that start with a char first and shorter ones
+    /// before longer ones, instead of in the order they are written. This can speed up patterns with many
+    /// alternatives and never changes what matches, only which alternative a capture comes from.
+    ///
+    /// Defaults to `false`.
+    pub fn reorder_alternatives(&mut self, yes: bool) -> &mut Self {
+        self.reorder_alternatives = yes;
+        self
+    }
+
+    /// Reads `?`, `*` and `+` right after a char, range or pattern as zero or one, zero or more
+    /// and one or more of it, like a regex does, e.g. `colou?r` matches `color` and `colour`.
+    ///
+    /// Elsewhere they keep their usual meaning, so `*.rs`, `src/*` and `a/**/b` are unchanged,
+    /// as is `?(...)` and the like. Escape them to match them literally, e.g. `a\?`.
+    ///
+    /// Defaults to `false`.
+    /// ```rust
+    /// # use globber::PatternBuilder;
+    /// let pattern = PatternBuilder::new("[0-9]+.@(tar|tgz)?")
+    ///     .postfix_quantifiers(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(pattern.matches("1.tar"));
+    /// assert!(pattern.matches("12.tar"));
+    /// assert!(pattern.matches("2."));
+    /// assert!(!pattern.matches(".tar"));
+    /// ```
+    pub fn postfix_quantifiers(&mut self, yes: bool) -> &mut Self {
+        self.postfix_quantifiers = yes;
+        self
+    }
+
+    /// Sets how deep patterns like `@(...)` may nest, `!(!(a))` nests two deep.
+    /// Deeper patterns fail to build instead of overflowing the stack, which matters for untrusted patterns.
+    ///
+    /// Defaults to 128.
+    pub fn max_nesting(&mut self, depth: usize) -> &mut Self {
+        self.max_nesting = depth;
+        self
+    }
+
+    /// Takes every char of the pattern literally, so it only matches exactly that string like
+    /// [`Ast::from_literal`] does. This lets a code path that builds patterns handle
+    /// an input that must not be globbed, without escaping it first. It never fails to build.
+    ///
+    /// Defaults to `false`.
+    /// ```rust
+    /// # use globber::PatternBuilder;
+    /// let pattern = PatternBuilder::new("*[1].rs").raw(true).build().unwrap();
+    /// assert!(pattern.matches("*[1].rs"));
+    /// assert!(!pattern.matches("a1.rs"));
+    /// ```
+    pub fn raw(&mut self, yes: bool) -> &mut Self {
+        self.raw = yes;
+        self
+    }
+
+    /// Reads `\` as a path separator instead of an escape, for Windows paths like `C:\Users\**\*.rs`.
+    /// A `` ` `` escapes the char after it instead, like in PowerShell, e.g. `` `* `` matches a literal `*`
+    /// and `` `x41 `` matches `A`. Unless [`separators`](PatternBuilder::separators) are set
+    /// both `/` and `\` are separators, also with the `std` feature on other platforms.
+    ///
+    /// The two can't both work at once, with this a `\` can't escape anything and without it a pattern
+    /// has to write every separator as `\\` or `/`. Matching still compares chars exactly, so `a\b` doesn't match `a/b`
+    /// unless [`MatchOptions::normalize_separators`](crate::MatchOptions::normalize_separators) is set.
+    ///
+    /// Defaults to `false`.
+    /// ```rust
+    /// # use globber::PatternBuilder;
+    /// let pattern = PatternBuilder::new("C:\\Users\\**\\`[1`].rs")
+    ///     .backslash_is_separator(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(pattern.matches("C:\\Users\\me\\src\\[1].rs"));
+    /// assert!(pattern.matches("C:\\Users\\[1].rs"));
+    /// ```
+    pub fn backslash_is_separator(&mut self, yes: bool) -> &mut Self {
+        self.backslash_is_separator = yes;
+        self
+    }
+
+    pub fn build(&self) -> Result<Pattern, Error> {
+        let is_separator = match (self.is_separator, self.backslash_is_separator) {
+            (Some(is_separator), _) => is_separator,
+            (None, true) => is_any_separator,
+            (None, false) => is_separator,
+        };
+        if self.raw {
+            return Ok(self.with_options(Ast::from_literal(&self.pattern).compile_with(is_separator)));
+        }
+
+        let syntax = Syntax {
+            is_separator,
+            postfix: self.postfix_quantifiers,
+            max_nesting: self.max_nesting,
+            escape: if self.backslash_is_separator { '`' } else { '\\' },
+            extended: self.extended,
+        };
+        let mut tokens = syntax.parse(&self.pattern)
+            .map_err(|e| Error::new(e, &self.pattern))?;
+        if self.reorder_alternatives {
+            tokens = reorder_alternatives(&tokens);
+        }
+
+        Ok(self.with_options(Ast::from_tokens(tokens).compile_with(is_separator)))
+    }
+
+    fn with_options(&self, mut pattern: Pattern) -> Pattern {
+        pattern.options = self.options;
+        pattern
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PatternBuilder;
+    use crate::{MatchOptions, Pattern};
+    use crate::syntax::{Error, MAX_NESTING};
+
+    #[test]
+    fn push_str() {
+        let mut builder = PatternBuilder::new("src/");
+        let p = builder.build().unwrap();
+        assert!(p.matches("src/"));
+        assert!(!p.matches("src/main.rs"));
+
+        let p = builder.push_str("*.rs").build().unwrap();
+        assert_eq!(p, PatternBuilder::new("src/*.rs").build().unwrap());
+        assert!(p.matches("src/main.rs"));
+        assert!(!p.matches("src/main.c"));
+
+        // the pieces are parsed together
+        let mut builder = PatternBuilder::new("@(a");
+        assert!(builder.build().is_err());
+        assert!(builder.push_str("|b)").build().unwrap().matches("b"));
+        let p = PatternBuilder::new("a\\").push_str("*").build().unwrap();
+        assert!(p.matches("a*"));
+        assert!(!p.matches("ab"));
+    }
+
+    #[test]
+    fn match_options() {
+        let build = |builder: &mut PatternBuilder| builder.build().unwrap();
+
+        let p = build(PatternBuilder::new("*.RS").case_insensitive(true));
+        assert!(p.matches("main.rs"));
+        assert!(!p.matches_with("main.rs", MatchOptions::new()));
+        assert_ne!(p, Pattern::new("*.RS").unwrap());
+
+        let p = build(PatternBuilder::new("src/*.rs").require_literal_separator(true));
+        assert!(p.matches("src/main.rs"));
+        assert!(!p.matches("src/bin/main.rs"));
+
+        let p = build(PatternBuilder::new("**/*").require_literal_leading_dot(true));
+        assert!(p.matches("src/main.rs"));
+        assert!(!p.matches(".git/config"));
+        assert!(!p.matches("src/.hidden"));
+
+        let p = build(PatternBuilder::new("a/**/b").max_depth(1));
+        assert!(p.matches("a/x/b"));
+        assert!(!p.matches("a/x/y/b"));
+
+        let options = MatchOptions { unanchored_start: true, ..MatchOptions::new() };
+        let p = build(PatternBuilder::new("*.rs").case_insensitive(true).match_options(options));
+        assert!(p.matches("a/b.rs"));
+        assert!(!p.matches("a/b.RS"));
+        let p = build(PatternBuilder::new("*.rs").match_options(options).case_insensitive(true));
+        assert!(p.matches("a/b.RS"));
+
+        // every method without options uses the kept ones
+        let p = build(PatternBuilder::new("@<name>(*).RS").case_insensitive(true));
+        assert_eq!(p.captures_named("main.rs").unwrap()["name"], "main");
+        assert!(p.could_match_prefix("main.r"));
+        assert!(matches!(p.explain_match("main.rs"), crate::MatchExplanation::Matched(_)));
+        assert!(p.matches_chars("main.rs".chars()));
+        let p = build(PatternBuilder::new("*.RS").case_insensitive(true));
+        assert!(p.matches_multiline("main.rs"));
+        let mut stream = crate::StreamMatcher::new(&p).unwrap();
+        stream.feed("main.rs");
+        assert!(stream.finish());
+
+        // raw patterns keep their options too
+        let p = build(PatternBuilder::new("A*").raw(true).case_insensitive(true));
+        assert!(p.matches("a*"));
+        assert!(!p.matches("ab"));
+
+        assert_eq!(build(&mut PatternBuilder::new("src/*.rs")), Pattern::new("src/*.rs").unwrap());
+    }
+
+    #[test]
+    fn extended() {
+        let basic = |p: &str| PatternBuilder::new(p).extended(false).build().unwrap();
+
+        let p = basic("@(a|b)");
+        assert!(p.is_literal());
+        assert!(p.matches("@(a|b)"));
+        assert!(!p.matches("a"));
+        assert!(basic("*(x)").matches("a(x)"));
+        assert!(basic("?(x)").matches("a(x)"));
+        assert!(basic("a|b").matches("a|b"));
+        assert!(basic(")").matches(")"));
+        assert!(basic("@<x>(a)").matches("@<x>(a)"));
+        assert!(PatternBuilder::new(")").build().is_err());
+
+        // the rest of the syntax is unchanged
+        let p = basic("[a-c]*.rs");
+        assert!(p.matches("b_x.rs"));
+        assert!(!p.matches("d.rs"));
+        assert!(PatternBuilder::new("a]").extended(false).build().is_err());
+        let p = PatternBuilder::new("colou?r(s)").extended(false).postfix_quantifiers(true).build().unwrap();
+        assert!(p.matches("color(s)"));
+        assert!(p.matches("colour(s)"));
+    }
+
+    #[test]
+    fn separators() {
+        let p = PatternBuilder::new("foo::**::bar")
+            .separators(|c| c == ':')
+            .build()
+            .unwrap();
+        assert!(p.matches("foo::bar"));
+        assert!(p.matches("foo::a::bar"));
+        assert!(p.matches("foo::a::b::c::bar"));
+        assert!(!p.matches("foo::a::baz"));
+        assert!(!p.matches("foo/a/bar"));
+
+        let p = PatternBuilder::new("com.**.Main")
+            .separators(|c| c == '.')
+            .build()
+            .unwrap();
+        assert!(p.matches("com.Main"));
+        assert!(p.matches("com.example.app.Main"));
+        assert!(!p.matches("org.example.Main"));
+
+        let p = PatternBuilder::new("**::test")
+            .separators(|c| c == ':')
+            .build()
+            .unwrap();
+        assert!(p.matches("test"));
+        assert!(p.matches("a::b::test"));
+    }
+
+    #[test]
+    fn reorder_alternatives() {
+        let p = PatternBuilder::new("src/+(*.c|[ab]|main.rs|a)/@<x>(**|lib)")
+            .reorder_alternatives(true)
+            .build()
+            .unwrap();
+        assert_eq!(p, PatternBuilder::new("src/+(a|main.rs|[ab]|*.c)/@<x>(lib|**)").build().unwrap());
+
+        let original = PatternBuilder::new("src/+(*.c|[ab]|main.rs|a)/@<x>(**|lib)").build().unwrap();
+        for input in ["src/main.rs/lib", "src/a/x/y", "src/x.c/lib", "src/b/", "src/c/lib", "src/main.rsa/lib"].iter() {
+            assert_eq!(p.matches(input), original.matches(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn postfix_quantifiers() {
+        let build = |p: &str| PatternBuilder::new(p).postfix_quantifiers(true).build().unwrap();
+
+        let p = build("colou?r");
+        assert!(p.matches("color"));
+        assert!(p.matches("colour"));
+        assert!(!p.matches("colouur"));
+        assert!(!p.matches("colou?r"));
+
+        let p = build("v[0-9]+.@(rs|toml)?");
+        assert!(p.matches("v1.rs"));
+        assert!(p.matches("v2."));
+        assert!(p.matches("v10.toml"));
+        assert!(!p.matches("v.rs"));
+        assert!(!p.matches("v1.c"));
+
+        let p = build("ab*c");
+        assert!(p.matches("ac"));
+        assert!(p.matches("abc"));
+        assert!(p.matches("abbbc"));
+        assert!(!p.matches("axc"));
+
+        // as many times as it takes, giving some back to what follows
+        let p = build("ab+");
+        assert!(p.matches("abbb"));
+        assert!(!p.matches("a"));
+        let p = build("v[0-9]+");
+        assert!(p.matches("v10"));
+        assert!(!p.matches("v10a"));
+        let p = build("[0-9]+.@(tar|tgz)?");
+        assert!(p.matches("12.tar"));
+        assert!(p.matches("123."));
+        let p = build("@(ab)+b");
+        assert!(p.matches("ababb"));
+        assert!(!p.matches("abab"));
+
+        // wildcards everywhere else
+        let p = build("?src/**/*.rs");
+        assert!(p.matches("_src/a/b/main.rs"));
+        assert!(!p.matches("src/main.rs"));
+        let p = build("a/?");
+        assert!(p.matches("a/b"));
+        assert!(!p.matches("a"));
+
+        // prefix patterns and escapes keep their meaning
+        let p = build("a?(b)\\?");
+        assert!(p.matches("a?"));
+        assert!(p.matches("ab?"));
+        assert!(!p.matches("a"));
+        assert!(build("+").matches("+"));
+
+        assert_eq!(build("colou?r"), PatternBuilder::new("colo?(u)r").build().unwrap());
+        assert!(!PatternBuilder::new("colou?r").build().unwrap().matches("color"));
+    }
+
+    #[test]
+    fn raw() {
+        let raw = |p: &str| PatternBuilder::new(p).raw(true).build().unwrap();
+
+        for p in ["*", "**", "?", "[a-z]", "[!a]", "@(a|b)", "!(a)", "\\*", "\\x41", "a**b", "@(", "[", "\\", ""].iter() {
+            let pattern = raw(p);
+            assert!(pattern.is_literal(), "{}", p);
+            assert!(pattern.matches(p), "{}", p);
+        }
+        assert!(!raw("*").matches("a"));
+        assert!(!raw("?").matches("a"));
+        assert!(!raw("[a-z]").matches("a"));
+        assert!(!raw("@(a|b)").matches("a"));
+        assert!(!raw("\\x41").matches("A"));
+
+        // the other settings still apply
+        let p = PatternBuilder::new("a:b").separators(|c| c == ':').raw(true).build().unwrap();
+        assert_eq!(p.depth(), Some(1));
+        assert!(PatternBuilder::new("*").raw(true).raw(false).build().unwrap().matches("a"));
+    }
+
+    #[test]
+    fn backslash_is_separator() {
+        let windows = |p: &str| PatternBuilder::new(p).backslash_is_separator(true).build().unwrap();
+
+        let p = windows("a\\b");
+        assert!(p.matches("a\\b"));
+        assert!(!p.matches("ab"));
+        assert!(!p.matches("a/b"));
+        assert_eq!(p.depth(), Some(1));
+        assert!(!PatternBuilder::new("a\\b").build().unwrap().matches("a\\b"));
+
+        let mut options = MatchOptions::new();
+        options.normalize_separators = true;
+        assert!(p.matches_with("a/b", options));
+
+        let p = windows("C:\\Users\\**\\*.rs");
+        assert!(p.matches("C:\\Users\\main.rs"));
+        assert!(p.matches("C:\\Users\\me\\src\\main.rs"));
+        assert!(!p.matches("C:\\Users\\main.c"));
+        assert!(windows("src\\**").matches("src\\a/b"));
+
+        // ` escapes instead
+        let p = windows("`*`x41`\\[`]]");
+        assert!(p.matches("*A\\]"));
+        assert!(!p.matches("xA\\]"));
+        assert!(windows("``").matches("`"));
+        assert!(windows("@(a`|b|c)").matches("a|b"));
+        assert_eq!(PatternBuilder::new("a`").backslash_is_separator(true).build().unwrap_err().inner, Error::IllegalEscape(1));
+        assert!(PatternBuilder::new("a`").build().unwrap().matches("a`"));
+
+        // separators set explicitly win
+        let p = PatternBuilder::new("a:**:b").backslash_is_separator(true).separators(|c| c == ':').build().unwrap();
+        assert!(p.matches("a:b"));
+        assert!(PatternBuilder::new("a\\**").separators(|c| c == ':').backslash_is_separator(true).build().is_err());
+        assert!(PatternBuilder::new("a\\**").backslash_is_separator(true).backslash_is_separator(false).build().unwrap().matches("a*x"));
+    }
+
+    #[test]
+    fn max_nesting() {
+        let nested = |depth: usize| "!(".repeat(depth) + "a" + &")".repeat(depth);
+
+        assert!(PatternBuilder::new(&nested(128)).build().is_ok());
+        let err = PatternBuilder::new(&nested(129)).build().unwrap_err();
+        assert_eq!(err.inner, Error::NestedTooDeep(256));
+
+        let err = PatternBuilder::new(&nested(1000)).build().unwrap_err();
+        assert_eq!(err.inner, Error::NestedTooDeep(256));
+
+        // patterns right at the limit still build and match quickly
+        let start = std::time::Instant::now();
+        for open in ["@(", "!(", "?(", "+(", "*(", "@(b|", "?(*|", "@<x>("].iter() {
+            let pattern = open.repeat(MAX_NESTING) + "a" + &")".repeat(MAX_NESTING);
+            let p = PatternBuilder::new(&pattern).build().unwrap();
+            p.matches("a");
+            p.matches("ba*");
+            assert!(PatternBuilder::new(&(open.to_string() + &pattern + ")")).build().is_err(), "{}", open);
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        let p = PatternBuilder::new("@(a|@<x>(b|?(c)))").max_nesting(2).build().unwrap_err();
+        assert_eq!(p.inner, Error::NestedTooDeep(11));
+        assert!(PatternBuilder::new("@(a|@<x>(b|?(c)))").max_nesting(3).build().is_ok());
+        assert!(PatternBuilder::new("@(a)@(b)").max_nesting(1).build().is_ok());
+        assert_eq!(PatternBuilder::new("@(a)").max_nesting(0).build().unwrap_err().inner, Error::NestedTooDeep(0));
+        assert!(PatternBuilder::new("a*b?[c]").max_nesting(0).build().is_ok());
+    }
+
+    #[test]
+    fn separator_errors() {
+        let err = PatternBuilder::new("foo/**/bar")
+            .separators(|c| c == ':')
+            .build()
+            .unwrap_err();
+        assert_eq!(err.inner, Error::IllegalRecursion(3));
+        assert_eq!(err.input, "foo/**/bar");
+
+        assert!(PatternBuilder::new("foo/**/bar").build().is_ok());
+    }
+}
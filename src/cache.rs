@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::{Error, Pattern};
+
+/// Keeps compiled patterns around by their source so patterns that come up again aren't parsed again.
+///
+/// Holds at most `capacity` patterns, once full the least recently used one is dropped.
+/// ```rust
+/// # use globber::PatternCache;
+/// let mut cache = PatternCache::new(64);
+/// for input in ["main.rs", "lib.rs"].iter() {
+///     assert!(cache.get_or_compile("*.rs").unwrap().matches(input));
+/// }
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PatternCache {
+    capacity: usize,
+    // the pattern and when it was last used
+    patterns: HashMap<String, (Pattern, u64)>,
+    clock: u64,
+}
+
+impl PatternCache {
+    /// Creates a cache holding up to `capacity` patterns, it always holds at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            patterns: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the pattern compiled from `src`, compiling it first if it isn't cached.
+    /// Patterns that fail to compile are not cached.
+    pub fn get_or_compile(&mut self, src: &str) -> Result<&Pattern, Error> {
+        self.clock += 1;
+
+        if !self.patterns.contains_key(src) {
+            let pattern = Pattern::new(src)?;
+            if self.patterns.len() >= self.capacity {
+                self.evict();
+            }
+            self.patterns.insert(src.into(), (pattern, 0));
+        }
+
+        let entry = self.patterns.get_mut(src).unwrap();
+        entry.1 = self.clock;
+        Ok(&entry.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.patterns.clear();
+    }
+
+    // drops the least recently used pattern
+    fn evict(&mut self) {
+        let oldest = self.patterns.iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(src, _)| src.clone());
+        if let Some(src) = oldest {
+            self.patterns.remove(&src);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PatternCache;
+    use crate::Pattern;
+
+    #[test]
+    fn hits() {
+        let mut cache = PatternCache::new(2);
+        let first = cache.get_or_compile("*.rs").unwrap() as *const Pattern;
+        let second = cache.get_or_compile("*.rs").unwrap() as *const Pattern;
+        assert_eq!(first, second);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get_or_compile("*.rs").unwrap(), &Pattern::new("*.rs").unwrap());
+
+        assert!(cache.get_or_compile("[a-").is_err());
+        assert_eq!(cache.len(), 1);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn eviction() {
+        let mut cache = PatternCache::new(2);
+        cache.get_or_compile("a").unwrap();
+        cache.get_or_compile("b").unwrap();
+        // a is now more recent than b
+        cache.get_or_compile("a").unwrap();
+        cache.get_or_compile("c").unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.patterns.contains_key("a"));
+        assert!(!cache.patterns.contains_key("b"));
+        assert!(cache.patterns.contains_key("c"));
+
+        let mut cache = PatternCache::new(0);
+        assert_eq!(cache.capacity(), 1);
+        cache.get_or_compile("a").unwrap();
+        assert!(cache.get_or_compile("b").unwrap().matches("b"));
+        assert_eq!(cache.len(), 1);
+    }
+}
\ No newline at end of file
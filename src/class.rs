@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::Error;
+use crate::matcher::{match_specifiers, Status};
+use crate::syntax::{CharSpecifier, parse_class, Token};
+
+/// A single bracket expression such as `[a-z0-9_]` or `[!abc]`.
+///
+/// It uses the same syntax and rules as ranges inside a [`Pattern`](crate::Pattern)
+/// and can be used to test single characters.
+/// ```rust
+/// # use globber::CharClass;
+/// let class = CharClass::new("[a-z_]").unwrap();
+/// assert!(class.contains('a'));
+/// assert!(class.contains('_'));
+/// assert!(!class.contains('A'));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CharClass {
+    negated: bool,
+    specifiers: Vec<CharSpecifier>,
+}
+
+impl CharClass {
+    pub fn new(class: &str) -> Result<Self, Error> {
+        class.parse()
+    }
+
+    /// Returns true if `c` is allowed by the class.
+    pub fn contains(&self, c: char) -> bool {
+        (match_specifiers(&self.specifiers, c) == Status::Match) != self.negated
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// The chars and ranges of the class, sorted and with overlapping ones merged,
+    /// so `[ca-b]` has the single range `a-c`. A negated class holds the chars it excludes.
+    pub fn specifiers(&self) -> &[CharSpecifier] {
+        &self.specifiers
+    }
+
+    pub(crate) fn to_token(&self) -> Token {
+        if self.negated {
+            Token::NotAnyOf(self.specifiers.clone())
+        } else {
+            Token::AnyOf(self.specifiers.clone())
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let token = parse_class(s)
+            .map_err(|e| Error::new(e, s))?;
+
+        match token {
+            Token::AnyOf(specifiers) => Ok(Self { negated: false, specifiers }),
+            Token::NotAnyOf(specifiers) => Ok(Self { negated: true, specifiers }),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CharClass;
+    use crate::CharSpecifier;
+    use crate::syntax::Error;
+
+    #[test]
+    fn contains() {
+        let class = CharClass::new("[a-z0-9_]").unwrap();
+        assert!(!class.is_negated());
+        for c in "abcxyz0189_".chars() {
+            assert!(class.contains(c));
+        }
+        for c in "ABZ-/. ".chars() {
+            assert!(!class.contains(c));
+        }
+
+        let class = CharClass::new("[abc-]").unwrap();
+        assert!(class.contains('a'));
+        assert!(class.contains('-'));
+        assert!(!class.contains('d'));
+    }
+
+    #[test]
+    fn large() {
+        let class = CharClass::new("[acegikmoqsuwy0-2A-CX-Z_~]").unwrap();
+        let expected = |c: char| "acegikmoqsuwy012ABCXYZ_~".contains(c);
+        for c in (0u8..128).map(char::from) {
+            assert_eq!(class.contains(c), expected(c), "{}", c);
+        }
+        assert!(!class.contains('é'));
+    }
+
+    #[test]
+    fn negated() {
+        let class = CharClass::new("[!a-z]").unwrap();
+        assert!(class.is_negated());
+        assert!(!class.contains('a'));
+        assert!(!class.contains('m'));
+        assert!(!class.contains('z'));
+        assert!(class.contains('A'));
+        assert!(class.contains('0'));
+        assert!(class.contains('_'));
+    }
+
+    #[test]
+    fn specifiers() {
+        let class = CharClass::new("[_ca-b0-9]").unwrap();
+        assert_eq!(class.specifiers(), &[CharSpecifier::Range('0', '9'), CharSpecifier::Char('_'), CharSpecifier::Range('a', 'c')]);
+        for c in "09_abc".chars() {
+            assert!(class.specifiers().iter().any(|s| s.matches(c)), "{}", c);
+        }
+
+        let char = CharSpecifier::Char('é');
+        assert!(char.matches('é'));
+        assert!(!char.matches('e'));
+        assert!(!char.matches('É'));
+
+        let range = CharSpecifier::Range('\0', char::MAX);
+        assert!(range.matches('\0'));
+        assert!(range.matches('a'));
+        assert!(range.matches(char::MAX));
+
+        let range = CharSpecifier::Range('b', 'd');
+        assert!(range.matches('b'));
+        assert!(range.matches('d'));
+        assert!(!range.matches('a'));
+        assert!(!range.matches('e'));
+        assert!(!range.matches('B'));
+        assert!(!CharSpecifier::Range('d', 'b').matches('c'));
+        assert!(CharSpecifier::Range('x', 'x').matches('x'));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(CharClass::new("").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(CharClass::new("[]").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(CharClass::new("[!]").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(CharClass::new("[a-z").unwrap_err().inner, Error::UnclosedRange(0));
+        assert_eq!(CharClass::new("[").unwrap_err().inner, Error::UnclosedRange(0));
+        assert_eq!(CharClass::new("a-z").unwrap_err().inner, Error::IllegalChar(0));
+        assert_eq!(CharClass::new("[a-z]b").unwrap_err().inner, Error::IllegalChar(5));
+        assert_eq!(CharClass::new("[a(]").unwrap_err().inner, Error::IllegalChar(2));
+    }
+}
@@ -0,0 +1,305 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::Range;
+
+use crate::syntax::{CharSpecifier, Token};
+
+/// Why an input did or didn't match a pattern, see [`Pattern::explain_match`](crate::Pattern::explain_match).
+///
+/// Tokens are counted at the top level of the pattern, every char, `?`, `*`, `**`, range
+/// and pattern like `@(...)` is one token, so `src/*.@(rs|c)` has 7 of them.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum MatchExplanation {
+    /// The input matched, with the byte range of the input each token consumed.
+    Matched(Vec<Range<usize>>),
+    /// The input didn't match, with the token that failed and the byte offset of the input it failed at.
+    /// Out of every way the pattern was tried this is the one that got the furthest into the pattern,
+    /// and then into the input. No token means the whole pattern matched but input was left over.
+    Failed {
+        token: Option<usize>,
+        offset: usize,
+    },
+}
+
+/// How much work matching an input took, see [`Pattern::matches_counted`](crate::Pattern::matches_counted).
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default)]
+pub struct MatchMetrics {
+    /// Every time a token was compared against the input, including tokens inside patterns like `@(...)`.
+    pub token_visits: usize,
+    /// Every attempt at matching the rest of the pattern that failed, after which the next way was tried.
+    pub backtracks: usize,
+    /// The most attempts nested into each other, each wildcard or pattern that is tried adds one.
+    pub max_depth: usize,
+}
+
+/// The index of a top level token of a pattern, counted like in [`MatchExplanation`].
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct TokenId(pub usize);
+
+/// Pairs the non empty spans of a match with the token that consumed them.
+pub(crate) fn trace(explanation: MatchExplanation) -> Option<Vec<(Range<usize>, TokenId)>> {
+    match explanation {
+        MatchExplanation::Matched(spans) => Some(spans.into_iter()
+            .enumerate()
+            .filter(|(_, span)| !span.is_empty())
+            .map(|(i, span)| (span, TokenId(i)))
+            .collect()),
+        MatchExplanation::Failed { .. } => None,
+    }
+}
+
+/// Renders tokens as an indented tree, one token per line with runs of chars joined into a single literal.
+pub(crate) fn explain(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    write_tokens(&mut out, tokens, "", "");
+    out
+}
+
+// the first line of tokens starts with first, every other line with indent
+fn write_tokens(out: &mut String, tokens: &[Token], first: &str, indent: &str) {
+    let mut prefix = first;
+    let mut i = 0;
+    while i < tokens.len() {
+        out.push_str(prefix);
+        prefix = indent;
+
+        let literal = tokens[i..].iter().take_while(|t| matches!(t, Token::Char(_))).count();
+        if literal > 0 {
+            let chars: String = tokens[i..i + literal].iter()
+                .filter_map(|t| match t {
+                    Token::Char(c) => Some(*c),
+                    _ => None,
+                })
+                .collect();
+            let _ = writeln!(out, "literal {:?}", chars);
+            i += literal;
+            continue;
+        }
+
+        let (token, name) = match &tokens[i] {
+            Token::Named(name, pattern) => (&**pattern, Some(name)),
+            t => (t, None),
+        };
+
+        let patterns = match token {
+            Token::AnyChar => {
+                out.push_str("any char (?)\n");
+                None
+            }
+            Token::AnySequence => {
+                out.push_str("any sequence (*)\n");
+                None
+            }
+            Token::AnyRecursive => {
+                out.push_str("any directories (**)\n");
+                None
+            }
+            Token::AtLeastOneSegment => {
+                out.push_str("at least one directory (**+)\n");
+                None
+            }
+            Token::AnyOf(s) => {
+                let _ = writeln!(out, "any of {}", class(s, ""));
+                None
+            }
+            Token::NotAnyOf(s) => {
+                let _ = writeln!(out, "none of {}", class(s, "!"));
+                None
+            }
+            Token::ZeroOrOne(p) => {
+                out.push_str("zero or one of");
+                Some(p)
+            }
+            Token::ZeroOrMore(p) => {
+                out.push_str("zero or more of");
+                Some(p)
+            }
+            Token::OneOrMore(p) => {
+                out.push_str("one or more of");
+                Some(p)
+            }
+            Token::ExactlyOne(p) => {
+                out.push_str("exactly one of");
+                Some(p)
+            }
+            Token::NoneOf(p) => {
+                out.push_str("anything except");
+                Some(p)
+            }
+            Token::Char(_) | Token::Named(..) => unreachable!(),
+        };
+        if patterns.is_some() {
+            if let Some(name) = name {
+                let _ = write!(out, " as <{}>", name);
+            }
+            out.push('\n');
+        }
+
+        // every alternative is a bullet with its tokens lined up below it
+        let first = String::from(indent) + "  - ";
+        let nested = String::from(indent) + "    ";
+        for pattern in patterns.into_iter().flatten() {
+            write_tokens(out, pattern, &first, &nested);
+        }
+        i += 1;
+    }
+}
+
+/// Writes tokens back in glob syntax, escaping chars that would mean something else.
+pub(crate) fn render(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        render_token(&mut out, token, None);
+    }
+    out
+}
+
+fn render_token(out: &mut String, token: &Token, name: Option<&str>) {
+    let (op, patterns) = match token {
+        Token::AnyChar => return out.push('?'),
+        Token::AnySequence => return out.push('*'),
+        Token::AnyRecursive => return out.push_str("**"),
+        Token::AtLeastOneSegment => return out.push_str("**+"),
+        Token::AnyOf(s) => return out.push_str(&class(s, "")),
+        Token::NotAnyOf(s) => return out.push_str(&class(s, "!")),
+        Token::Char(c) => {
+            if let '\\' | '*' | '?' | '[' | ']' | '(' | ')' | '|' | '!' | '@' | '+' = c {
+                out.push('\\');
+            }
+            return out.push(*c);
+        }
+        Token::Named(name, pattern) => return render_token(out, pattern, Some(name)),
+        Token::ZeroOrOne(p) => ('?', p),
+        Token::ZeroOrMore(p) => ('*', p),
+        Token::OneOrMore(p) => ('+', p),
+        Token::ExactlyOne(p) => ('@', p),
+        Token::NoneOf(p) => ('!', p),
+    };
+
+    out.push(op);
+    if let Some(name) = name {
+        let _ = write!(out, "<{}>", name);
+    }
+    out.push('(');
+    for (i, pattern) in patterns.iter().enumerate() {
+        if i > 0 {
+            out.push('|');
+        }
+        for token in pattern {
+            render_token(out, token, None);
+        }
+    }
+    out.push(')');
+}
+
+// writes the specifiers back in glob syntax, e.g. [!a-z_]
+fn class(specifiers: &[CharSpecifier], negation: &str) -> String {
+    let mut out = String::from("[");
+    out.push_str(negation);
+    // ranges that are reversed leave none behind, [] wouldn't parse but one of them does
+    if specifiers.is_empty() {
+        out.push_str("z-a");
+    }
+    for specifier in specifiers {
+        match *specifier {
+            CharSpecifier::Char(c) => escape(&mut out, c),
+            CharSpecifier::Range(start, end) => {
+                escape(&mut out, start);
+                out.push('-');
+                escape(&mut out, end);
+            }
+        }
+    }
+    out.push(']');
+    out
+}
+
+fn escape(out: &mut String, c: char) {
+    if let '[' | ']' | '-' | '!' | '\\' | '(' | ')' | '|' = c {
+        out.push('\\');
+    }
+    out.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatchExplanation::{Failed, Matched};
+    use super::TokenId;
+    use crate::Pattern;
+
+    #[test]
+    fn explain() {
+        let p = Pattern::new("src/**/!(*.rs|mod.[!a-z\\-]|@(a|b?)*)").unwrap();
+        assert_eq!(p.explain(), "\
+literal \"src/\"
+any directories (**)
+literal \"/\"
+anything except
+  - any sequence (*)
+    literal \".rs\"
+  - literal \"mod.\"
+    none of [!\\-a-z]
+  - exactly one of
+      - literal \"a\"
+      - literal \"b\"
+        any char (?)
+    any sequence (*)
+");
+
+        assert_eq!(Pattern::new("").unwrap().explain(), "");
+        assert_eq!(Pattern::new("a\\*[]x]").unwrap().explain(), "literal \"a*\"\nany of [\\]x]\n");
+    }
+    #[test]
+    fn explain_match() {
+        let p = Pattern::new("a?c").unwrap();
+        assert_eq!(p.explain_match("abc"), Matched(vec![0..1, 1..2, 2..3]));
+        assert_eq!(p.explain_match("abbc"), Failed { token: Some(2), offset: 2 });
+        assert_eq!(p.explain_match("ac"), Failed { token: Some(2), offset: 2 });
+        assert_eq!(p.explain_match("xbc"), Failed { token: Some(0), offset: 0 });
+        assert_eq!(p.explain_match("abcd"), Failed { token: None, offset: 3 });
+        assert!(!p.matches("abbc"));
+        assert!(!p.matches("ac"));
+
+        // a ** matching nothing skips the separator after it
+        let p = Pattern::new("**/x").unwrap();
+        assert_eq!(p.explain_match("x"), Matched(vec![0..0, 0..0, 0..1]));
+        assert_eq!(p.explain_match("a/b/x"), Matched(vec![0..3, 3..4, 4..5]));
+
+        // failures inside a pattern are reported on the whole pattern
+        let p = Pattern::new("src/@(lib|main).rs").unwrap();
+        assert_eq!(p.explain_match("src/main.rs"), Matched(vec![0..1, 1..2, 2..3, 3..4, 4..8, 8..9, 9..10, 10..11]));
+        assert_eq!(p.explain_match("src/mod.rs"), Failed { token: Some(4), offset: 5 });
+        assert_eq!(p.explain_match("src/lib.c"), Failed { token: Some(6), offset: 8 });
+
+        // offsets count bytes
+        let p = Pattern::new("é*ü").unwrap();
+        assert_eq!(p.explain_match("éaü"), Matched(vec![0..2, 2..3, 3..5]));
+        assert_eq!(p.explain_match("éé"), Failed { token: Some(2), offset: 4 });
+    }
+    #[test]
+    fn match_trace() {
+        let p = Pattern::new("src/*.rs").unwrap();
+        let trace = p.match_trace("src/main.rs").unwrap();
+        let expected = [(0..1, 0), (1..2, 1), (2..3, 2), (3..4, 3), (4..8, 4), (8..9, 5), (9..10, 6), (10..11, 7)];
+        assert_eq!(trace, expected.iter().map(|(r, t)| (r.clone(), TokenId(*t))).collect::<Vec<_>>());
+        assert_eq!(p.match_trace("src/main.c"), None);
+
+        // the * first tries to take everything, the trace is the assignment that matched in the end
+        let p = Pattern::new("*a*").unwrap();
+        assert_eq!(p.match_trace("bab"), Some(vec![(0..1, TokenId(0)), (1..2, TokenId(1)), (2..3, TokenId(2))]));
+
+        // tokens that consumed nothing are left out
+        let p = Pattern::new("**/x").unwrap();
+        assert_eq!(p.match_trace("x"), Some(vec![(0..1, TokenId(2))]));
+        assert_eq!(Pattern::new("*").unwrap().match_trace(""), Some(vec![]));
+    }
+    #[test]
+    fn render() {
+        for p in ["", "src/**/*.rs", "a/**+/b", "[!a-c\\]]?", "\\*\\(a\\|", "@<x>(a|*(b|c))!(?(d))+(e)", "a@b!c+d", "\\\\x41"].iter() {
+            let pattern = Pattern::new(p).unwrap();
+            let rendered = super::render(pattern.matcher.tokens());
+            assert_eq!(Pattern::new(&rendered).unwrap(), pattern, "{} {}", p, rendered);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,416 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use crate::{Error, MatchOptions, Pattern};
+
+/// A single line of a `.gitignore` file, see [`Pattern::gitignore`].
+///
+/// It follows git's rules instead of the shell's:
+/// * a pattern without a `/` except at the end matches at any depth, otherwise it is relative to the `.gitignore`
+/// * a leading `/` only anchors the pattern, `/build` matches `build` but not `src/build`
+/// * a trailing `/` only matches directories
+/// * `*` never matches a `/` and `**` is only special as a whole path component, elsewhere it is a `*`
+/// * a leading `!` negates the pattern, see [`GitignoreSet`]
+/// * trailing spaces are ignored unless escaped with `\`
+///
+/// Paths are matched relative to the directory of the `.gitignore` file and use `/` as the separator.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct GitignorePattern {
+    pattern: Pattern,
+    negated: bool,
+}
+
+impl GitignorePattern {
+    pub fn new(line: &str) -> Result<Self, Error> {
+        let (glob, negated) = translate(line);
+        Ok(Self {
+            pattern: Pattern::new(&glob)?,
+            negated,
+        })
+    }
+
+    /// The glob the line was translated to.
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// True when the line started with `!`, meaning it re-includes whatever it matches.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    pub fn is_dir_only(&self) -> bool {
+        self.pattern.is_dir_only()
+    }
+
+    /// Returns true if the pattern matches the path, ignoring negation.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        let mut options = MatchOptions::new();
+        options.require_literal_separator = true;
+
+        if self.pattern.is_dir_only() {
+            is_dir && self.pattern.matcher.matches_dir(path.trim_end_matches('/').chars(), &options)
+        } else {
+            self.pattern.matches_with(path, options)
+        }
+    }
+}
+
+/// The lines of a `.gitignore` file, deciding which paths are ignored.
+///
+/// The last pattern matching a path wins, so a negated pattern can re-include a path an earlier one ignored.
+/// Like git, nothing inside an ignored directory can be re-included.
+/// ```rust
+/// # use globber::GitignoreSet;
+/// let set = GitignoreSet::from_lines("# logs\n*.log\n!important.log\ntarget/".lines()).unwrap();
+/// assert!(set.is_ignored("debug.log", false));
+/// assert!(!set.is_ignored("important.log", false));
+/// assert!(set.is_ignored("target", true));
+/// assert!(set.is_ignored("target/debug/app", false));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GitignoreSet {
+    patterns: Vec<GitignorePattern>,
+}
+
+impl GitignoreSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the lines of a `.gitignore` file skipping blank lines and comments,
+    /// on failure the error reports the line that failed starting at 1.
+    pub fn from_lines<I, S>(lines: I) -> Result<Self, Error>
+        where I: IntoIterator<Item=S>,
+              S: AsRef<str>,
+    {
+        let mut set = Self::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let pattern = GitignorePattern::new(line)
+                .map_err(|mut e| {
+                    e.line = Some(i + 1);
+                    e
+                })?;
+            set.insert(pattern);
+        }
+        Ok(set)
+    }
+
+    pub fn insert(&mut self, pattern: GitignorePattern) {
+        self.patterns.push(pattern);
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns true if the path or any directory it is in is ignored.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        if parents(path).any(|parent| self.decide(parent, true) == Some(true)) {
+            return true;
+        }
+
+        self.decide(path, is_dir).unwrap_or(false)
+    }
+
+    // whether the last matching pattern ignores the path, if any matches
+    fn decide(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.patterns.iter()
+            .rev()
+            .find(|p| p.matches(path, is_dir))
+            .map(|p| !p.negated)
+    }
+}
+
+/// Ordered include and exclude rules made of patterns, deciding which paths are included.
+///
+/// Like a [`GitignoreSet`] the last rule matching a path wins, a rule excludes what it matches
+/// and a negated rule includes it again. A path no rule matches is included, and so is nothing inside
+/// an excluded directory. Unlike a `.gitignore` the patterns match like they do on their own,
+/// a [dir only](Pattern::is_dir_only) one only matching directories. Paths use `/` as the separator.
+/// ```rust
+/// # use globber::{Pattern, RuleSet};
+/// let rules: RuleSet = vec![
+///     (Pattern::new("**/*.log").unwrap(), false),
+///     (Pattern::new("**/keep.log").unwrap(), true),
+///     (Pattern::new("target/").unwrap(), false),
+/// ].into_iter().collect();
+/// assert!(!rules.is_included("logs/debug.log", false));
+/// assert!(rules.is_included("logs/keep.log", false));
+/// assert!(!rules.is_included("target/keep.log", false));
+/// assert!(rules.is_included("src/main.rs", false));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct RuleSet {
+    // (pattern, negated)
+    rules: Vec<(Pattern, bool)>,
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule after every other one, a negated rule includes what it matches.
+    pub fn insert(&mut self, pattern: Pattern, negated: bool) {
+        self.rules.push((pattern, negated));
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns true unless the path or any directory it is in is excluded.
+    pub fn is_included(&self, path: &str, is_dir: bool) -> bool {
+        if parents(path).any(|parent| self.decide(parent, true) == Some(false)) {
+            return false;
+        }
+
+        self.decide(path, is_dir).unwrap_or(true)
+    }
+
+    // whether the last matching rule includes the path, if any matches
+    fn decide(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.rules.iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches_path_kind(path, is_dir))
+            .map(|&(_, negated)| negated)
+    }
+}
+
+impl FromIterator<(Pattern, bool)> for RuleSet {
+    fn from_iter<T: IntoIterator<Item=(Pattern, bool)>>(iter: T) -> Self {
+        let mut rules = Self::new();
+        rules.extend(iter);
+        rules
+    }
+}
+
+impl Extend<(Pattern, bool)> for RuleSet {
+    fn extend<T: IntoIterator<Item=(Pattern, bool)>>(&mut self, iter: T) {
+        for (pattern, negated) in iter {
+            self.insert(pattern, negated);
+        }
+    }
+}
+
+// every directory the path is in, outermost first
+fn parents(path: &str) -> impl Iterator<Item=&str> {
+    path.char_indices()
+        .filter(|&(i, c)| c == '/' && i > 0)
+        .map(move |(i, _)| &path[..i])
+}
+
+// turns a line into a glob following git's rules, returning it and whether it was negated
+fn translate(line: &str) -> (String, bool) {
+    let mut line = line;
+    while line.ends_with(' ') && !line[..line.len() - 1].ends_with('\\') {
+        line = &line[..line.len() - 1];
+    }
+
+    let negated = line.starts_with('!');
+    if negated {
+        line = &line[1..];
+    }
+
+    let dir_only = line.ends_with('/');
+    if dir_only {
+        line = &line[..line.len() - 1];
+    }
+
+    let mut glob = String::with_capacity(line.len() + 4);
+    if line.starts_with('/') {
+        line = &line[1..];
+    } else if !line.contains('/') {
+        glob.push_str("**/");
+    }
+
+    // only a ** making up a whole component is special, any other run of * is a single *
+    let chars: Vec<char> = line.chars().collect();
+    let mut escaped = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if escaped || c != '*' {
+            escaped = !escaped && c == '\\';
+            glob.push(c);
+            i += 1;
+            continue;
+        }
+
+        let run = chars[i..].iter().take_while(|c| **c == '*').count();
+        let component = (i == 0 || chars[i - 1] == '/') && matches!(chars.get(i + run), None | Some('/'));
+        glob.push_str(if run > 1 && component { "**" } else { "*" });
+        i += run;
+    }
+
+    if dir_only {
+        glob.push('/');
+    }
+    (glob, negated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{translate, GitignorePattern, GitignoreSet, RuleSet};
+    use crate::{Pattern, PatternBuilder};
+    use crate::syntax::Error;
+
+    fn matches(line: &str, path: &str, is_dir: bool) -> bool {
+        Pattern::gitignore(line).unwrap().matches(path, is_dir)
+    }
+
+    #[test]
+    fn translation() {
+        assert_eq!(translate("*.log"), ("**/*.log".into(), false));
+        assert_eq!(translate("!*.log"), ("**/*.log".into(), true));
+        assert_eq!(translate("/build"), ("build".into(), false));
+        assert_eq!(translate("build/"), ("**/build/".into(), false));
+        assert_eq!(translate("doc/*.txt"), ("doc/*.txt".into(), false));
+        assert_eq!(translate("a**b/***/c"), ("a*b/**/c".into(), false));
+        assert_eq!(translate("foo  "), ("**/foo".into(), false));
+        assert_eq!(translate("foo\\ "), ("**/foo\\ ".into(), false));
+        assert_eq!(translate("\\!x"), ("**/\\!x".into(), false));
+        assert_eq!(translate("a\\**b"), ("**/a\\**b".into(), false));
+    }
+
+    // the examples from the gitignore documentation
+    #[test]
+    fn documented() {
+        assert!(matches("hello.*", "hello.txt", false));
+        assert!(matches("hello.*", "a/b/hello.c", false));
+        assert!(!matches("hello.*", "hello", false));
+
+        assert!(matches("foo/", "foo", true));
+        assert!(matches("foo/", "a/foo", true));
+        assert!(!matches("foo/", "foo", false));
+
+        assert!(matches("doc/frotz/", "doc/frotz", true));
+        assert!(!matches("doc/frotz/", "a/doc/frotz", true));
+        assert!(matches("frotz/", "frotz", true));
+        assert!(matches("frotz/", "a/frotz", true));
+
+        assert!(matches("doc/*.txt", "doc/notes.txt", false));
+        assert!(!matches("doc/*.txt", "doc/server/arch.txt", false));
+
+        assert!(matches("/bar", "bar", false));
+        assert!(!matches("/bar", "a/bar", false));
+        assert!(matches("bar", "a/bar", false));
+
+        assert!(matches("**/foo", "foo", false));
+        assert!(matches("**/foo", "a/b/foo", false));
+        assert!(matches("**/foo/bar", "a/foo/bar", false));
+
+        assert!(matches("abc/**", "abc/x", false));
+        assert!(matches("abc/**", "abc/x/y", false));
+        assert!(!matches("abc/**", "abc", true));
+
+        assert!(matches("a/**/b", "a/b", false));
+        assert!(matches("a/**/b", "a/x/b", false));
+        assert!(matches("a/**/b", "a/x/y/b", false));
+
+        assert!(matches("foo**bar", "foobazbar", false));
+        assert!(!matches("foo**bar", "foo/bar", false));
+        assert!(matches("\\#file", "#file", false));
+        assert!(matches("\\!file", "!file", false));
+        assert!(matches("foo  ", "foo", false));
+        assert!(matches("foo\\ ", "foo ", false));
+    }
+
+    #[test]
+    fn set() {
+        let set = GitignoreSet::from_lines([
+            "# build output", "", "target/", "*.log", "!important.log", "/docs/*.html", "!docs/index.html",
+        ].iter()).unwrap();
+        assert_eq!(set.len(), 5);
+
+        assert!(set.is_ignored("target", true));
+        assert!(!set.is_ignored("target", false));
+        assert!(set.is_ignored("crates/a/target", true));
+        assert!(set.is_ignored("target/debug/app", false));
+
+        assert!(set.is_ignored("a.log", false));
+        assert!(set.is_ignored("logs/a.log", false));
+        assert!(!set.is_ignored("important.log", false));
+        assert!(!set.is_ignored("logs/important.log", false));
+
+        assert!(set.is_ignored("docs/a.html", false));
+        assert!(!set.is_ignored("docs/index.html", false));
+        assert!(!set.is_ignored("docs/api/a.html", false));
+        assert!(!set.is_ignored("src/main.rs", false));
+
+        // a file can't be re-included when its directory is ignored
+        let set = GitignoreSet::from_lines(["build/", "!build/keep"].iter()).unwrap();
+        assert!(set.is_ignored("build/keep", false));
+        let set = GitignoreSet::from_lines(["build/*", "!build/keep"].iter()).unwrap();
+        assert!(!set.is_ignored("build/keep", false));
+        assert!(set.is_ignored("build/other", false));
+
+        let err = GitignoreSet::from_lines(["*.rs", "[a-"].iter()).unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.inner, Error::UnclosedRange(3));
+
+        assert!(GitignorePattern::new("!*.rs").unwrap().is_negated());
+        assert!(GitignorePattern::new("dist/").unwrap().is_dir_only());
+    }
+    #[test]
+    fn rules() {
+        // the same as the set test, written as patterns
+        let literal = |p| PatternBuilder::new(p).require_literal_separator(true).build().unwrap();
+        let rules: RuleSet = vec![
+            (literal("**/target/"), false),
+            (literal("**/*.log"), false),
+            (literal("**/important.log"), true),
+            (literal("docs/*.html"), false),
+            (literal("docs/index.html"), true),
+        ].into_iter().collect();
+        assert_eq!(rules.len(), 5);
+
+        assert!(!rules.is_included("target", true));
+        assert!(rules.is_included("target", false));
+        assert!(!rules.is_included("crates/a/target", true));
+        assert!(!rules.is_included("target/debug/app", false));
+
+        assert!(!rules.is_included("a.log", false));
+        assert!(!rules.is_included("logs/a.log", false));
+        assert!(rules.is_included("important.log", false));
+        assert!(rules.is_included("logs/important.log", false));
+
+        assert!(!rules.is_included("docs/a.html", false));
+        assert!(rules.is_included("docs/index.html", false));
+        assert!(rules.is_included("docs/api/a.html", false));
+        assert!(rules.is_included("src/main.rs", false));
+
+        // the last matching rule wins, in either direction
+        let mut rules = RuleSet::new();
+        assert!(rules.is_empty());
+        assert!(rules.is_included("a.rs", false));
+        rules.insert(Pattern::new("*.rs").unwrap(), true);
+        rules.insert(Pattern::new("*").unwrap(), false);
+        assert!(!rules.is_included("a.rs", false));
+        rules.insert(Pattern::new("a.*").unwrap(), true);
+        assert!(rules.is_included("a.rs", false));
+        assert!(!rules.is_included("b.rs", false));
+
+        // nothing inside an excluded directory comes back
+        let rules: RuleSet = vec![(literal("build/"), false), (literal("build/keep"), true)].into_iter().collect();
+        assert!(!rules.is_included("build/keep", false));
+        let rules: RuleSet = vec![(literal("build/*"), false), (literal("build/keep"), true)].into_iter().collect();
+        assert!(rules.is_included("build/keep", false));
+        assert!(!rules.is_included("build/other", false));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,129 @@
+use alloc::vec::Vec;
+
+use crate::{Ast, Pattern};
+use crate::options::is_separator;
+use crate::syntax::{normalize_specifiers, CharSpecifier, Token};
+
+/// Converts a pattern of the [glob](https://docs.rs/glob/0.3) crate, which matches the same strings
+/// as long as both are matched with their default options.
+///
+/// This can't fail, glob's syntax is a subset of this crate's. The pattern is translated as glob parsed it
+/// rather than parsed again, so chars that mean something here but not to glob stay literal,
+/// e.g. `\` which glob never treats as an escape and the `(` of `@(a|b)` which it doesn't know.
+/// ```rust
+/// # use globber::Pattern;
+/// let pattern = Pattern::from(glob::Pattern::new("src/**/[!_]*.rs").unwrap());
+/// assert!(pattern.matches("src/bin/main.rs"));
+/// assert!(!pattern.matches("src/_build.rs"));
+/// ```
+impl From<&glob::Pattern> for Pattern {
+    fn from(pattern: &glob::Pattern) -> Self {
+        Ast::from_tokens(tokens(pattern.as_str())).compile_with(is_separator)
+    }
+}
+
+impl From<glob::Pattern> for Pattern {
+    fn from(pattern: glob::Pattern) -> Self {
+        Pattern::from(&pattern)
+    }
+}
+
+// follows glob::Pattern::new, which already rejected anything that isn't valid
+fn tokens(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let token = match chars[i] {
+            '?' => Token::AnyChar,
+            // glob swallows the separator after a **, here it stays and ** skips it instead
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                i += 1;
+                Token::AnyRecursive
+            }
+            '*' => Token::AnySequence,
+            // the first char of a range is never its end, even if it is a ]
+            '[' if chars.get(i + 1) == Some(&'!') => {
+                let (specifiers, end) = range(&chars, i + 2);
+                i = end;
+                Token::NotAnyOf(specifiers)
+            }
+            '[' => {
+                let (specifiers, end) = range(&chars, i + 1);
+                i = end;
+                Token::AnyOf(specifiers)
+            }
+            c => Token::Char(c),
+        };
+
+        tokens.push(token);
+        i += 1;
+    }
+
+    tokens
+}
+
+// the specifiers of the range starting at chars[start] and the index of the ] ending it
+fn range(chars: &[char], start: usize) -> (Vec<CharSpecifier>, usize) {
+    let end = chars[start + 1..].iter()
+        .position(|c| *c == ']')
+        .map(|j| start + 1 + j)
+        .expect("glob checked that ranges are closed");
+    let body = &chars[start..end];
+
+    // like here a - needs a char on both sides to form a range, but nothing is ever escaped
+    let mut specifiers = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        if i + 2 < body.len() && body[i + 1] == '-' {
+            specifiers.push(CharSpecifier::Range(body[i], body[i + 2]));
+            i += 3;
+        } else {
+            specifiers.push(CharSpecifier::Char(body[i]));
+            i += 1;
+        }
+    }
+
+    (normalize_specifiers(&specifiers), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Pattern;
+
+    #[test]
+    fn same_as_glob() {
+        let patterns = [
+            "", "*", "**", "abc", "*.rs", "a*b*c", "src/**/*.rs", "**/test", "/**/test", "a/**", "**/*", "a?c",
+            "[a-z]*", "[!0-9]?", "[]a]", "[!]a]", "[-a]", "[a-]", "[a-c-e]", "[z-a]", "[*?[]", "@(a|b)", "!(a)",
+            "a\\b", "\\*", "*(x)", "x<y>(z)",
+        ];
+        let inputs = [
+            "", "a", "b", "abc", "main.rs", "src/main.rs", "src/bin/main.rs", "test", "a/test", "/test", "/a/b/test",
+            "a/", "a/b/c", "a/b", "axc", "a/c", "x9", "9x", "]", "-", "c", "d", "e", "*", "?", "[", "@(a|b)", "!(a)",
+            "a\\b", "\\*", "*(x)", "x", "xx", "x<y>(z)",
+        ];
+
+        for p in patterns.iter() {
+            let glob = glob::Pattern::new(p).unwrap();
+            let pattern = Pattern::from(&glob);
+            for input in inputs.iter() {
+                assert_eq!(pattern.matches(input), glob.matches(input), "{} {}", p, input);
+            }
+        }
+    }
+
+    #[test]
+    fn literal_syntax() {
+        // \ escapes nothing in glob
+        let pattern = Pattern::from(glob::Pattern::new("a\\*").unwrap());
+        assert!(pattern.matches("a\\xyz"));
+        assert!(!pattern.matches("a*"));
+
+        let pattern = Pattern::from(glob::Pattern::new("@(a|b).rs").unwrap());
+        assert!(pattern.matches("@(a|b).rs"));
+        assert!(!pattern.matches("a.rs"));
+        assert_eq!(pattern.explain(), "literal \"@(a|b).rs\"\n");
+    }
+}
@@ -1,4 +1,5 @@
 //#![deny(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! This crate provides matching of strings to extended glob patterns.
 //! Only matching is supported currently and actual filesystem look up is on the road map.
@@ -12,6 +13,26 @@
 //! globber = "0.1"
 //! ```
 //!
+//! The `std` feature is enabled by default. Without it the crate is `no_std` and only needs `alloc`,
+//! in which case both `/` and `\` are treated as path separators.
+//! ```toml
+//! [dependencies]
+//! globber = { version = "0.1", default-features = false }
+//! ```
+//!
+//! The `glob` feature adds conversions from [`glob::Pattern`](https://docs.rs/glob/0.3/glob/struct.Pattern.html)
+//! to `Pattern`, to ease moving over from the [glob] crate.
+//!
+//! The `unicode` feature adds `Pattern::matches_normalized`, which compares the pattern and the input
+//! in Unicode normalization form C, e.g. for file names on macOS which are stored decomposed,
+//! and `Pattern::matches_graphemes`, which lets `?` match what reads as one character like a flag emoji.
+//!
+//! The `memchr` feature searches the input for the chars following a `*` with the [memchr] crate,
+//! which makes patterns like `*somelongtoken?` quicker on long input. Without it the standard library's search is used.
+//!
+//! The `globber-macros` crate has `glob_matches!("*.rs", path)`, which checks the pattern while compiling
+//! and is handy in `if` conditions and `match` guards.
+//!
 //! # Examples
 //!
 //! ### Wildcards
@@ -52,11 +73,31 @@
 //! ?           is any character
 //! *           any sqeunece of characters
 //! **          matches zero or more sqeuneces of characters
+//! **+         matches one or more sqeuneces of characters
 //! [abc]       matches one character given in the bracket
 //! [a-z]       matches a character in the range inclusively
 //! [!abc]      does not match one character given in the bracket
 //! [!a-z]      does not match a character in the range inclusively
+//! \x          matches x literally, for any character x
+//! \x41        matches the character with the hex code 41, which is A
+//! \u{1F980}   matches the character with the hex code 1F980, up to six digits
 //! ```
+//! Inside brackets `\` escapes the next character too, so `[\]\-\\]` matches one of `]`, `-` or `\`.
+//! Hex codes work there as well, `[\x41-\x5a]` matches `A` to `Z`.
+//! There are no special characters in the input, NUL is matched like any other character
+//! so `*\x00*` finds a separator in `find -print0` output.
+//! A `-` only forms a range with a character on both sides of it, otherwise it is a literal `-`.
+//! So `[-a]` and `[a-]` match `a` or `-`, and `[a-c-e]` matches `a` to `c`, `-` or `e`.
+//! An escaped `-` is never a range, `[a\-z]` matches one of `a`, `-` or `z`.
+//! Like POSIX a `]` right after `[` or `[!` is part of the range instead of closing it, so `[]a]` matches `]` or `a`.
+//! A `|` inside brackets is a plain character, even inside a pattern like `@(...)`, so `@(a[b|c]d|e)` matches
+//! `abd`, `a|d`, `acd` or `e`. A `[`, `(` or `)` inside brackets has to be escaped.
+//!
+//! `**` can also match no directories at all, taking the separators that follow it along.
+//! So a leading `**/` makes everything before the rest of the pattern optional,
+//! `**/x` matches `x`, `a/x` and `a/b/x`, and so does `**/**/x`.
+//! `**+` is a `**` that has to match at least one character, so `a/**+/b` needs a directory
+//! between `a` and `b` and doesn't match `a/b` like `a/**/b` does.
 //! ### Extended
 //! ```text
 //! ?(pattern|pattern|pattern) matches zero or one of the patterns
@@ -67,21 +108,150 @@
 //! ```
 //! A pattern is any valid glob pattern e.g, `!(+(ab|def)*+(.jpg|.gif))`
 //!
+//! Any of them can be given a name made of letters, digits and `_` to capture what it matched,
+//! e.g. `*.@<ext>(rs|toml)`, see `Pattern::captures_named`.
+//!
+//! # Semantics
+//! ### Empty patterns
+//! An empty pattern is valid and only matches the empty string, while `*` and `**` match any string including the empty one.
+//! ### Greediness
+//! A pattern always has to match the whole input, from the first character to the last.
+//! `*` and `**` are tried shortest first and grow one character at a time until the rest of the pattern matches,
+//! so they behave lazily. Since the whole input has to be consumed either way, this never changes what `matches` returns.
+//! It does change what `Pattern::captures` returns, captures are greedy by default and lazy with `MatchOptions::lazy`,
+//! so `a*b*c` captures `xby` and `z` from `axbybzc` greedily but `x` and `ybz` lazily.
+//! ### Alternatives
+//! Every alternative of a pattern is tried together with the rest of the glob, so the order they are written in never
+//! changes the result, `@(ab|a)bc` and `@(a|ab)bc` match exactly the same strings.
+//! A pattern matches as soon as any alternative leads to a match, even when several of them could,
+//! so `@([a-z]|[a-c])` matches `a`.
+//! Escaped `|`, `(` and `)` are part of an alternative instead of delimiting it, `@(a\|b|c)` matches `a|b` or `c`.
+//! ```rust
+//! # use globber::Pattern;
+//! let pattern = Pattern::new("@(ab|a)bc").unwrap();
+//! assert!(pattern.matches("abc"));
+//! assert!(pattern.matches("abbc"));
+//! ```
+//! An alternative can't be empty, `@(a|b|)` is an error. To match one of the alternatives or nothing
+//! use `?(a|b)`, and `*(a|b)` instead of `+(a|b|)`.
+//! ```rust
+//! # use globber::{ErrorKind, Pattern};
+//! assert_eq!(Pattern::new("@(a|b|)").unwrap_err().kind(), ErrorKind::IllegalOr);
+//! let pattern = Pattern::new("x?(a|b)").unwrap();
+//! assert!(pattern.matches("xa"));
+//! assert!(pattern.matches("x"));
+//! ```
+//!
 //! [glob]: https://github.com/rust-lang-nursery/glob
+//! [memchr]: https://docs.rs/memchr
 
+extern crate alloc;
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Error as FmtError, Formatter};
+use core::ops::Range;
+use core::str::FromStr;
+#[cfg(feature = "std")]
 use std::error::Error as StdError;
-use std::fmt::{Display, Error as FmtError, Formatter};
-use std::str::FromStr;
 
+pub use crate::analysis::{Complexity, PatternStats, SegmentPos};
+pub use crate::ast::Ast;
+pub use crate::builder::PatternBuilder;
+#[cfg(feature = "std")]
+pub use crate::cache::PatternCache;
+pub use crate::class::CharClass;
+pub use crate::explain::{MatchExplanation, MatchMetrics, TokenId};
+pub use crate::gitignore::{GitignorePattern, GitignoreSet, RuleSet};
+pub use crate::map::GlobMap;
+pub use crate::options::MatchOptions;
+pub use crate::set::GlobSet;
+pub use crate::stream::{MatchState, StreamMatcher};
+pub use crate::suffix::SuffixGlobSet;
+pub use crate::syntax::{CharSpecifier, ErrorKind};
+pub use crate::token::PublicToken;
+use crate::analysis::{complexity, depth, enumerate, is_satisfiable, literal_segments, required_extension, stats};
+use crate::explain::{explain, render, trace};
 use crate::matcher::Matcher;
-use crate::syntax::{Error as SyntaxError, parse};
+use crate::overlap::overlaps;
+use crate::syntax::Error as SyntaxError;
+use crate::syntax::Token;
+use crate::transform::{canonicalize, simplify_classes};
 
 pub(crate) mod syntax;
+pub(crate) mod ast;
 pub(crate) mod matcher;
+pub(crate) mod class;
+pub(crate) mod transform;
+pub(crate) mod options;
+pub(crate) mod analysis;
+pub(crate) mod set;
+pub(crate) mod suffix;
+pub(crate) mod map;
+pub(crate) mod overlap;
+pub(crate) mod builder;
+pub(crate) mod nfa;
+pub(crate) mod stream;
+pub(crate) mod explain;
+pub(crate) mod gitignore;
+pub(crate) mod token;
+#[cfg(feature = "std")]
+pub(crate) mod cache;
+#[cfg(feature = "glob")]
+pub(crate) mod interop;
+
+/// Parses the pattern and matches it against the input in one go.
+///
+/// The pattern is parsed again on every call, when matching the same pattern repeatedly
+/// build a [`Pattern`] once and reuse it.
+/// ```rust
+/// # use globber::glob_match;
+/// assert_eq!(glob_match("*.rs", "main.rs"), Ok(true));
+/// assert_eq!(glob_match("*.rs", "main.c"), Ok(false));
+/// assert!(glob_match("[a-", "main.rs").is_err());
+/// ```
+pub fn glob_match(pattern: &str, input: &str) -> Result<bool, Error> {
+    Ok(Pattern::new(pattern)?.matches(input))
+}
+
+/// Returns true if the string has any unescaped `*`, `?`, `[`, `]`, `(`, `)` or `|`, which covers every wildcard,
+/// range and pattern like `@(...)`. This is a quick scan without parsing, e.g. to compare strings directly
+/// instead of building a [`Pattern`] when there is nothing to glob.
+///
+/// A string without any matches only the string it spells out with its escapes removed, `a\*` matches `a*`.
+/// A trailing `\` escapes nothing, so a string ending in one counts as a glob since it isn't a valid literal either.
+/// ```rust
+/// # use globber::is_glob;
+/// assert!(is_glob("src/*.rs"));
+/// assert!(is_glob("@(a|b)"));
+/// assert!(!is_glob("src/main.rs"));
+/// assert!(!is_glob("main\\[1\\].rs"));
+/// ```
+pub fn is_glob(s: &str) -> bool {
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.next().is_none() => return true,
+            '\\' => {}
+            '*' | '?' | '[' | ']' | '(' | ')' | '|' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Stands in for the alternative of a pattern that matched without using any,
+/// see [`Pattern::matches_with_branches`].
+pub const NO_BRANCH: usize = usize::MAX;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Pattern {
     matcher: Matcher,
+    // what the methods that don't take options match with, see PatternBuilder
+    options: MatchOptions,
 }
 
 impl Pattern {
@@ -89,8 +259,803 @@ impl Pattern {
         pattern.parse()
     }
 
+    /// Builds a pattern from tokens instead of parsing a string, e.g. for code generators targeting this crate.
+    /// The tokens are checked like a parsed pattern is, the position of an error counts chars
+    /// into the tokens written out as a pattern, which is what [`Error::input`] returns.
+    /// ```rust
+    /// # use globber::{Pattern, PublicToken};
+    /// let tokens = vec![PublicToken::AnySequence, PublicToken::Char('.'), PublicToken::Char('r'), PublicToken::Char('s')];
+    /// assert!(Pattern::from_tokens(tokens).unwrap().matches("main.rs"));
+    ///
+    /// let err = Pattern::from_tokens(vec![PublicToken::Char('a'), PublicToken::AnyRecursive]).unwrap_err();
+    /// assert_eq!((err.input(), err.position()), ("a**", 0));
+    /// ```
+    pub fn from_tokens(tokens: Vec<PublicToken>) -> Result<Pattern, Error> {
+        token::compile(tokens)
+    }
+
+    /// Combines patterns into one that matches whatever any of them matches, like `@(a|b)` does.
+    /// Unlike a [`GlobSet`] the result is a pattern again, so it can be captured from or combined further.
+    ///
+    /// It uses the separators and options of the first pattern, with no patterns at all it never matches.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::from_alternatives(vec![Pattern::new("*.rs").unwrap(), Pattern::new("*.toml").unwrap()]);
+    /// assert!(pattern.matches("main.rs"));
+    /// assert!(pattern.matches("Cargo.toml"));
+    /// assert!(!pattern.matches("Cargo.lock"));
+    /// ```
+    pub fn from_alternatives<I: IntoIterator<Item=Pattern>>(patterns: I) -> Pattern {
+        let mut first = None;
+        let alternatives = patterns.into_iter()
+            .map(|p| {
+                first.get_or_insert((p.matcher.is_separator(), p.options));
+                p.matcher.tokens().to_vec()
+            })
+            .collect();
+
+        let (is_separator, options) = first.unwrap_or((options::is_separator, MatchOptions::new()));
+        Self {
+            matcher: Matcher::new(vec![Token::ExactlyOne(alternatives)], is_separator),
+            options,
+        }
+    }
+
     pub fn matches(&self, input: &str) -> bool {
-        self.matcher.matches(input.chars())
+        self.matches_with(input, self.options)
+    }
+
+    /// Matches like [`matches`](Pattern::matches) using the given options.
+    pub fn matches_with(&self, input: &str, options: MatchOptions) -> bool {
+        self.matcher.matches_str(input, &options)
+    }
+
+    /// Matches like [`matches`](Pattern::matches) after bringing the chars of the pattern and the input into
+    /// Unicode normalization form C, so an accented char written as one char matches the same char written
+    /// as a letter followed by a combining accent. macOS stores file names in the latter form while
+    /// patterns are usually typed in the former.
+    ///
+    /// This costs a copy of the input on every call, and of the pattern too unless its chars are normalized already.
+    /// Chars of the pattern are only composed with the chars next to them, a combining accent right after a
+    /// wildcard or range stays a char of its own.
+    ///
+    /// Normalization happens before [`MatchOptions::case_insensitive`] compares chars, which only folds ASCII letters.
+    /// So a decomposed `E` and accent, which matches `e` and an accent with the option, turns into `É`
+    /// and no longer matches `é`.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("café.txt").unwrap();
+    /// assert!(!pattern.matches("cafe\u{301}.txt"));
+    /// assert!(pattern.matches_normalized("cafe\u{301}.txt"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn matches_normalized(&self, input: &str) -> bool {
+        self.matches_normalized_with(input, self.options)
+    }
+
+    /// Matches like [`matches_normalized`](Pattern::matches_normalized) using the given options.
+    #[cfg(feature = "unicode")]
+    pub fn matches_normalized_with(&self, input: &str, options: MatchOptions) -> bool {
+        use unicode_normalization::UnicodeNormalization;
+
+        let input: String = input.nfc().collect();
+        let tokens = transform::nfc(self.matcher.tokens());
+        if tokens[..] == *self.matcher.tokens() {
+            return self.matcher.matches_str(&input, &options);
+        }
+        Matcher::new(tokens, self.matcher.is_separator()).matches_str(&input, &options)
+    }
+
+    /// Matches like [`matches`](Pattern::matches) but takes grapheme clusters, what a reader sees as one character,
+    /// as the unit of matching instead of chars. A flag emoji is two chars and a letter with a combining accent
+    /// is two as well, in this mode `?` matches either of them as a whole.
+    ///
+    /// - `?` matches one cluster
+    /// - a range matches a cluster whose first char it matches, so `[a-z]` matches `e` followed by a combining accent
+    /// - `*` and `**` only take whole clusters, right after a char that started a cluster they match nothing
+    /// - chars in the pattern still match chars, so `e` and a combining accent match that cluster, while
+    ///   a lone `e` can't match the start of it since whatever follows has to begin a new cluster
+    ///
+    /// The input is segmented into clusters on every call, which takes time and memory linear in its length,
+    /// and this always backtracks instead of using the faster matching some patterns allow.
+    /// Captures and the other methods keep matching chars.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("?.png").unwrap();
+    /// assert!(!pattern.matches("🇨🇦.png"));
+    /// assert!(pattern.matches_graphemes("🇨🇦.png"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn matches_graphemes(&self, input: &str) -> bool {
+        self.matches_graphemes_with(input, self.options)
+    }
+
+    /// Matches like [`matches_graphemes`](Pattern::matches_graphemes) using the given options.
+    #[cfg(feature = "unicode")]
+    pub fn matches_graphemes_with(&self, input: &str, options: MatchOptions) -> bool {
+        self.matcher.matches_graphemes(input, &options)
+    }
+
+    /// Matches like [`matches`](Pattern::matches) but gives up once matching takes longer than `limit`,
+    /// e.g. to bound the time spent on untrusted patterns.
+    ///
+    /// The clock is only looked at every thousand or so steps of backtracking, so the limit can be overshot
+    /// by the time those take. Patterns that don't need to backtrack at all are never timed out.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// # use std::time::Duration;
+    /// let pattern = Pattern::new("src/@(*.rs|*.toml)").unwrap();
+    /// assert_eq!(pattern.matches_with_timeout("src/main.rs", Duration::from_secs(1)), Ok(true));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn matches_with_timeout(&self, input: &str, limit: std::time::Duration) -> Result<bool, Timeout> {
+        let start = std::time::Instant::now();
+        let expired = || start.elapsed() > limit;
+        self.matcher.matches_until(input, &self.options, &expired).ok_or(Timeout)
+    }
+
+    /// Matches like [`matches`](Pattern::matches) and counts how much backtracking it took,
+    /// e.g. to find the patterns worth rewriting in a large set of them.
+    ///
+    /// The input is always matched by backtracking, even for patterns `matches` handles without it,
+    /// so the counts show what the pattern costs at worst. Plain `matches` doesn't count anything.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let (matched, metrics) = Pattern::new("*@(a)*@(a)*@(a)b").unwrap().matches_counted("aaaaaaaaaaaaaaaa");
+    /// assert!(!matched);
+    /// assert!(metrics.backtracks > 1000);
+    /// ```
+    pub fn matches_counted(&self, input: &str) -> (bool, MatchMetrics) {
+        self.matcher.matches_counted(input, &self.options)
+    }
+
+    /// Returns true if the input is the start of something the pattern matches, i.e. some more input
+    /// appended to it could make it match, e.g. to prune candidates while a path is being typed.
+    ///
+    /// Patterns like `!(...)` that haven't been reached by the end of the input are assumed to be able to match,
+    /// so for them this can be true even though nothing appended would match.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/*.rs").unwrap();
+    /// assert!(pattern.could_match_prefix("sr"));
+    /// assert!(pattern.could_match_prefix("src/main"));
+    /// assert!(!pattern.could_match_prefix("test/"));
+    /// ```
+    pub fn could_match_prefix(&self, partial: &str) -> bool {
+        self.matcher.matches_prefix(partial, &self.options)
+    }
+
+    /// Returns true if the pattern can match some path inside the directory, e.g. to skip whole directories
+    /// while walking the filesystem. An empty directory stands for the top of the walk, which everything is inside.
+    ///
+    /// Like [`could_match_prefix`](Pattern::could_match_prefix) this can be true when nothing inside matches,
+    /// but never false when something does. The directory is joined to what is inside it with `/`,
+    /// or `\` where that is the only separator, with other separators it has to end with one.
+    /// ```rust
+    /// # use globber::{Pattern, PatternBuilder};
+    /// let pattern = Pattern::new("src/**/*.rs").unwrap();
+    /// assert!(pattern.could_match_under("src/a"));
+    /// assert!(pattern.could_match_under("src/"));
+    /// assert!(!pattern.could_match_under("tests"));
+    ///
+    /// // a * crosses separators unless it is told not to
+    /// assert!(Pattern::new("*.rs").unwrap().could_match_under("src"));
+    /// assert!(!PatternBuilder::new("*.rs").require_literal_separator(true).build().unwrap().could_match_under("src"));
+    /// ```
+    pub fn could_match_under(&self, dir: &str) -> bool {
+        let is_separator = self.matcher.is_separator();
+        if dir.is_empty() || dir.ends_with(is_separator) {
+            return self.could_match_prefix(dir);
+        }
+
+        match ['/', '\\'].iter().find(|c| is_separator(**c)) {
+            Some(&separator) => {
+                let mut dir = String::from(dir);
+                dir.push(separator);
+                self.could_match_prefix(&dir)
+            }
+            // there is no knowing what follows the directory
+            None => true,
+        }
+    }
+
+    /// Matches the start of an input and returns the state to resume from with the rest of it,
+    /// e.g. to match a directory once and go on with every entry inside it on another thread.
+    ///
+    /// Returns `None` if nothing appended to the prefix could match, or for patterns [`StreamMatcher`]
+    /// doesn't support, like `@(...)`.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/*.rs").unwrap();
+    /// let state = pattern.match_prefix_state("src/").unwrap();
+    /// assert!(state.advance("main.rs").unwrap().is_match());
+    /// assert!(pattern.match_prefix_state("tests/").is_none());
+    /// ```
+    pub fn match_prefix_state(&self, prefix: &str) -> Option<MatchState> {
+        self.match_prefix_state_with(prefix, self.options)
+    }
+
+    /// Like [`match_prefix_state`](Pattern::match_prefix_state) using the given options for all of the input.
+    pub fn match_prefix_state_with(&self, prefix: &str, options: MatchOptions) -> Option<MatchState> {
+        MatchState::new(self, prefix, options)
+    }
+
+    /// Returns what each `*` and `**` matched, in the order they appear in the pattern,
+    /// or `None` if the input doesn't match. Wildcards inside patterns like `@(...)` are not captured.
+    ///
+    /// Wildcards take as much of the input as they can, see [`MatchOptions::lazy`] to take as little as possible.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/**/*.rs").unwrap();
+    /// assert_eq!(pattern.captures("src/bin/tool/main.rs"), Some(vec!["bin/tool", "main"]));
+    /// assert_eq!(pattern.captures("src/main.rs"), Some(vec!["", "main"]));
+    /// assert_eq!(pattern.captures("src/main.c"), None);
+    /// ```
+    pub fn captures<'a>(&self, input: &'a str) -> Option<Vec<&'a str>> {
+        self.captures_with(input, self.options)
+    }
+
+    /// Captures like [`captures`](Pattern::captures) using the given options.
+    pub fn captures_with<'a>(&self, input: &'a str, options: MatchOptions) -> Option<Vec<&'a str>> {
+        self.matcher.captures(input, &options)
+    }
+
+    /// Returns what each named pattern like `@<name>(...)` matched, or `None` if the input doesn't match.
+    ///
+    /// Any pattern can be named, e.g. `?<name>(...)` or `!<name>(...)`. A named pattern that matched nothing,
+    /// like an optional one that was left out, captures an empty string.
+    /// When a name is used more than once, the pattern matching furthest into the input wins.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("@<name>(*).@<ext>(rs|toml)").unwrap();
+    /// let captures = pattern.captures_named("Cargo.toml").unwrap();
+    /// assert_eq!(captures["name"], "Cargo");
+    /// assert_eq!(captures["ext"], "toml");
+    /// ```
+    pub fn captures_named<'a>(&self, input: &'a str) -> Option<BTreeMap<String, &'a str>> {
+        self.matcher.captures_named(input, &self.options)
+    }
+
+    /// Returns the index of the alternative that matched for every pattern like `@(...)` outside of other patterns,
+    /// in the order they appear in the pattern, or `None` if the input doesn't match.
+    ///
+    /// When several alternatives could match, the one the match went through is returned, which is the first one
+    /// in the pattern that lets the rest of the input match. An optional pattern that was left out and `!(...)`,
+    /// which matches by none of its alternatives matching, give [`NO_BRANCH`].
+    /// ```rust
+    /// # use globber::{Pattern, NO_BRANCH};
+    /// let route = Pattern::new("@(get|post|put) /users/?(*/)@(list|show)").unwrap();
+    /// assert_eq!(route.matches_with_branches("post /users/list"), Some(vec![1, NO_BRANCH, 0]));
+    /// assert_eq!(route.matches_with_branches("put /users/7/show"), Some(vec![2, 0, 1]));
+    /// assert_eq!(route.matches_with_branches("delete /users/list"), None);
+    /// ```
+    pub fn matches_with_branches(&self, input: &str) -> Option<Vec<usize>> {
+        self.matcher.branches(input, &self.options)
+    }
+
+    /// Returns the byte ranges of every part of the input the pattern matches, e.g. to highlight them.
+    ///
+    /// Matches don't overlap, they are searched for from left to right and at each start the longest one is taken,
+    /// the search then carries on where it ended. Empty matches are left out.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("a?c").unwrap();
+    /// assert_eq!(pattern.match_positions("abc adc aXc"), vec![(0, 3), (4, 7), (8, 11)]);
+    /// let pattern = Pattern::new("[0-9]*[0-9]").unwrap();
+    /// assert_eq!(pattern.match_positions("v1.2.3 and 4"), vec![(1, 12)]);
+    /// ```
+    pub fn match_positions(&self, input: &str) -> Vec<(usize, usize)> {
+        self.matcher.match_positions(input, &self.options)
+    }
+
+    /// Splits off the literal directories at the start of the pattern, returning the directory to start
+    /// walking the filesystem from and the pattern to match paths relative to it.
+    /// Without any literal directories the base is `.`.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// # use std::path::PathBuf;
+    /// let (base, rest) = Pattern::new("src/foo/**/*.rs").unwrap().split_root();
+    /// assert_eq!(base, PathBuf::from("src/foo"));
+    /// assert_eq!(rest, Pattern::new("**/*.rs").unwrap());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn split_root(&self) -> (std::path::PathBuf, Pattern) {
+        let tokens = self.matcher.tokens();
+        let is_separator = self.matcher.is_separator();
+
+        let literal = tokens.iter().take_while(|t| matches!(t, Token::Char(_))).count();
+        let split = tokens[..literal].iter()
+            .rposition(|t| matches!(t, Token::Char(c) if is_separator(*c)));
+        let split = match split {
+            Some(split) => split,
+            None => return (".".into(), self.clone()),
+        };
+
+        let chars = |tokens: &[Token]| -> String {
+            tokens.iter()
+                .filter_map(|t| match t {
+                    Token::Char(c) => Some(*c),
+                    _ => None,
+                })
+                .collect()
+        };
+        let mut base = chars(&tokens[..split]);
+        base.truncate(base.trim_end_matches(is_separator).len());
+        // a root like / or C:/ means something else without its separator
+        if base.is_empty() || base.ends_with(':') {
+            base.push_str(&chars(&tokens[split..=split]));
+        }
+
+        let rest = Self {
+            matcher: Matcher::new(tokens[split + 1..].to_vec(), is_separator),
+            options: self.options,
+        };
+        (base.into(), rest)
+    }
+
+    /// Matches a path given as its components, e.g. while walking a directory tree, without joining them into a string.
+    ///
+    /// The components are matched as if joined by `/`, only `**` and separators in the pattern cross from
+    /// one component into the next while `*` and `?` stay inside of one, like with
+    /// [`MatchOptions::require_literal_separator`]. So a component containing a separator is split by it.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/**/*.rs").unwrap();
+    /// assert!(pattern.matches_components(vec!["src", "bin", "main.rs"].into_iter()));
+    /// assert!(pattern.matches_components("src/main.rs".split('/')));
+    /// assert!(!Pattern::new("src/*").unwrap().matches_components(vec!["src", "bin", "main.rs"].into_iter()));
+    /// ```
+    pub fn matches_components<'a, I: Iterator<Item=&'a str> + Clone>(&self, components: I) -> bool {
+        let chars = components
+            .enumerate()
+            .flat_map(|(i, component)| if i == 0 { None } else { Some('/') }.into_iter().chain(component.chars()));
+        let mut options = self.options;
+        options.require_literal_separator = true;
+        self.matcher.matches(chars, &options)
+    }
+
+    /// Matches chars from any source, e.g. a decoder, without collecting them into a string first.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let utf16: Vec<u16> = "main.rs".encode_utf16().collect();
+    /// let chars = std::char::decode_utf16(utf16.iter().copied()).map(|c| c.unwrap_or('\u{FFFD}'));
+    /// assert!(Pattern::new("*.rs").unwrap().matches_chars(chars));
+    /// ```
+    pub fn matches_chars<I: Iterator<Item=char> + Clone>(&self, input: I) -> bool {
+        self.matcher.matches(input, &self.options)
+    }
+
+    /// Matches an `OsStr` such as a path from the filesystem, converting it with
+    /// [`OsStr::to_string_lossy`](std::ffi::OsStr::to_string_lossy) first.
+    ///
+    /// This is lossy, anything that isn't valid Unicode is replaced by `U+FFFD` before matching.
+    /// So such parts only match wildcards, `?` or a literal `\u{FFFD}`, and inputs that differ only
+    /// in invalid sequences can't be told apart.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// # use std::path::Path;
+    /// let pattern = Pattern::new("src/*.rs").unwrap();
+    /// assert!(pattern.matches_os_str_lossy(Path::new("src/main.rs").as_os_str()));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn matches_os_str_lossy(&self, input: &std::ffi::OsStr) -> bool {
+        self.matches(&input.to_string_lossy())
+    }
+
+    /// Matches the part of `path` inside of `base`, e.g. to match absolute paths against patterns relative
+    /// to the root of a repository. It's false if `path` isn't inside of `base`.
+    ///
+    /// Only whole components are stripped, `/repo` isn't a base of `/repository/a`,
+    /// and the rest is matched like [`matches_os_str_lossy`](Pattern::matches_os_str_lossy) does.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// # use std::path::Path;
+    /// let pattern = Pattern::new("src/*.rs").unwrap();
+    /// assert!(pattern.matches_relative(Path::new("/home/user/repo"), Path::new("/home/user/repo/src/main.rs")));
+    /// assert!(!pattern.matches_relative(Path::new("/home/user/repo"), Path::new("/home/user/other/src/main.rs")));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn matches_relative(&self, base: &std::path::Path, path: &std::path::Path) -> bool {
+        match path.strip_prefix(base) {
+            Ok(relative) => self.matches_os_str_lossy(relative.as_os_str()),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns true if the pattern ends with a separator like `build/`, which only matches directories.
+    pub fn is_dir_only(&self) -> bool {
+        self.matcher.is_dir_only()
+    }
+
+    /// Matches a path knowing whether it is a directory. A [dir only](Pattern::is_dir_only) pattern
+    /// only matches directories and doesn't need the path to end with a separator,
+    /// every other pattern matches like [`matches`](Pattern::matches).
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("build/").unwrap();
+    /// assert!(pattern.matches_path_kind("build", true));
+    /// assert!(!pattern.matches_path_kind("build", false));
+    /// ```
+    pub fn matches_path_kind(&self, path: &str, is_dir: bool) -> bool {
+        if !self.is_dir_only() {
+            return self.matches(path);
+        }
+
+        is_dir && self.matcher.matches_dir(path.trim_end_matches(self.matcher.is_separator()).chars(), &self.options)
+    }
+
+    /// Parses a line of a `.gitignore` file, which follows git's rules instead of the shell's,
+    /// see [`GitignorePattern`].
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::gitignore("doc/*.txt").unwrap();
+    /// assert!(pattern.matches("doc/notes.txt", false));
+    /// assert!(!pattern.matches("doc/server/arch.txt", false));
+    /// ```
+    pub fn gitignore(line: &str) -> Result<GitignorePattern, Error> {
+        GitignorePattern::new(line)
+    }
+
+    /// Returns true if any of the inputs match, stopping at the first one that does.
+    pub fn matches_any(&self, inputs: &[&str]) -> bool {
+        inputs.iter().any(|input| self.matches(input))
+    }
+
+    /// Returns true if all of the inputs match, stopping at the first one that doesn't.
+    pub fn matches_all(&self, inputs: &[&str]) -> bool {
+        inputs.iter().all(|input| self.matches(input))
+    }
+
+    /// Splits the inputs into the ones that match and the ones that don't, keeping their order.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let (rust, other) = Pattern::new("*.rs").unwrap().partition(vec!["main.rs", "Cargo.toml", "lib.rs"]);
+    /// assert_eq!(rust, vec!["main.rs", "lib.rs"]);
+    /// assert_eq!(other, vec!["Cargo.toml"]);
+    /// ```
+    pub fn partition<S: AsRef<str>>(&self, inputs: impl IntoIterator<Item=S>) -> (Vec<S>, Vec<S>) {
+        inputs.into_iter().partition(|input| self.matches(input.as_ref()))
+    }
+
+    /// Matches text made of several lines, where `*` and `?` stay within a line and only `**` crosses into the next,
+    /// see [`MatchOptions::newline_is_boundary`].
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert!(!Pattern::new("*foo").unwrap().matches_multiline("a\nfoo"));
+    /// assert!(Pattern::new("**/foo").unwrap().matches_multiline("a\nb/foo"));
+    /// ```
+    pub fn matches_multiline(&self, input: &str) -> bool {
+        let mut options = self.options;
+        options.newline_is_boundary = true;
+        self.matches_with(input, options)
+    }
+
+    /// Matches ignoring the case of ASCII letters, see [`MatchOptions::case_insensitive`].
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("*.RS").unwrap();
+    /// assert!(pattern.matches_case_fold("main.rs"));
+    /// assert!(!pattern.matches("main.rs"));
+    /// ```
+    pub fn matches_case_fold(&self, input: &str) -> bool {
+        let mut options = self.options;
+        options.case_insensitive = true;
+        self.matches_with(input, options)
+    }
+
+    /// Matches raw bytes like [`matches_case_fold`](Pattern::matches_case_fold), e.g. file names from an index
+    /// that were stored without checking their encoding.
+    ///
+    /// The bytes are read as UTF-8 and only ASCII letters are folded, any other char has to match exactly.
+    /// Like [`matches_os_str_lossy`](Pattern::matches_os_str_lossy) every invalid sequence is replaced by `U+FFFD`,
+    /// which only allocates when there is one.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("*.TXT").unwrap();
+    /// assert!(pattern.matches_ascii_case_insensitive_bytes(b"file.txt"));
+    /// assert!(pattern.matches_ascii_case_insensitive_bytes(b"\xff.Txt"));
+    /// assert!(!pattern.matches_ascii_case_insensitive_bytes(b"file.rs"));
+    /// ```
+    pub fn matches_ascii_case_insensitive_bytes(&self, input: &[u8]) -> bool {
+        let mut options = self.options;
+        options.case_insensitive = true;
+        self.matches_with(&String::from_utf8_lossy(input), options)
+    }
+
+    /// Matches raw bytes that don't have to be valid UTF-8, e.g. paths on Unix, without replacing anything.
+    ///
+    /// Every byte is matched on its own. A char of the pattern matches the bytes of its UTF-8 encoding,
+    /// so `é` matches the two bytes `\xc3\xa9`. `?` and ranges match exactly one byte, which is compared as
+    /// an ASCII char, so `[a-z]` matches the byte `b'a'` and no byte of `é`, while `[!a-z]` and `?` match any single
+    /// byte of it including invalid ones. Non ASCII chars in a range can't match a single byte and are left out.
+    /// Separators other than ASCII ones are never found in the input.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("café/*.[a-z]").unwrap();
+    /// assert!(pattern.matches_bytes(b"caf\xc3\xa9/\xff.c"));
+    /// assert!(!pattern.matches_bytes(b"caf\xe9/a.c"));
+    /// assert!(Pattern::new("caf??").unwrap().matches_bytes("café".as_bytes()));
+    /// ```
+    pub fn matches_bytes(&self, input: &[u8]) -> bool {
+        self.matches_bytes_with(input, self.options)
+    }
+
+    /// Matches like [`matches_bytes`](Pattern::matches_bytes) using the given options.
+    pub fn matches_bytes_with(&self, input: &[u8], options: MatchOptions) -> bool {
+        let chars = input.iter().map(|&b| char::from(b));
+        let tokens = transform::utf8_bytes(self.matcher.tokens());
+        if tokens[..] == *self.matcher.tokens() {
+            return self.matcher.matches(chars, &options);
+        }
+        Matcher::new(tokens, self.matcher.is_separator()).matches(chars, &options)
+    }
+
+    /// Returns true if the pattern has no wildcards, ranges or patterns,
+    /// i.e. it only matches the exact string it was built from.
+    pub fn is_literal(&self) -> bool {
+        self.matcher.is_literal()
+    }
+
+    /// Returns false if no input can ever match the pattern, e.g. `[2-1]` which is reversed and so empty,
+    /// to catch dead patterns in configuration.
+    ///
+    /// A pattern like `!(...)` is only known to match nothing when one of its alternatives is a lone `*` or `**`,
+    /// otherwise it is assumed to match something.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert!(Pattern::new("[a-z]").unwrap().is_satisfiable());
+    /// assert!(!Pattern::new("file[2-1].txt").unwrap().is_satisfiable());
+    /// assert!(!Pattern::new("@([2-1]|!(*))").unwrap().is_satisfiable());
+    /// ```
+    pub fn is_satisfiable(&self) -> bool {
+        is_satisfiable(self.matcher.tokens())
+    }
+
+    /// Estimates how expensive matching this pattern can get, e.g. to reject untrusted patterns.
+    /// ```rust
+    /// # use globber::{Complexity, Pattern};
+    /// assert_eq!(Pattern::new("*.rs").unwrap().complexity_hint(), Complexity::Linear);
+    /// assert_eq!(Pattern::new("a*b*c").unwrap().complexity_hint(), Complexity::Polynomial);
+    /// assert_eq!(Pattern::new("!(*.rs)").unwrap().complexity_hint(), Complexity::Exponential);
+    /// ```
+    pub fn complexity_hint(&self) -> Complexity {
+        complexity(self.matcher.tokens())
+    }
+
+    /// Counts the tokens, wildcards, alternatives and how deep patterns nest, e.g. to flag risky patterns.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let stats = Pattern::new("src/**/*.@(rs|toml)").unwrap().stats();
+    /// assert_eq!(stats.tokens, 15);
+    /// assert_eq!(stats.wildcards, 2);
+    /// assert_eq!(stats.max_depth, 1);
+    /// assert_eq!(stats.alternatives, 2);
+    /// ```
+    pub fn stats(&self) -> PatternStats {
+        stats(self.matcher.tokens())
+    }
+
+    /// Returns the fewest and the most chars an input matching the pattern can have, `None` meaning no limit.
+    /// [`matches`](Pattern::matches) rejects input outside of these right away.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert_eq!(Pattern::new("a?c").unwrap().len_bounds(), (3, Some(3)));
+    /// assert_eq!(Pattern::new("a*c").unwrap().len_bounds(), (2, None));
+    /// assert_eq!(Pattern::new("[0-9]?(.@(rs|toml))").unwrap().len_bounds(), (1, Some(6)));
+    /// ```
+    pub fn len_bounds(&self) -> (usize, Option<usize>) {
+        self.matcher.len_bounds()
+    }
+
+    /// Lists the strings the pattern matches, e.g. to generate test cases or show examples of a pattern.
+    /// Returns `None` for a pattern matching infinitely many, i.e. one with a wildcard or a pattern like `*(...)`,
+    /// and otherwise stops after `limit` strings.
+    ///
+    /// The strings come in the order the pattern is written in: the alternatives of a pattern like `@(...)`
+    /// left to right, the chars of a range from lowest to highest and `?(...)` matching nothing first.
+    /// `?` and ranges like `[!a]` match nearly every char, so patterns with them usually hit the limit.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert_eq!(Pattern::new("@(a|b).rs").unwrap().enumerate(10), Some(vec!["a.rs".into(), "b.rs".into()]));
+    /// assert_eq!(Pattern::new("v[1-3]?(.0)").unwrap().enumerate(4).unwrap(), ["v1", "v1.0", "v2", "v2.0"]);
+    /// assert_eq!(Pattern::new("*.rs").unwrap().enumerate(10), None);
+    /// ```
+    pub fn enumerate(&self, limit: usize) -> Option<Vec<String>> {
+        enumerate(self.matcher.tokens(), self.matcher.is_separator(), &self.options, limit, &|s| self.matches(s))
+    }
+
+    /// Returns how many path levels the pattern spans, counted by the separators written in it,
+    /// e.g. to bucket patterns by the depth of the directory index they apply to.
+    ///
+    /// It's `None` if that isn't fixed, which is the case for a `**` or a pattern like `@(...)` containing separators.
+    /// A `*` is taken to stay within its component, as with [`MatchOptions::require_literal_separator`].
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert_eq!(Pattern::new("a/b/*.rs").unwrap().depth(), Some(2));
+    /// assert_eq!(Pattern::new("*.rs").unwrap().depth(), Some(0));
+    /// assert_eq!(Pattern::new("a/**/x").unwrap().depth(), None);
+    /// ```
+    pub fn depth(&self) -> Option<usize> {
+        depth(self.matcher.tokens(), self.matcher.is_separator())
+    }
+
+    /// Returns every path component of the pattern that is a plain string, along with where it is,
+    /// e.g. to look up candidate paths in an index before matching them.
+    ///
+    /// Components are counted from the start up to the first `**` and from the end after the last one,
+    /// a component between two of them is [floating](SegmentPos::Floating).
+    /// A `*` is taken to stay within its component, as with [`MatchOptions::require_literal_separator`].
+    /// ```rust
+    /// # use globber::{Pattern, SegmentPos};
+    /// let pattern = Pattern::new("a/*/b/**/c.rs").unwrap();
+    /// assert_eq!(pattern.literal_segments(), vec![
+    ///     (SegmentPos::Start(0), "a".to_string()),
+    ///     (SegmentPos::Start(2), "b".to_string()),
+    ///     (SegmentPos::End(0), "c.rs".to_string()),
+    /// ]);
+    /// ```
+    pub fn literal_segments(&self) -> Vec<(SegmentPos, String)> {
+        literal_segments(self.matcher.tokens(), self.matcher.is_separator())
+    }
+
+    /// Returns the extension every match ends with, if the pattern pins one after its last wildcard.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// assert_eq!(Pattern::new("*.rs").unwrap().required_extension(), Some("rs".into()));
+    /// assert_eq!(Pattern::new("src/**/test.rs").unwrap().required_extension(), Some("rs".into()));
+    /// assert_eq!(Pattern::new("*.@(rs|toml)").unwrap().required_extension(), None);
+    /// ```
+    pub fn required_extension(&self) -> Option<String> {
+        required_extension(self.matcher.tokens(), self.matcher.is_separator())
+    }
+
+    /// Describes the pattern as an indented tree, one token per line, which helps to understand
+    /// how a complicated pattern was parsed. `{:?}` still gives the compact form.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/!(*.rs|mod.*)").unwrap();
+    /// assert_eq!(pattern.explain(), "\
+    /// literal \"src/\"
+    /// anything except
+    ///   - any sequence (*)
+    ///     literal \".rs\"
+    ///   - literal \"mod.\"
+    ///     any sequence (*)
+    /// ");
+    /// ```
+    pub fn explain(&self) -> String {
+        explain(self.matcher.tokens())
+    }
+
+    /// Explains how the input matched or why it didn't, e.g. to point out the part of
+    /// a user supplied pattern that stopped a path from matching.
+    /// ```rust
+    /// # use globber::{MatchExplanation, Pattern};
+    /// let pattern = Pattern::new("src/*.rs").unwrap();
+    /// assert_eq!(pattern.explain_match("src/lib.rs"),
+    ///            MatchExplanation::Matched(vec![0..1, 1..2, 2..3, 3..4, 4..7, 7..8, 8..9, 9..10]));
+    /// // the r of .rs didn't match the c at offset 8
+    /// assert_eq!(pattern.explain_match("src/lib.c"), MatchExplanation::Failed { token: Some(6), offset: 8 });
+    /// ```
+    pub fn explain_match(&self, input: &str) -> MatchExplanation {
+        self.matcher.explain_match(input, &self.options)
+    }
+
+    /// Maps the input of a match onto the tokens of the pattern, e.g. for an editor to highlight
+    /// which part of a path each part of the pattern matched. Returns `None` if the input doesn't match.
+    ///
+    /// When there are several ways to match, like `*a*` on `aaa`, this is the one the matcher settled on
+    /// after backtracking, the same as [`captures`](Pattern::captures) returns. Tokens that consumed
+    /// nothing, like a `*` matching no chars, are left out so the ranges cover the input in order.
+    /// ```rust
+    /// # use globber::{Pattern, TokenId};
+    /// let pattern = Pattern::new("*.rs").unwrap();
+    /// assert_eq!(pattern.match_trace("lib.rs"),
+    ///            Some(vec![(0..3, TokenId(0)), (3..4, TokenId(1)), (4..5, TokenId(2)), (5..6, TokenId(3))]));
+    /// assert_eq!(pattern.match_trace("lib.c"), None);
+    /// ```
+    pub fn match_trace(&self, input: &str) -> Option<Vec<(Range<usize>, TokenId)>> {
+        trace(self.explain_match(input))
+    }
+
+    /// Returns the parsed pattern so it can be extended and compiled again.
+    /// It compiles with the default separators, even if this pattern was built with others.
+    pub fn ast(&self) -> Ast {
+        Ast::from_tokens(self.matcher.tokens().to_vec())
+    }
+
+    /// Returns true if some input matches both patterns, e.g. to warn about rules in a configuration that conflict.
+    ///
+    /// This is exact for patterns without patterns like `@(...)`, for those it is only ruled out when the
+    /// literal start or end of the patterns differ or no input could have a length both allow,
+    /// so it can be true even though they never match the same input.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let rust = Pattern::new("*.rs").unwrap();
+    /// assert!(rust.overlaps(&Pattern::new("main.*").unwrap()));
+    /// assert!(!rust.overlaps(&Pattern::new("*.toml").unwrap()));
+    /// ```
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        overlaps(&self.matcher, &other.matcher)
+    }
+
+    /// Returns the tokens both patterns start with as a pattern of their own, e.g. to group patterns
+    /// in a trie. Patterns are compared as parsed, so `[ab]` and `[ba]` are the same but `**/**` and `**` are not,
+    /// see [`canonical`](Pattern::canonical).
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let a = Pattern::new("src/foo*.rs").unwrap();
+    /// let b = Pattern::new("src/foobar.rs").unwrap();
+    /// assert_eq!(a.common_prefix(&b), Pattern::new("src/foo").unwrap());
+    /// ```
+    pub fn common_prefix(&self, other: &Pattern) -> Pattern {
+        let tokens = self.matcher.tokens();
+        let len = tokens.iter()
+            .zip(other.matcher.tokens())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        Self {
+            matcher: Matcher::new(tokens[..len].to_vec(), self.matcher.is_separator()),
+            options: self.options,
+        }
+    }
+
+    /// Returns the pattern in a canonical form, e.g. `**/**` becomes `**` and `[ca-b]` becomes `[a-c]`.
+    /// Patterns that only differ in such ways compare and hash equal once canonical.
+    pub fn canonical(&self) -> Pattern {
+        Self {
+            matcher: Matcher::new(canonicalize(self.matcher.tokens()), self.matcher.is_separator()),
+            options: self.options,
+        }
+    }
+
+    /// Returns the pattern rewritten to match quicker, matching exactly the same strings with every option.
+    /// Meant for generated patterns, e.g. a range of a single char like `[a]` becomes the char itself
+    /// so `[a]bc` is matched as the literal `abc`.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("[a]bc").unwrap().optimize();
+    /// assert_eq!(pattern, Pattern::new("abc").unwrap());
+    /// assert!(pattern.is_literal());
+    /// ```
+    pub fn optimize(&self) -> Pattern {
+        Self {
+            matcher: Matcher::new(simplify_classes(self.matcher.tokens(), self.matcher.is_separator()), self.matcher.is_separator()),
+            options: self.options,
+        }
+    }
+
+    /// Returns true if both patterns match the same strings, which `==` is too strict for since it compares
+    /// how they were written, e.g. `**/**` and `**` or `[ab]` and `[ba]` are equivalent but not equal.
+    ///
+    /// Both patterns are compared in their [`canonical`](Pattern::canonical) form, so `true` is always right
+    /// while `false` only means they differ in a way that isn't canonicalized, like `a*` and `a*(?)`,
+    /// which doesn't rule out that they match the same strings. Patterns with different separators are never equivalent.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/**/**/@(b|a).[ba]").unwrap();
+    /// assert!(pattern.equivalent(&Pattern::new("src/**/@(a|b).[ab]").unwrap()));
+    /// assert!(!pattern.equivalent(&Pattern::new("src/**/@(a|b).a").unwrap()));
+    /// ```
+    pub fn equivalent(&self, other: &Pattern) -> bool {
+        self.canonical() == other.canonical()
+    }
+
+    /// Writes the [`canonical`](Pattern::canonical) form of the pattern back in glob syntax, e.g. to store patterns
+    /// so that [`equivalent`](Pattern::equivalent) ones are stored the same. Ranges come out sorted and merged,
+    /// `**/**` as `**` and every char that means something in a pattern escaped with `\`,
+    /// even where it wouldn't have to be. Parsing the string gives back the canonical pattern.
+    ///
+    /// Separators and options aren't part of the string.
+    /// ```rust
+    /// # use globber::Pattern;
+    /// let pattern = Pattern::new("src/**/**/@(b|a).[cba]").unwrap();
+    /// assert_eq!(pattern.to_glob_string_normalized(), "src/**/@(a|b).[a-c]");
+    /// assert_eq!(Pattern::new("a+b").unwrap().to_glob_string_normalized(), "a\\+b");
+    /// ```
+    pub fn to_glob_string_normalized(&self) -> String {
+        render(&canonicalize(self.matcher.tokens()))
     }
 }
 
@@ -98,15 +1063,7 @@ impl FromStr for Pattern {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            matcher: Matcher::new(
-                parse(s)
-                    .map_err(|e| Error {
-                        inner: e,
-                        input: s.to_owned(),
-                    })?
-            ),
-        })
+        Ok(Ast::parse(s)?.compile_with(options::is_separator))
     }
 }
 
@@ -114,34 +1071,94 @@ impl FromStr for Pattern {
 pub struct Error {
     pub(crate) inner: SyntaxError,
     pub(crate) input: String,
+    pub(crate) line: Option<usize>,
+}
+
+impl Error {
+    pub(crate) fn new(inner: SyntaxError, input: &str) -> Self {
+        Self {
+            inner,
+            input: input.to_owned(),
+            line: None,
+        }
+    }
+
+    /// What went wrong, e.g. to describe the error in another language.
+    /// ```rust
+    /// # use globber::{ErrorKind, Pattern};
+    /// let err = Pattern::new("src/[a-z").unwrap_err();
+    /// let message = match err.kind() {
+    ///     ErrorKind::UnclosedRange => "Bereich ohne ]",
+    ///     kind => kind.desc(),
+    /// };
+    /// assert_eq!(message, "Bereich ohne ]");
+    /// assert_eq!(err.position(), 4);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        self.inner.kind()
+    }
+
+    /// The description of the error in English, which is what `Display` shows.
+    pub fn desc(&self) -> &'static str {
+        self.inner.kind().desc()
+    }
+
+    /// The offset in chars into the pattern the error points at.
+    pub fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    /// The pattern that failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The line of the failed pattern, starting at 1, when it was parsed as part of a list
+    /// e.g. by [`GlobSet::from_lines`].
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
         let mut pos = String::new();
-        match self.inner {
-            SyntaxError::EmptyPattern(i) | SyntaxError::UnclosedPattern(i)
-            | SyntaxError::IllegalChar(i) | SyntaxError::IllegalOr(i)
-            | SyntaxError::UnclosedRange(i) | SyntaxError::EmptyRange(i)
-            | SyntaxError::IllegalWildcard(i) | SyntaxError::IllegalRecursion(i)
-            | SyntaxError::IllegalEscape(i) => {
-                for _ in 0..i { pos.push('-') }
-                pos.push('^');
-            }
+        for _ in 0..self.inner.position() { pos.push('-') }
+        pos.push('^');
+        if let Some(line) = self.line {
+            writeln!(f, "Glob syntax error on line {}", line)?;
+        } else {
+            writeln!(f, "Glob syntax error")?;
         }
         write!(f,
-               "Glob syntax error\n-------------------------------------\n{}\n{}\n{}\n-------------------------------------",
+               "-------------------------------------\n{}\n{}\n{}\n-------------------------------------",
                self.inner.desc(), self.input, pos
         )
     }
 }
 
+#[cfg(feature = "std")]
 impl StdError for Error {}
 
+/// Matching took longer than allowed, see [`Pattern::matches_with_timeout`].
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Timeout;
+
+#[cfg(feature = "std")]
+impl Display for Timeout {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "matching timed out")
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for Timeout {}
+
 #[cfg(test)]
 mod tests {
-    use super::Pattern;
-    use super::syntax::Error;
+    use super::{Complexity, MatchOptions, Pattern, PatternBuilder, PatternStats};
+    use super::syntax::{CharSpecifier, Error, Token};
 
     #[test]
     fn wildcards() {
@@ -167,6 +1184,8 @@ mod tests {
         assert!(Pattern::new("/**/[xyz]").unwrap().matches("/a/z"));
         assert!(Pattern::new("*.log").unwrap().matches("sys.log"));
         assert!(Pattern::new("sys.*").unwrap().matches("sys.log"));
+        assert!(Pattern::new("prefix*").unwrap().matches(&format!("prefix{}", "x".repeat(100_000))));
+        assert!(!Pattern::new("prefix*").unwrap().matches(&format!("prefiy{}", "x".repeat(100_000))));
 
         let p = Pattern::new("some/**/needle.txt").unwrap();
         assert!(p.matches("some/needle.txt"));
@@ -221,106 +1240,1801 @@ mod tests {
     }
 
     #[test]
-    fn ranges() {
-        let p = Pattern::new("a[a-z]c").unwrap();
-        assert!(p.matches("aac"));
-        assert!(p.matches("aec"));
-        assert!(p.matches("azc"));
-        assert!(!p.matches("a0c"));
-        assert!(!p.matches("aAc"));
-        assert!(!p.matches("aBc"));
-        assert!(!p.matches("aZc"));
+    fn empty() {
+        let p = Pattern::new("").unwrap();
+        assert!(p.is_literal());
+        assert!(p.matches(""));
+        assert!(!p.matches("a"));
+        assert!(!p.matches("/"));
+
+        assert!(Pattern::new("*").unwrap().matches(""));
+        assert!(Pattern::new("**").unwrap().matches(""));
     }
 
     #[test]
-    fn range_errors() {
-        assert_eq!(Pattern::new("[!]").unwrap_err().inner, Error::EmptyRange(0));
-        assert_eq!(Pattern::new("[]").unwrap_err().inner, Error::EmptyRange(0));
-        assert_eq!(Pattern::new("[]]]]]").unwrap_err().inner, Error::EmptyRange(0));
-        assert_eq!(Pattern::new("[dfsfsdfsdf").unwrap_err().inner, Error::UnclosedRange(10));
-        assert_eq!(Pattern::new("[!sdfdsfdf").unwrap_err().inner, Error::UnclosedRange(9));
-        assert_eq!(Pattern::new("abc[def").unwrap_err().inner, Error::UnclosedRange(6));
-        assert_eq!(Pattern::new("abc[!def").unwrap_err().inner, Error::UnclosedRange(7));
-        assert_eq!(Pattern::new("abc[").unwrap_err().inner, Error::UnclosedRange(3));
-        assert_eq!(Pattern::new("abc[!").unwrap_err().inner, Error::UnclosedRange(4));
-        assert_eq!(Pattern::new("abc[d").unwrap_err().inner, Error::UnclosedRange(4));
-        assert_eq!(Pattern::new("abc[!d").unwrap_err().inner, Error::UnclosedRange(5));
-        assert_eq!(Pattern::new("abc[]").unwrap_err().inner, Error::EmptyRange(3));
-        assert_eq!(Pattern::new("abc[!]").unwrap_err().inner, Error::EmptyRange(3));
-        assert_eq!(Pattern::new("abc[!]").unwrap_err().inner, Error::EmptyRange(3));
-        assert_eq!(Pattern::new("[adc(]").unwrap_err().inner, Error::IllegalChar(4));
-        assert_eq!(Pattern::new("[adc[]").unwrap_err().inner, Error::IllegalChar(4));
-        assert_eq!(Pattern::new("[adc]]").unwrap_err().inner, Error::IllegalChar(5));
-        assert_eq!(Pattern::new("[adc)]").unwrap_err().inner, Error::IllegalChar(4));
+    fn literals() {
+        let p = Pattern::new("src/main.rs").unwrap();
+        assert!(p.is_literal());
+        assert!(p.matches("src/main.rs"));
+        assert!(!p.matches("src/main.rs2"));
+        assert!(!p.matches("src/main.r"));
+        assert!(!p.matches("/src/main.rs"));
+        assert!(!p.matches(""));
+
+        let p = Pattern::new("star\\*").unwrap();
+        assert!(p.is_literal());
+        assert!(p.matches("star*"));
+        assert!(!p.matches("starlight"));
+
+        assert!(!Pattern::new("*.rs").unwrap().is_literal());
+        assert!(!Pattern::new("a?c").unwrap().is_literal());
+        assert!(!Pattern::new("[abc]").unwrap().is_literal());
+        assert!(!Pattern::new("@(a|b)").unwrap().is_literal());
     }
 
     #[test]
-    fn pattern_matches() {
-        let txt_pat = Pattern::new("*hello.txt").unwrap();
-        assert!(txt_pat.matches("hello.txt"));
-        assert!(txt_pat.matches("gareth_says_hello.txt"));
-        assert!(txt_pat.matches("some/path/to/hello.txt"));
-        assert!(txt_pat.matches("some\\path\\to\\hello.txt"));
-        assert!(txt_pat.matches("/an/absolute/path/to/hello.txt"));
-        assert!(!txt_pat.matches("hello.txt-and-then-some"));
-        assert!(!txt_pat.matches("goodbye.txt"));
+    fn canonical() {
+        let same = [
+            ("**/**", "**"),
+            ("a/**/**/**/b", "a/**/b"),
+            ("**/**/*.rs", "**/*.rs"),
+            ("[ba]", "[ab]"),
+            ("[a-cb-d]", "[a-d]"),
+            ("[a-ce-g]", "[e-ga-c]"),
+            ("[abcd]", "[a-d]"),
+            ("[a-a]", "[a]"),
+            ("[!zyx]", "[!x-z]"),
+            ("[2-1a]", "[a]"),
+            ("@(b|a)", "@(a|b)"),
+            ("!(b|a|b)", "!(a|b)"),
+            ("*(b|[ba])", "*([ab]|b)"),
+            ("@(x|+([ba]|[ab]))", "@(+([a-b])|x)"),
+            ("@(a|a)", "@(a)"),
+            ("?(b|a|b)", "?(a|b)"),
+        ];
+        for &(a, b) in same.iter() {
+            assert_eq!(Pattern::new(a).unwrap().canonical(), Pattern::new(b).unwrap().canonical(), "{} {}", a, b);
+        }
 
-        let dir_pat = Pattern::new("*some/path/to/hello.txt").unwrap();
-        assert!(dir_pat.matches("some/path/to/hello.txt"));
-        assert!(dir_pat.matches("a/bigger/some/path/to/hello.txt"));
-        assert!(!dir_pat.matches("some/path/to/hello.txt-and-then-some"));
-        assert!(!dir_pat.matches("some/other/path/to/hello.txt"));
+        assert_ne!(Pattern::new("**/*").unwrap().canonical(), Pattern::new("**").unwrap().canonical());
+        assert_ne!(Pattern::new("[a-c]").unwrap().canonical(), Pattern::new("[a-d]").unwrap().canonical());
+
+        for &(a, b) in same.iter() {
+            assert!(Pattern::new(a).unwrap().equivalent(&Pattern::new(b).unwrap()), "{} {}", a, b);
+        }
+        assert!(!Pattern::new("**/*").unwrap().equivalent(&Pattern::new("**").unwrap()));
+        assert!(!Pattern::new("[ab]").unwrap().equivalent(&Pattern::new("[abc]").unwrap()));
+        assert!(!Pattern::new("**").unwrap().equivalent(&PatternBuilder::new("**").separators(|c| c == ':').build().unwrap()));
+
+        for &(a, b) in same.iter() {
+            let normalized = Pattern::new(a).unwrap().to_glob_string_normalized();
+            assert_eq!(normalized, Pattern::new(b).unwrap().to_glob_string_normalized(), "{} {}", a, b);
+            assert_eq!(Pattern::new(&normalized).unwrap(), Pattern::new(a).unwrap().canonical(), "{} {}", a, normalized);
+        }
+        assert_eq!(Pattern::new("[!zyx]a\\b").unwrap().to_glob_string_normalized(), "[!x-z]ab");
+
+        // a range with nothing left in it still parses back
+        for &(p, normalized) in [("x[z-a]", "x[z-a]"), ("x[!c-a]", "x[!z-a]"), ("[b-a]y", "[z-a]y")].iter() {
+            let pattern = Pattern::new(p).unwrap();
+            assert_eq!(pattern.to_glob_string_normalized(), normalized);
+            assert_eq!(Pattern::new(normalized).unwrap(), pattern.canonical(), "{}", p);
+        }
+        assert!(!Pattern::new("x[z-a]").unwrap().matches("xq"));
+        assert!(Pattern::new("x[!z-a]").unwrap().matches("xq"));
+        assert_eq!(Pattern::new("@<x>(b|a)\\*").unwrap().to_glob_string_normalized(), "@<x>(a|b)\\*");
+        assert_ne!(Pattern::new("**/*").unwrap().to_glob_string_normalized(), Pattern::new("**").unwrap().to_glob_string_normalized());
+
+        let p = Pattern::new("some/**/**/needle.txt").unwrap().canonical();
+        assert!(p.matches("some/needle.txt"));
+        assert!(p.matches("some/one/two/needle.txt"));
+        assert!(!p.matches("some/other/notthis.txt"));
+
+        let p = Pattern::new("[a-cb-dx]").unwrap().canonical();
+        assert!(p.matches("a"));
+        assert!(p.matches("d"));
+        assert!(p.matches("x"));
+        assert!(!p.matches("e"));
     }
 
     #[test]
-    fn ranges_plus() {
-        let pat = Pattern::new("a[0-9]b").unwrap();
-        for i in 0..10 {
-            assert!(pat.matches(&format!("a{}b", i)));
-        }
-        assert!(!pat.matches("a_b"));
+    fn required_extension() {
+        let extensions = [
+            ("*.rs", Some("rs")),
+            ("src/**/test.rs", Some("rs")),
+            ("test.rs", Some("rs")),
+            ("*.tar.gz", Some("gz")),
+            ("src/*/mod.rs", Some("rs")),
+            ("*.[rt]s", None),
+            ("*.@(rs|toml)", None),
+            ("*.rs.*", None),
+            ("*", None),
+            ("*rs", None),
+            ("*.", None),
+            ("src/*.rs/", None),
+            (".gitignore", None),
+            ("**/.gitignore", None),
+            ("*.gitignore", Some("gitignore")),
+            ("", None),
+        ];
 
-        let pat = Pattern::new("a[!0-9]b").unwrap();
-        for i in 0..10 {
-            assert!(!pat.matches(&format!("a{}b", i)));
+        for &(p, extension) in extensions.iter() {
+            assert_eq!(Pattern::new(p).unwrap().required_extension().as_deref(), extension, "{}", p);
         }
-        assert!(pat.matches("a_b"));
+    }
 
-        let pats = ["[a-z123]", "[1a-z23]", "[123a-z]"];
-        for &p in pats.iter() {
-            let pat = Pattern::new(p).unwrap();
-            for c in "abcdefghijklmnopqrstuvwxyz".chars() {
-                assert!(pat.matches(&c.to_string()));
+    #[test]
+    fn recursive_zero_segments() {
+        let patterns = ["**/x", "**/**/x", "**/**/**/x", "**/?(a/)x", "*(**/)x", "@(**)/x"];
+        for p in patterns.iter() {
+            let p = Pattern::new(p).unwrap();
+            for input in ["x", "a/x", "a/b/x", "/x"].iter() {
+                assert!(p.matches(input), "{:?} {}", p, input);
+                assert!(p.canonical().matches(input), "{:?} {}", p, input);
             }
-            assert!(pat.matches("1"));
-            assert!(pat.matches("2"));
-            assert!(pat.matches("3"));
+            assert!(!p.matches(""), "{:?}", p);
+            assert!(!p.matches("ax"), "{:?}", p);
+            assert!(!p.matches("a/x/b"), "{:?}", p);
         }
 
-        let pats = ["[abc-]", "[-abc]", "[a-c-]"];
-        for &p in pats.iter() {
-            let pat = Pattern::new(p).unwrap();
-            assert!(pat.matches("a"));
-            assert!(pat.matches("b"));
-            assert!(pat.matches("c"));
-            assert!(pat.matches("-"));
-            assert!(!pat.matches("d"));
+        for p in ["**/*", "**/**", "**/**/*", "**"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches(""), "{:?}", p);
+            assert!(p.matches("a"), "{:?}", p);
+            assert!(p.matches("a/b"), "{:?}", p);
         }
 
-        let pat = Pattern::new("[2-1]").unwrap();
-        assert!(!pat.matches("1"));
-        assert!(!pat.matches("2"));
+        let p = Pattern::new("**/x/**/y").unwrap();
+        assert!(p.matches("x/y"));
+        assert!(p.matches("a/x/b/y"));
+        assert!(!p.matches("xy"));
 
-        assert!(Pattern::new("[-]").unwrap().matches("-"));
-        assert!(!Pattern::new("[!-]").unwrap().matches("-"));
+        let p = Pattern::new("**/**/*.rs").unwrap();
+        assert!(p.matches("main.rs"));
+        assert!(p.matches("src/bin/main.rs"));
+        assert_eq!(p.captures("main.rs"), Some(vec!["", "", "main"]));
+
+        let p = Pattern::new("**/@(a|b)").unwrap();
+        assert!(p.matches("a"));
+        assert!(p.matches("x/b"));
+        assert!(!p.matches("c"));
     }
 
     #[test]
-    fn zero_or_one() {
+    fn at_least_one_segment() {
+        let zero = Pattern::new("a/**/b").unwrap();
+        let one = Pattern::new("a/**+/b").unwrap();
+        for input in ["a/x/b", "a/x/y/b", "a/x/y/z/b"].iter() {
+            assert!(zero.matches(input), "{}", input);
+            assert!(one.matches(input), "{}", input);
+        }
+        assert!(zero.matches("a/b"));
+        assert!(!one.matches("a/b"));
+        assert!(!one.matches("a/x/c"));
+
+        let p = Pattern::new("**+/x").unwrap();
+        assert!(p.matches("a/x"));
+        assert!(p.matches("a/b/x"));
+        assert!(!p.matches("x"));
+        assert!(!p.matches("/x"));
+
+        let p = Pattern::new("src/**+").unwrap();
+        assert!(p.matches("src/a"));
+        assert!(p.matches("src/a/b.rs"));
+        assert!(!p.matches("src/"));
+        assert!(!p.matches("src"));
+        assert_eq!(p.captures("src/a/b.rs"), Some(vec!["a/b.rs"]));
+        assert_eq!(p.len_bounds(), (5, None));
+        assert_eq!(p.depth(), None);
+        assert!(Pattern::new("src/**").unwrap().matches("src/"));
+
+        let mut options = MatchOptions::new();
+        options.max_depth = Some(1);
+        assert!(one.matches_with("a/x/b", options));
+        assert!(!one.matches_with("a/x/y/b", options));
+
+        assert_eq!(Pattern::new("a/**+b").unwrap_err().inner, Error::IllegalRecursion(5));
+        assert_eq!(Pattern::new("**+*").unwrap_err().inner, Error::IllegalWildcard(3));
+        assert_eq!(Pattern::new("**++").unwrap_err().inner, Error::IllegalRecursion(3));
+        assert_ne!(one, zero);
+    }
+
+    #[test]
+    fn is_glob() {
+        for p in ["*", "?", "a/**/b", "[ab]", "@(a|b)", "!(a)", "+(a)", "a]", "a|b", "\\", "a\\", "\\**", "\\\\*"].iter() {
+            assert!(super::is_glob(p), "{}", p);
+        }
+        for p in ["", "a", "src/main.rs", "a@b!c+d", "\\*", "a\\?b\\[c\\]", "\\(\\|\\)", "\\\\", "C:\\\\x"].iter() {
+            assert!(!super::is_glob(p), "{}", p);
+            assert!(Pattern::new(p).unwrap().is_literal(), "{}", p);
+        }
+    }
+
+    #[test]
+    fn optimize() {
+        let p = Pattern::new("[a]bc").unwrap().optimize();
+        assert_eq!(p, Pattern::new("abc").unwrap());
+        assert!(p.matches("abc"));
+        assert!(!p.matches("bbc"));
+        assert!(!p.matches("Abc"));
+
+        let same = [
+            ("[a-a]*[!b]", "a*[!b]"),
+            ("@([x]|[xy])", "@(x|[xy])"),
+            ("@<n>([a])[b]?(c[d])", "@<n>(a)b?(cd)"),
+            ("[\\*]", "\\*"),
+            // separators behave differently from a range
+            ("a/[/]/**/[/]b", "a/[/]/**/[/]b"),
+            ("[\\\\]", "[\\\\]"),
+        ];
+        for &(a, b) in same.iter() {
+            assert_eq!(Pattern::new(a).unwrap().optimize(), Pattern::new(b).unwrap(), "{}", a);
+        }
+
+        let p = PatternBuilder::new("[:][x]").separators(|c| c == ':').build().unwrap().optimize();
+        assert_eq!(p.matcher.tokens()[1], Token::Char('x'));
+        assert_ne!(p.matcher.tokens()[0], Token::Char(':'));
+
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        assert!(Pattern::new("[a]bc").unwrap().optimize().matches_with("ABC", options));
+    }
+
+    #[test]
+    fn captures() {
+        let p = Pattern::new("a*b*c").unwrap();
+        let mut lazy = MatchOptions::new();
+        lazy.lazy = true;
+        assert_eq!(p.captures("axbybzc"), Some(vec!["xby", "z"]));
+        assert_eq!(p.captures_with("axbybzc", lazy), Some(vec!["x", "ybz"]));
+        assert_eq!(p.captures("abc"), Some(vec!["", ""]));
+        assert_eq!(p.captures_with("abc", lazy), Some(vec!["", ""]));
+        assert_eq!(p.captures("axbyc!"), None);
+        assert_eq!(p.captures_with("axbyc!", lazy), None);
+
+        let p = Pattern::new("src/**/*.rs").unwrap();
+        assert_eq!(p.captures("src/a/b/main.rs"), Some(vec!["a/b", "main"]));
+        assert_eq!(p.captures_with("src/a/b/main.rs", lazy), Some(vec!["", "a/b/main"]));
+        assert_eq!(p.captures("src/main.rs"), Some(vec!["", "main"]));
+        assert_eq!(p.captures_with("src/main.rs", lazy), Some(vec!["", "main"]));
+
+        // wildcards after and inside patterns
+        let p = Pattern::new("@(a|b)*.*").unwrap();
+        assert_eq!(p.captures("a.tar.gz"), Some(vec![".tar", "gz"]));
+        assert_eq!(p.captures_with("a.tar.gz", lazy), Some(vec!["", "tar.gz"]));
+        assert_eq!(p.captures("bx.tar.gz"), Some(vec!["x.tar", "gz"]));
+        assert_eq!(p.captures_with("bx.tar.gz", lazy), Some(vec!["x", "tar.gz"]));
+        let p = Pattern::new("*-@(*.rs|*.toml)").unwrap();
+        assert_eq!(p.captures("a-b-c.rs"), Some(vec!["a-b"]));
+        assert_eq!(p.captures_with("a-b-c.rs", lazy), Some(vec!["a"]));
+
+        assert_eq!(Pattern::new("abc").unwrap().captures("abc"), Some(vec![]));
+        assert_eq!(Pattern::new("abc").unwrap().captures("abd"), None);
+        assert_eq!(Pattern::new("*").unwrap().captures(""), Some(vec![""]));
+
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        assert_eq!(Pattern::new("*.RS").unwrap().captures_with("main.rs", options), Some(vec!["main"]));
+        assert_eq!(Pattern::new("*.RS").unwrap().captures("main.rs"), None);
+
+        let patterns = ["*", "a*", "*a*b", "**/test", "a*a*a*a*b", "!(*.rs)", "@(a|b)*", "*/**/*.rs", "*.@(rs|toml)"];
+        let inputs = ["", "a", "ab", "aab", "test", "a/b/test", "main.rs", "src/a/b.rs", "a.toml", "aaaaaaab", "é/ü.rs"];
+        for p in patterns.iter() {
+            let p = Pattern::new(p).unwrap();
+            for input in inputs.iter() {
+                assert_eq!(p.captures(input).is_some(), p.matches(input), "{:?} {}", p, input);
+                assert_eq!(p.captures_with(input, lazy).is_some(), p.matches(input), "{:?} {}", p, input);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn matches_normalized() {
+        let composed = "caf\u{e9}.txt";
+        let decomposed = "cafe\u{301}.txt";
+
+        for pattern in [composed, decomposed, "caf?.txt", "*\u{e9}.txt", "@(caf\u{e9}|tea).txt"].iter() {
+            let p = Pattern::new(pattern).unwrap();
+            assert!(p.matches_normalized(composed), "{}", pattern);
+            assert!(p.matches_normalized(decomposed), "{}", pattern);
+        }
+        assert!(!Pattern::new(composed).unwrap().matches(decomposed));
+        assert!(!Pattern::new(decomposed).unwrap().matches(composed));
+        assert!(!Pattern::new("caf?.txt").unwrap().matches(decomposed));
+        assert!(!Pattern::new(composed).unwrap().matches_normalized("cafe.txt"));
+
+        // an accent after a wildcard isn't composed with anything in the pattern
+        assert!(!Pattern::new("caf[e]\u{301}.txt").unwrap().matches_normalized(decomposed));
+
+        // case folding only knows ASCII letters, which composing can take away
+        let options = MatchOptions { case_insensitive: true, ..MatchOptions::new() };
+        let p = Pattern::new("cafe\u{301}.txt").unwrap();
+        assert!(p.matches_with("CAFE\u{301}.TXT", options));
+        assert!(!p.matches_normalized_with("CAFE\u{301}.TXT", options));
+        assert!(p.matches_normalized_with("CAF\u{e9}.TXT", options));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn matches_graphemes() {
+        let flag = "\u{1F1E8}\u{1F1E6}";
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let accented = "e\u{301}";
+
+        let p = Pattern::new("?").unwrap();
+        for cluster in [flag, family, accented, "a"].iter() {
+            assert!(p.matches_graphemes(cluster), "{}", cluster);
+        }
+        assert!(!p.matches(flag));
+        assert!(!p.matches(family));
+        assert!(!p.matches(accented));
+        assert!(!p.matches_graphemes(&format!("{}{}", flag, flag)));
+        assert!(Pattern::new("??").unwrap().matches(flag));
+        assert!(!Pattern::new("??").unwrap().matches_graphemes(flag));
+
+        let p = Pattern::new("icon-?.png").unwrap();
+        assert!(p.matches_graphemes(&format!("icon-{}.png", family)));
+        assert!(!p.matches(&format!("icon-{}.png", family)));
+
+        // a range looks at the first char of a cluster
+        let p = Pattern::new("caf[a-z]").unwrap();
+        assert!(p.matches_graphemes("cafe\u{301}"));
+        assert!(!p.matches("cafe\u{301}"));
+        assert!(Pattern::new("caf[!a-z]").unwrap().matches_graphemes(&format!("caf{}", flag)));
+
+        // wildcards end between clusters, chars still match chars
+        assert!(!Pattern::new("caf*\u{301}").unwrap().matches_graphemes("cafe\u{301}"));
+        assert!(Pattern::new("caf*\u{301}").unwrap().matches("cafe\u{301}"));
+        assert!(Pattern::new("cafe\u{301}").unwrap().matches_graphemes("cafe\u{301}"));
+        assert!(!Pattern::new("cafe*").unwrap().matches_graphemes("cafe\u{301}"));
+        assert!(!Pattern::new("cafe*x").unwrap().matches_graphemes("cafe\u{301}x"));
+        assert!(Pattern::new("cafe*\u{301}x").unwrap().matches_graphemes("cafe\u{301}x"));
+        assert!(Pattern::new("*.txt").unwrap().matches_graphemes(&format!("{}.txt", family)));
+        assert!(Pattern::new("a/**/?").unwrap().matches_graphemes(&format!("a/b/{}", flag)));
+
+        let options = MatchOptions { unanchored_start: true, ..MatchOptions::new() };
+        assert!(!Pattern::new("\u{1F1E6}").unwrap().matches_graphemes_with(flag, options));
+        assert!(Pattern::new("\u{1F1E6}").unwrap().matches_with(flag, options));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn split_root() {
+        use std::path::PathBuf;
+        use super::PatternBuilder;
+
+        let roots = [
+            ("src/foo/**/*.rs", "src/foo", "**/*.rs"),
+            ("src/foo*/bar", "src", "foo*/bar"),
+            ("src//*.rs", "src", "*.rs"),
+            ("/usr/lib/*.so", "/usr/lib", "*.so"),
+            ("/*.so", "/", "*.so"),
+            ("//*.so", "/", "*.so"),
+            ("C:/Windows/System32/*.dll", "C:/Windows/System32", "*.dll"),
+            ("C:/*.dll", "C:/", "*.dll"),
+            ("**/x", ".", "**/x"),
+            ("*.rs", ".", "*.rs"),
+            ("@(src|tests)/*.rs", ".", "@(src|tests)/*.rs"),
+            ("src/@(a|b)/*.rs", "src", "@(a|b)/*.rs"),
+            ("src/main.rs", "src", "main.rs"),
+            ("main.rs", ".", "main.rs"),
+            ("src/", "src", ""),
+            ("", ".", ""),
+        ];
+        for &(p, base, rest) in roots.iter() {
+            let (b, r) = Pattern::new(p).unwrap().split_root();
+            assert_eq!(b, PathBuf::from(base), "{}", p);
+            assert_eq!(r, Pattern::new(rest).unwrap(), "{}", p);
+        }
+
+        let (base, rest) = PatternBuilder::new("C:\\\\Users\\\\*\\\\**")
+            .separators(|c| c == '/' || c == '\\')
+            .build()
+            .unwrap()
+            .split_root();
+        assert_eq!(base, PathBuf::from("C:\\Users"));
+        assert!(rest.matches("me\\Documents\\a.txt"));
+        assert!(!rest.matches("me"));
+    }
+
+    #[test]
+    fn captures_named() {
+        let p = Pattern::new("*.@<ext>(rs|toml)").unwrap();
+        assert_eq!(p.captures_named("main.rs").unwrap()["ext"], "rs");
+        assert_eq!(p.captures_named("Cargo.toml").unwrap()["ext"], "toml");
+        assert_eq!(p.captures_named("Cargo.lock"), None);
+        assert!(p.matches("lib.rs"));
+        assert!(!p.matches("lib.c"));
+
+        let p = Pattern::new("src/@<dir>(*)/@<file>(*).rs").unwrap();
+        let captures = p.captures_named("src/bin/main.rs").unwrap();
+        assert_eq!(captures.len(), 2);
+        assert_eq!(captures["dir"], "bin");
+        assert_eq!(captures["file"], "main");
+
+        // an optional pattern that was left out captures nothing
+        let p = Pattern::new("lib?<version>(-v+([0-9])).so").unwrap();
+        assert_eq!(p.captures_named("lib-v2.so").unwrap()["version"], "-v2");
+        assert_eq!(p.captures_named("lib.so").unwrap()["version"], "");
+
+        // names can be nested and used more than once
+        let p = Pattern::new("@<all>(@<x>([a-z])@<x>([0-9]))").unwrap();
+        let captures = p.captures_named("a1").unwrap();
+        assert_eq!(captures["all"], "a1");
+        assert_eq!(captures["x"], "1");
+
+        let p = Pattern::new("!<other>(*.rs)").unwrap();
+        assert_eq!(p.captures_named("main.c").unwrap()["other"], "main.c");
+        assert_eq!(p.captures_named("main.rs"), None);
+
+        // ** skipping the separator that closes the pattern
+        let p = Pattern::new("@<dirs>(src/**)/*.rs").unwrap();
+        assert_eq!(p.captures_named("src/a/b/main.rs").unwrap()["dirs"], "src/a/b");
+        assert_eq!(p.captures_named("src/main.rs").unwrap()["dirs"], "src/");
+
+        // wildcards and names are captured independently
+        let p = Pattern::new("*-@<ext>(*)").unwrap();
+        assert_eq!(p.captures("a-b-c"), Some(vec!["a-b"]));
+        assert_eq!(p.captures_named("a-b-c").unwrap()["ext"], "c");
+
+        assert!(Pattern::new("*.rs").unwrap().captures_named("a.rs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn matches_with_branches() {
+        use super::NO_BRANCH;
+
+        let router = Pattern::new("/api/@(v1|v2)/@(users|posts)/@<id>([0-9])?(/@(edit|delete))").unwrap();
+        let branches = |input| router.matches_with_branches(input);
+        assert_eq!(branches("/api/v2/posts/1"), Some(vec![1, 1, 0, NO_BRANCH]));
+        assert_eq!(branches("/api/v1/users/3/delete"), Some(vec![0, 0, 0, 0]));
+        assert_eq!(branches("/api/v3/users/3"), None);
+
+        // the first alternative that lets the rest match is the one reported
+        let p = Pattern::new("@(a*|ab|*b)c").unwrap();
+        assert_eq!(p.matches_with_branches("abc"), Some(vec![0]));
+        assert_eq!(p.matches_with_branches("xbc"), Some(vec![2]));
+        let p = Pattern::new("@(a|ab)c").unwrap();
+        assert_eq!(p.matches_with_branches("abc"), Some(vec![1]));
+
+        // repeated patterns report the alternative they matched with, !(...) never has one
+        let p = Pattern::new("+(x|y)*(1|2)!(z)").unwrap();
+        assert_eq!(p.matches_with_branches("y2w"), Some(vec![1, 1, NO_BRANCH]));
+        assert_eq!(p.matches_with_branches("x"), Some(vec![0, NO_BRANCH, NO_BRANCH]));
+
+        // only patterns outside of other patterns are counted
+        let p = Pattern::new("@(a|@(b|c))").unwrap();
+        assert_eq!(p.matches_with_branches("c"), Some(vec![1]));
+        assert_eq!(Pattern::new("*.rs").unwrap().matches_with_branches("a.rs"), Some(vec![]));
+    }
+
+    #[test]
+    fn named_syntax() {
+        let tokens = |p| Pattern::new(p).unwrap().matcher.tokens().to_vec();
+        assert_eq!(tokens("@<ext>(rs)"), vec![
+            Token::Named("ext".into(), Box::new(Token::ExactlyOne(vec![vec![Token::Char('r'), Token::Char('s')]]))),
+        ]);
+        assert_eq!(tokens("?<a_1>(x)"), vec![
+            Token::Named("a_1".into(), Box::new(Token::ZeroOrOne(vec![vec![Token::Char('x')]]))),
+        ]);
+
+        // without a valid name and ( it's just chars
+        assert_eq!(tokens("*<a>"), vec![Token::AnySequence, Token::Char('<'), Token::Char('a'), Token::Char('>')]);
+        assert_eq!(tokens("@<a-b>"), vec![
+            Token::Char('@'), Token::Char('<'), Token::Char('a'), Token::Char('-'), Token::Char('b'), Token::Char('>'),
+        ]);
+        assert_eq!(Pattern::new("@<>(a)").unwrap_err().inner, Error::IllegalChar(3));
+        assert_eq!(Pattern::new("@<x>()").unwrap_err().inner, Error::EmptyPattern(5));
+        assert_eq!(Pattern::new("@<x>(a|@(b]))").unwrap_err().inner, Error::IllegalChar(10));
+
+        assert_eq!(Pattern::new("@<x>(b|a)").unwrap().canonical(), Pattern::new("@<x>(a|b)").unwrap().canonical());
+        assert_ne!(Pattern::new("@<x>(a|b)").unwrap().canonical(), Pattern::new("@<y>(a|b)").unwrap().canonical());
+        assert_eq!(Pattern::new("src/@<x>(a|b)").unwrap().complexity_hint(), Complexity::Polynomial);
+        assert_eq!(Pattern::new("@<ext>(rs|*.toml)").unwrap().explain(), "\
+exactly one of as <ext>
+  - literal \"rs\"
+  - any sequence (*)
+    literal \".toml\"
+");
+    }
+
+    #[test]
+    fn matches_chars() {
+        let chars: Vec<char> = "src/lib.rs".chars().collect();
+        assert!(Pattern::new("src/*.rs").unwrap().matches_chars(chars.iter().copied()));
+        assert!(Pattern::new("src/lib.rs").unwrap().matches_chars(chars.iter().copied()));
+        assert!(!Pattern::new("src/lib.r").unwrap().matches_chars(chars.iter().copied()));
+        assert!(!Pattern::new("!(src)/*").unwrap().matches_chars(chars.iter().copied()));
+
+        // reversed on the fly
+        assert!(Pattern::new("sr.*/*").unwrap().matches_chars("src/lib.rs".chars().rev()));
+
+        let utf16: Vec<u16> = "dir/é.txt".encode_utf16().collect();
+        let decoded = core::char::decode_utf16(utf16.iter().copied()).map(|c| c.unwrap_or('\u{FFFD}'));
+        assert!(Pattern::new("**/[é].txt").unwrap().matches_chars(decoded.clone()));
+        assert!(!Pattern::new("**/e.txt").unwrap().matches_chars(decoded));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn matches_os_str_lossy() {
+        use std::ffi::OsStr;
+        use std::path::PathBuf;
+
+        let p = Pattern::new("src/**/*.rs").unwrap();
+        assert!(p.matches_os_str_lossy(OsStr::new("src/main.rs")));
+        assert!(p.matches_os_str_lossy(PathBuf::from("src/bin/é.rs").as_os_str()));
+        assert!(!p.matches_os_str_lossy(OsStr::new("src/main.c")));
+        assert!(!p.matches_os_str_lossy(OsStr::new("")));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            let invalid = OsStr::from_bytes(b"src/\xff.rs");
+            assert!(p.matches_os_str_lossy(invalid));
+            assert!(Pattern::new("src/?.rs").unwrap().matches_os_str_lossy(invalid));
+            assert!(Pattern::new("src/\\u{FFFD}.rs").unwrap().matches_os_str_lossy(invalid));
+        }
+    }
+
+    #[test]
+    fn matches_relative() {
+        use std::path::Path;
+
+        let base = Path::new("/home/user/repo");
+        let p = Pattern::new("src/*.rs").unwrap();
+        assert!(p.matches_relative(base, Path::new("/home/user/repo/src/main.rs")));
+        assert!(p.matches_relative(Path::new("/home/user/repo/"), Path::new("/home/user/repo/src/main.rs")));
+        assert!(!p.matches_relative(base, Path::new("/home/user/repo/src/main.c")));
+        assert!(!p.matches_relative(base, Path::new("/home/user/repository/src/main.rs")));
+        assert!(!p.matches_relative(base, Path::new("src/main.rs")));
+        assert!(!p.matches_relative(base, Path::new("/home/user/src/main.rs")));
+
+        assert!(Pattern::new("").unwrap().matches_relative(base, base));
+        assert!(Pattern::new("**/*.rs").unwrap().matches_relative(Path::new("repo"), Path::new("repo/a/b/lib.rs")));
+        assert!(Pattern::new("*").unwrap().matches_relative(Path::new(""), Path::new("Cargo.toml")));
+    }
+
+    #[test]
+    fn dir_only() {
+        let p = Pattern::new("build/").unwrap();
+        assert!(p.is_dir_only());
+        assert!(p.matches_path_kind("build", true));
+        assert!(p.matches_path_kind("build/", true));
+        assert!(p.matches_path_kind("build//", true));
+        assert!(!p.matches_path_kind("build", false));
+        assert!(!p.matches_path_kind("build/", false));
+        assert!(!p.matches_path_kind("builds", true));
+        // plain matching still needs the separator
+        assert!(p.matches("build/"));
+        assert!(!p.matches("build"));
+
+        let p = Pattern::new("src/*/").unwrap();
+        assert!(p.is_dir_only());
+        assert!(p.matches_path_kind("src/bin", true));
+        assert!(!p.matches_path_kind("src/bin", false));
+
+        let p = Pattern::new("build").unwrap();
+        assert!(!p.is_dir_only());
+        assert!(p.matches_path_kind("build", true));
+        assert!(p.matches_path_kind("build", false));
+        assert!(!p.matches_path_kind("build/", true));
+
+        assert!(!Pattern::new("/").unwrap().is_dir_only());
+        assert!(!Pattern::new("").unwrap().is_dir_only());
+        assert!(!Pattern::new("*.rs").unwrap().is_dir_only());
+    }
+
+    #[test]
+    fn require_literal_separator() {
+        let mut options = MatchOptions::new();
+        options.require_literal_separator = true;
+
+        let p = Pattern::new("src/*.rs").unwrap();
+        assert!(p.matches_with("src/main.rs", options));
+        assert!(!p.matches_with("src/bin/main.rs", options));
+        assert!(p.matches("src/bin/main.rs"));
+
+        let p = Pattern::new("src/**/*.rs").unwrap();
+        assert!(p.matches_with("src/main.rs", options));
+        assert!(p.matches_with("src/bin/main.rs", options));
+        assert!(p.matches_with("src/a/b/main.rs", options));
+
+        let p = Pattern::new("*/*").unwrap();
+        assert!(p.matches_with("a/b", options));
+        assert!(!p.matches_with("a/b/c", options));
+        assert!(!Pattern::new("src/*").unwrap().matches_with("src/a/b", options));
+        assert!(Pattern::new("src/*").unwrap().matches_with("src/", options));
+
+        let p = Pattern::new("*/*.rs").unwrap();
+        assert_eq!(p.captures_with("src/main.rs", options), Some(vec!["src", "main"]));
+        assert_eq!(p.captures_with("src/bin/main.rs", options), None);
+        assert_eq!(p.captures("src/bin/main.rs"), Some(vec!["src/bin", "main"]));
+
+        // ? doesn't match a separator either, whichever chars are separators
+        let p = Pattern::new("a?b").unwrap();
+        assert!(p.matches_with("axb", options));
+        assert!(!p.matches_with("a/b", options));
+        assert!(p.matches("axb"));
+        assert!(p.matches("a/b"));
+        let p = PatternBuilder::new("a?b").separators(|c| c == ':').build().unwrap();
+        assert!(!p.matches_with("a:b", options));
+        assert!(p.matches_with("a/b", options));
+        assert!(p.matches("a:b"));
+    }
+
+    #[test]
+    fn require_literal_leading_dot() {
+        let options = MatchOptions { require_literal_leading_dot: true, ..MatchOptions::new() };
+        let matches = |p: &str, input: &str| Pattern::new(p).unwrap().matches_with(input, options);
+
+        assert!(!matches("*", ".bashrc"));
+        assert!(!matches("?bashrc", ".bashrc"));
+        assert!(!matches("[.]bashrc", ".bashrc"));
+        assert!(!matches("[!a]bashrc", ".bashrc"));
+        assert!(matches(".*", ".bashrc"));
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("a*", "a.b"));
+
+        // only at the start of a path component
+        assert!(!matches("src/*", "src/.git"));
+        assert!(!matches("**/*.rs", ".git/x.rs"));
+        assert!(!matches("**/*.rs", "a/.b/x.rs"));
+        assert!(!matches("**+/x.rs", ".git/x.rs"));
+        assert!(matches("**/x.rs", "a/b/x.rs"));
+        assert!(matches("**/.git/*.rs", "a/.git/x.rs"));
+        assert!(matches("src/a.*", "src/a.rs"));
+        assert!(!matches("!(x)", ".y"));
+        assert!(matches("!(x)", "y.z"));
+        assert!(!matches("@(*|b)", ".b"));
+
+        // patterns the nfa would otherwise run agree with backtracking
+        assert!(!matches("*a*b", ".ab"));
+        assert!(matches("*a*b", "xa.b"));
+        assert!(Pattern::new("*").unwrap().matches(".bashrc"));
+
+        let mut unanchored = options;
+        unanchored.unanchored_start = true;
+        assert!(Pattern::new("x.rs").unwrap().matches_with(".a/x.rs", unanchored));
+    }
+
+    #[test]
+    fn max_depth() {
+        let depth = |max| {
+            let mut options = MatchOptions::new();
+            options.max_depth = Some(max);
+            options
+        };
+
+        let p = Pattern::new("a/**/b").unwrap();
+        let inputs = ["a/b", "a/x/b", "a/x/y/b", "a/x/y/z/b"];
+        for max in 0..5 {
+            for (components, input) in inputs.iter().enumerate() {
+                assert_eq!(p.matches_with(input, depth(max)), components <= max, "{} {}", max, input);
+                assert!(p.matches(input));
+            }
+        }
+
+        let p = Pattern::new("src/**").unwrap();
+        assert!(p.matches_with("src/", depth(0)));
+        assert!(!p.matches_with("src/a", depth(0)));
+        assert!(p.matches_with("src/a", depth(1)));
+        assert!(!p.matches_with("src/a/b", depth(1)));
+        assert!(p.matches_with("src/a/b", depth(2)));
+
+        // without a literal separator the * takes the components ** can't
+        let p = Pattern::new("**/*.rs").unwrap();
+        let mut options = depth(1);
+        assert!(p.matches_with("src/bin/main.rs", options));
+        options.require_literal_separator = true;
+        assert!(p.matches_with("main.rs", options));
+        assert!(p.matches_with("src/main.rs", options));
+        assert!(!p.matches_with("src/bin/main.rs", options));
+        assert_eq!(p.captures_with("src/bin/main.rs", depth(1)), Some(vec!["src", "bin/main"]));
+        assert_eq!(p.captures_with("src/bin/main.rs", depth(2)), Some(vec!["src/bin", "main"]));
+
+        // * is never limited
+        assert!(Pattern::new("a/*").unwrap().matches_with("a/x/y/z", depth(0)));
+    }
+
+    #[test]
+    fn unanchored_start() {
+        let mut options = MatchOptions::new();
+        options.unanchored_start = true;
+
+        let p = Pattern::new("foo.txt").unwrap();
+        assert!(p.matches_with("foo.txt", options));
+        assert!(p.matches_with("a/foo.txt", options));
+        assert!(p.matches_with("a/b/xfoo.txt", options));
+        assert!(!p.matches("a/foo.txt"));
+        // unlike searching for the pattern anywhere the end is still anchored
+        assert!(!p.matches_with("foo.txt.bak", options));
+        assert!(!p.matches_with("a/foo.txt/b", options));
+
+        let p = Pattern::new("src/*.rs").unwrap();
+        assert!(p.matches_with("project/src/main.rs", options));
+        assert!(!p.matches_with("project/src/main.rs.bak", options));
+        assert!(!p.matches_with("project/src/main.c", options));
+        assert_eq!(p.captures_with("project/src/main.rs", options), Some(vec!["main"]));
+
+        let p = Pattern::new("a*a*a*b").unwrap();
+        assert!(p.matches_with("xxaaab", options));
+        assert!(!p.matches_with("xxaaabx", options));
+
+        let p = Pattern::new("@(a|b)/!(c)").unwrap();
+        assert!(p.matches_with("x/a/d", options));
+        assert!(!p.matches_with("x/a/c", options));
+
+        assert!(Pattern::new("").unwrap().matches_with("abc", options));
+        assert!(Pattern::new("**").unwrap().matches_with("abc", options));
+
+        options.case_insensitive = true;
+        assert!(Pattern::new("FOO").unwrap().matches_with("a/foo", options));
+        assert!(!Pattern::new("FOO").unwrap().matches_with("a/foo/", options));
+    }
+
+    #[test]
+    fn normalize_separators() {
+        let mut options = MatchOptions::new();
+        options.normalize_separators = true;
+
+        let p = Pattern::new("some/path/*.txt").unwrap();
+        assert!(p.matches_with("some/path/hello.txt", options));
+        assert!(p.matches_with("some\\path\\hello.txt", options));
+        assert!(p.matches_with("some/path\\hello.txt", options));
+        assert!(!p.matches("some\\path\\hello.txt"));
+        assert!(!p.matches_with("some_path_hello.txt", options));
+
+        let p = Pattern::new("some\\\\path").unwrap();
+        assert!(p.matches_with("some/path", options));
+        assert!(p.matches_with("some\\path", options));
+        assert!(!p.matches("some/path"));
+
+        let p = Pattern::new("src/**/main.rs").unwrap();
+        assert!(p.matches_with("src\\main.rs", options));
+        assert!(p.matches_with("src\\bin\\main.rs", options));
+        assert!(!p.matches("src\\main.rs"));
+    }
+
+    #[test]
+    fn complexity_hint() {
+        let hints = [
+            ("abc", Complexity::Linear),
+            ("a?c", Complexity::Linear),
+            ("*", Complexity::Linear),
+            ("**", Complexity::Linear),
+            ("*.log", Complexity::Linear),
+            ("/var/log/**", Complexity::Linear),
+            ("a[a-z]c", Complexity::Linear),
+            ("*hello.txt", Complexity::Linear),
+            ("a*b", Complexity::Linear),
+            ("a*b*c", Complexity::Polynomial),
+            ("some/**/*.rs", Complexity::Polynomial),
+            ("a*a*a*a*a*a*a*a*a", Complexity::Polynomial),
+            ("src/@([a-z]|[a-c]).rs", Complexity::Polynomial),
+            ("src/!([a-z]|[a-c]).rs", Complexity::Polynomial),
+            ("/var/log/!(containers)*/**", Complexity::Polynomial),
+            ("@(a*|b)", Complexity::Exponential),
+            ("!(+(ab|def)*+(.jpg|.gif))", Complexity::Exponential),
+            ("!(!(!(!(vec|test)))).rs", Complexity::Exponential),
+        ];
+        for &(p, hint) in hints.iter() {
+            assert_eq!(Pattern::new(p).unwrap().complexity_hint(), hint, "{}", p);
+        }
+        assert!(Complexity::Linear < Complexity::Polynomial);
+        assert!(Complexity::Polynomial < Complexity::Exponential);
+    }
+
+    #[test]
+    fn from_alternatives() {
+        let p = Pattern::from_alternatives(vec![Pattern::new("*.rs").unwrap(), Pattern::new("*.toml").unwrap()]);
+        assert_eq!(p, Pattern::new("@(*.rs|*.toml)").unwrap());
+        assert!(p.matches("main.rs"));
+        assert!(p.matches("Cargo.toml"));
+        assert!(!p.matches("Cargo.lock"));
+        assert!(!p.matches("main.rs.bak"));
+
+        let p = Pattern::from_alternatives(["src/**/*.rs", "Cargo.toml", "@<doc>(*.md)"].iter().map(|p| Pattern::new(p).unwrap()));
+        assert!(p.matches("src/bin/main.rs"));
+        assert!(p.matches("Cargo.toml"));
+        assert!(!p.matches("tests/a.rs"));
+        assert_eq!(p.captures_named("README.md").unwrap()["doc"], "README.md");
+
+        let p = Pattern::from_alternatives(vec![]);
+        assert!(!p.matches(""));
+        assert!(!p.matches("a"));
+
+        let custom = PatternBuilder::new("a::**").separators(|c| c == ':').build().unwrap();
+        let p = Pattern::from_alternatives(vec![custom, Pattern::new("b").unwrap()]);
+        assert!(p.matches("a::"));
+        assert!(p.matches("a::b::c"));
+        assert!(!p.matches("a/b/c"));
+        assert!(p.matches("b"));
+    }
+
+    #[test]
+    fn stats() {
+        let stats = |p: &str| Pattern::new(p).unwrap().stats();
+        assert_eq!(stats(""), PatternStats::default());
+        assert_eq!(stats("abc"), PatternStats { tokens: 3, wildcards: 0, max_depth: 0, alternatives: 0 });
+        assert_eq!(stats("a?[bc]*"), PatternStats { tokens: 4, wildcards: 1, max_depth: 0, alternatives: 0 });
+        assert_eq!(stats("/var/**/*.log"), PatternStats { tokens: 12, wildcards: 2, max_depth: 0, alternatives: 0 });
+        assert_eq!(stats("@(a|b)?(c)"), PatternStats { tokens: 5, wildcards: 0, max_depth: 1, alternatives: 3 });
+        assert_eq!(stats("!(!(a*))"), PatternStats { tokens: 4, wildcards: 1, max_depth: 2, alternatives: 2 });
+        assert_eq!(stats("!(!(!(!(vec|test)))).rs"),
+                   PatternStats { tokens: 14, wildcards: 0, max_depth: 4, alternatives: 5 });
+        assert_eq!(stats("+(a|*(b|@<x>(c|**)))"), PatternStats { tokens: 7, wildcards: 1, max_depth: 3, alternatives: 6 });
+    }
+
+    #[test]
+    fn matches_any_all() {
+        let p = Pattern::new("*.rs").unwrap();
+        assert!(p.matches_any(&["main.c", "lib.rs", "mod.h"]));
+        assert!(!p.matches_any(&["main.c", "mod.h"]));
+        assert!(!p.matches_any(&[]));
+
+        assert!(p.matches_all(&["main.rs", "lib.rs"]));
+        assert!(!p.matches_all(&["main.rs", "lib.c", "mod.rs"]));
+        assert!(p.matches_all(&[]));
+    }
+
+    #[test]
+    fn partition() {
+        let p = Pattern::new("*.rs").unwrap();
+        let files = ["main.rs", "Cargo.toml", "src/lib.rs", "README.md", ".rs", "rs"];
+        let (matched, unmatched) = p.partition(files.iter());
+        assert_eq!(matched, vec![&"main.rs", &"src/lib.rs", &".rs"]);
+        assert_eq!(unmatched, vec![&"Cargo.toml", &"README.md", &"rs"]);
+
+        let owned: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+        let (matched, unmatched) = p.partition(owned);
+        assert_eq!(matched.len(), 3);
+        assert_eq!(unmatched.len(), 3);
+
+        let (matched, unmatched) = p.partition(Vec::<&str>::new());
+        assert!(matched.is_empty() && unmatched.is_empty());
+    }
+
+    #[test]
+    fn case_fold() {
+        let p = Pattern::new("*.RS").unwrap();
+        assert!(p.matches_case_fold("main.rs"));
+        assert!(p.matches_case_fold("MAIN.Rs"));
+        assert!(p.matches_case_fold("src/lib.RS"));
+        assert!(!p.matches_case_fold("main.rc"));
+        assert!(!p.matches("main.rs"));
+
+        let p = Pattern::new("Cargo.toml").unwrap();
+        assert!(p.matches_case_fold("cargo.TOML"));
+        assert!(!p.matches_case_fold("cargo.tom"));
+        assert!(!p.matches("cargo.toml"));
+
+        let p = Pattern::new("[a-c]x[!Q]").unwrap();
+        assert!(p.matches_case_fold("AXz"));
+        assert!(p.matches_case_fold("bxa"));
+        assert!(!p.matches_case_fold("dxa"));
+        assert!(!p.matches_case_fold("axq"));
+
+        // only ascii is folded
+        let p = Pattern::new("café.txt").unwrap();
+        assert!(p.matches_case_fold("CAFé.TXT"));
+        assert!(!p.matches_case_fold("CAFÉ.TXT"));
+    }
+
+    #[test]
+    fn could_match_prefix() {
+        let p = Pattern::new("abc*.rs").unwrap();
+        for partial in ["", "a", "ab", "abc", "abcdef", "abc.r", "abc.rs", "abc.rs.rs", "abc/x."].iter() {
+            assert!(p.could_match_prefix(partial), "{}", partial);
+        }
+        for partial in ["xyz", "b", "abd"].iter() {
+            assert!(!p.could_match_prefix(partial), "{}", partial);
+        }
+
+        let p = Pattern::new("main.rs").unwrap();
+        assert!(p.could_match_prefix("main."));
+        assert!(p.could_match_prefix("main.rs"));
+        assert!(!p.could_match_prefix("main.rs2"));
+        assert!(!p.could_match_prefix("mainx"));
+
+        // the ** can still take anything after src/
+        let p = Pattern::new("src/**/[a-c]?.@(rs|toml)").unwrap();
+        for partial in ["src", "src/", "src/a/b/", "src/a/b/c", "src/a/b/cd.", "src/bx.t", "src/bx.toml", "src/d", "src/ax.c"].iter() {
+            assert!(p.could_match_prefix(partial), "{}", partial);
+        }
+        for partial in ["lib", "srx", "src\\a"].iter() {
+            assert!(!p.could_match_prefix(partial), "{}", partial);
+        }
+
+        let p = Pattern::new("?.@(rs|toml)").unwrap();
+        assert!(p.could_match_prefix("a.to"));
+        assert!(!p.could_match_prefix("a.rst"));
+        assert!(!p.could_match_prefix("ab"));
+
+        // a*b*c goes through the simulation instead
+        let p = Pattern::new("a*b*c[!x]").unwrap();
+        assert!(p.could_match_prefix("axxbxx"));
+        assert!(p.could_match_prefix("axxbxxcx"));
+        assert!(!p.could_match_prefix("bxx"));
+
+        // can't tell yet whether this ends up as *.rs
+        let p = Pattern::new("src/!(*.rs)").unwrap();
+        assert!(p.could_match_prefix("src/main.rs"));
+        assert!(!p.could_match_prefix("lib/"));
+    }
+
+    #[test]
+    fn could_match_under() {
+        let p = Pattern::new("src/**/*.rs").unwrap();
+        for dir in ["src", "src/", "src/a", "src/a/b", ""].iter() {
+            assert!(p.could_match_under(dir), "{}", dir);
+        }
+        for dir in ["tests", "sr", "srcx", "lib/src"].iter() {
+            assert!(!p.could_match_under(dir), "{}", dir);
+        }
+
+        // nothing is inside a file the pattern names
+        let p = Pattern::new("src/main.rs").unwrap();
+        assert!(p.could_match_under("src"));
+        assert!(!p.could_match_under("src/main.rs"));
+
+        let p = PatternBuilder::new("src/*.rs").require_literal_separator(true).build().unwrap();
+        assert!(p.could_match_under("src"));
+        assert!(!p.could_match_under("src/a"));
+        assert!(Pattern::new("src/*.rs").unwrap().could_match_under("src/a"));
+
+        let p = PatternBuilder::new("a:b:*").separators(|c| c == ':').build().unwrap();
+        assert!(p.could_match_under("a:b:"));
+        assert!(!p.could_match_under("a:c:"));
+        assert!(p.could_match_under("a:c"));
+    }
+
+    #[test]
+    fn ascii_case_insensitive_bytes() {
+        let p = Pattern::new("*.TXT").unwrap();
+        assert!(p.matches_ascii_case_insensitive_bytes(b"file.txt"));
+        assert!(p.matches_ascii_case_insensitive_bytes(b"FILE.TxT"));
+        assert!(p.matches_ascii_case_insensitive_bytes(b".txt"));
+        assert!(!p.matches_ascii_case_insensitive_bytes(b"file.txt.bak"));
+        assert!(!p.matches_ascii_case_insensitive_bytes(b""));
+
+        // only ascii is folded
+        let p = Pattern::new("caf\u{c9}_[a-z].txt").unwrap();
+        assert!(p.matches_ascii_case_insensitive_bytes("CAFÉ_X.TXT".as_bytes()));
+        assert!(!p.matches_ascii_case_insensitive_bytes("café_x.txt".as_bytes()));
+
+        // invalid sequences are a single char
+        let p = Pattern::new("[A-Z]?.TXT").unwrap();
+        assert!(p.matches_ascii_case_insensitive_bytes(b"a\xff.txt"));
+        assert!(p.matches_ascii_case_insensitive_bytes(b"a\xe2\x82.txt"));
+        assert!(!p.matches_ascii_case_insensitive_bytes(b"\xffa.txt"));
+        assert!(Pattern::new("\u{FFFD}.txt").unwrap().matches_ascii_case_insensitive_bytes(b"\xc3.TXT"));
+    }
+
+    #[test]
+    fn matches_bytes() {
+        // a multibyte char matches its UTF-8 bytes and nothing else
+        let p = Pattern::new("é[a-z]").unwrap();
+        assert!(p.matches_bytes(b"\xc3\xa9x"));
+        assert!(!p.matches_bytes(b"\xe9x"));
+        assert!(!p.matches_bytes(b"\xc3x"));
+        assert!(!p.matches_bytes(b"\xc3\xa9\xc3\xa9"));
+
+        // ranges and ? take a single byte
+        let p = Pattern::new("[a-z]?[!a-z]").unwrap();
+        assert!(p.matches_bytes(b"a\xff\x80"));
+        assert!(!p.matches_bytes("aéb".as_bytes()));
+        assert!(p.matches_bytes("aé".as_bytes()));
+        assert!(Pattern::new("??").unwrap().matches_bytes("é".as_bytes()));
+        assert!(!Pattern::new("?").unwrap().matches_bytes("é".as_bytes()));
+
+        // non ascii members of a range can't match a single byte
+        assert!(!Pattern::new("[é]").unwrap().matches_bytes(b"\xe9"));
+        assert!(!Pattern::new("[é]").unwrap().matches_bytes("é".as_bytes()));
+        assert!(Pattern::new("[!é]").unwrap().matches_bytes(b"\xe9"));
+        assert!(Pattern::new("[x-é]").unwrap().matches_bytes(b"z"));
+        assert!(!Pattern::new("[x-é]").unwrap().matches_bytes(b"\xe9"));
+
+        // wildcards, patterns and options see the same bytes
+        let p = Pattern::new("**/*ü.@(rs|é)").unwrap();
+        assert!(p.matches_bytes(b"src/\xff\xfe\xc3\xbc.rs"));
+        assert!(p.matches_bytes("a/b/ü.é".as_bytes()));
+        assert!(!p.matches_bytes(b"a/\xfc.rs"));
+        assert!(Pattern::new("CAFÉ").unwrap().matches_bytes_with("cafÉ".as_bytes(), MatchOptions { case_insensitive: true, ..MatchOptions::new() }));
+        assert!(!Pattern::new("CAFÉ").unwrap().matches_bytes_with("café".as_bytes(), MatchOptions { case_insensitive: true, ..MatchOptions::new() }));
+        assert!(Pattern::new("a/*").unwrap().matches_bytes(b"a/\xff"));
+        assert!(!PatternBuilder::new("a/*").require_literal_separator(true).build().unwrap().matches_bytes(b"a/\xff/b"));
+    }
+
+    #[test]
+    fn newline_is_boundary() {
+        let p = Pattern::new("*foo").unwrap();
+        assert!(p.matches_multiline("a foo"));
+        assert!(!p.matches_multiline("a\nfoo"));
+        assert!(p.matches("a\nfoo"));
+
+        let p = Pattern::new("a?b").unwrap();
+        assert!(p.matches_multiline("a-b"));
+        assert!(!p.matches_multiline("a\nb"));
+
+        // ** crosses lines, newlines can be separators too
+        assert!(Pattern::new("**/foo").unwrap().matches_multiline("a\nb/foo"));
+        assert!(!Pattern::new("**/*foo").unwrap().matches_multiline("a/b\nfoo"));
+        let p = PatternBuilder::new("**\nfoo").separators(|c| c == '\n').build().unwrap();
+        assert!(p.matches_multiline("a\nb\nfoo"));
+        assert!(p.matches_multiline("foo"));
+        assert!(!p.matches_multiline("a\nb\nxfoo"));
+        assert!(!Pattern::new("*\n*").unwrap().matches_multiline("a\nb\nc"));
+        assert!(Pattern::new("*\n*\n*").unwrap().matches_multiline("a\nb\nc"));
+
+        // also in patterns, while matching with the simulation and when searching
+        assert!(!Pattern::new("@(*foo)").unwrap().matches_multiline("a\nfoo"));
+        assert!(!Pattern::new("a*b*c").unwrap().matches_multiline("ab\nc"));
+        assert!(Pattern::new("a*b*c").unwrap().matches_multiline("abxc"));
+        let mut options = MatchOptions::new();
+        options.newline_is_boundary = true;
+        options.unanchored_start = true;
+        assert!(Pattern::new("*foo").unwrap().matches_with("a\nbfoo", options));
+        assert!(!Pattern::new("a*foo").unwrap().matches_with("a\nfoo", options));
+
+        // separators are still crossed
+        assert!(Pattern::new("a?b").unwrap().matches_multiline("a/b"));
+        assert!(Pattern::new("*.rs").unwrap().matches_multiline("src/main.rs"));
+    }
+
+    #[test]
+    fn error_kind() {
+        use super::ErrorKind;
+
+        let localized = |p: &str| {
+            let err = Pattern::new(p).unwrap_err();
+            let message = match err.kind() {
+                ErrorKind::UnclosedRange => "Bereich ohne ]",
+                ErrorKind::IllegalRecursion => "** muss allein stehen",
+                kind => kind.desc(),
+            };
+            format!("{}\n{}\n{}^", message, err.input(), "-".repeat(err.position()))
+        };
+
+        assert_eq!(localized("src/[a-z"), "Bereich ohne ]\nsrc/[a-z\n----^");
+        assert_eq!(localized("a**/b"), "** muss allein stehen\na**/b\n^");
+        assert_eq!(localized("@()"), "patterns must not be empty! e.g !(), *(), @(), etc\n@()\n--^");
+
+        let err = Pattern::new("a|b").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::IllegalChar);
+        assert_eq!(err.desc(), ErrorKind::IllegalChar.desc());
+        assert!(err.to_string().contains(err.desc()));
+        assert_eq!(err.position(), 1);
+    }
+
+    #[test]
+    fn nested_too_deep() {
+        let nested = "!(".repeat(1000) + "a" + &")".repeat(1000);
+        let err = Pattern::new(&nested).unwrap_err();
+        assert_eq!(err.inner, Error::NestedTooDeep(256));
+        assert!(err.to_string().contains("nested too deep"));
+    }
+
+    #[test]
+    fn match_positions() {
+        let positions = |p: &str, input: &str| Pattern::new(p).unwrap().match_positions(input);
+
+        assert_eq!(positions("foo", "foo bar foofoo"), vec![(0, 3), (8, 11), (11, 14)]);
+        assert_eq!(positions("a?c", "abcabc"), vec![(0, 3), (3, 6)]);
+        assert_eq!(positions("aa", "aaa"), vec![(0, 2)]);
+        assert_eq!(positions("x", "abc"), vec![]);
+        assert_eq!(positions("x", ""), vec![]);
+
+        // the longest match at the leftmost start
+        assert_eq!(positions("a*c", "xabcbc ac"), vec![(1, 9)]);
+        assert_eq!(positions("[a-z]*", "ab1cd"), vec![(0, 5)]);
+        assert_eq!(positions("[a-z]", "ab1cd"), vec![(0, 1), (1, 2), (3, 4), (4, 5)]);
+        assert_eq!(positions("[0-9]+([0-9])", "a1b22c"), vec![(3, 5)]);
+
+        // empty matches are skipped
+        assert_eq!(positions("", "abc"), vec![]);
+        assert_eq!(positions("?(x)", "axbxx"), vec![(1, 2), (3, 4), (4, 5)]);
+
+        // offsets are in bytes
+        assert_eq!(positions("é?", "aébéc"), vec![(1, 4), (4, 7)]);
+        assert_eq!(positions("@(é|b)", "aébéc"), vec![(1, 3), (3, 4), (4, 6)]);
+    }
+
+    #[test]
+    fn enumerate() {
+        let all = |p: &str| Pattern::new(p).unwrap().enumerate(usize::MAX);
+
+        assert_eq!(all("").unwrap(), [""]);
+        assert_eq!(all("abc").unwrap(), ["abc"]);
+        assert_eq!(all("@(a|b).rs").unwrap(), ["a.rs", "b.rs"]);
+        assert_eq!(all("[a-c]x[0-1]").unwrap(), ["ax0", "ax1", "bx0", "bx1", "cx0", "cx1"]);
+        assert_eq!(all("a?(b|c)@(d|?(e))").unwrap(), ["ad", "a", "ae", "abd", "ab", "abe", "acd", "ac", "ace"]);
+        assert_eq!(all("@<x>(a|b)c").unwrap(), ["ac", "bc"]);
+        assert_eq!(all("@(a|b/c)/d").unwrap(), ["a/d", "b/c/d"]);
+        // the same string spelled out twice is listed once
+        assert_eq!(all("@(a|a|[a])?(b)?(b)").unwrap(), ["a", "ab", "abb"]);
+        assert_eq!(all("x[2-1]").unwrap(), Vec::<String>::new());
+        assert_eq!(all("???@([2-1])").unwrap(), Vec::<String>::new());
+
+        // every string is matched by the pattern and the count agrees with the choices
+        let p = Pattern::new("[a-z]?(-[0-9])").unwrap();
+        let strings = p.enumerate(usize::MAX).unwrap();
+        assert_eq!(strings.len(), 26 * 11);
+        assert!(strings.iter().all(|s| p.matches(s)));
+
+        // chars matching almost anything are listed in order up to the limit
+        let p = Pattern::new("a?").unwrap();
+        assert_eq!(p.enumerate(3).unwrap(), ["a\0", "a\u{1}", "a\u{2}"]);
+        assert_eq!(Pattern::new("[!\\x00-\\x60]").unwrap().enumerate(2).unwrap(), ["a", "b"]);
+        assert_eq!(p.enumerate(0).unwrap(), Vec::<String>::new());
+        assert_eq!(Pattern::new("[!\\x00-\\u{10fffc}]").unwrap().enumerate(usize::MAX).unwrap(), ["\u{10fffd}", "\u{10fffe}", "\u{10ffff}"]);
+
+        // options take part
+        let p = PatternBuilder::new("[A-b]").case_insensitive(true).build().unwrap();
+        assert_eq!(p.enumerate(100).unwrap().len(), 52 + 6);
+        let strings = PatternBuilder::new("?").require_literal_separator(true).build().unwrap().enumerate(0x30).unwrap();
+        assert_eq!(strings.len(), 0x30);
+        assert_eq!(strings[0x2f], "0");
+        assert_eq!(PatternBuilder::new("a").case_insensitive(true).build().unwrap().enumerate(10).unwrap(), ["a", "A"]);
+
+        // infinite
+        for p in ["*", "a/**", "**+/a", "a*(b)", "+(a)", "!(a)", "@(a|*)", "?(**)"].iter() {
+            assert_eq!(all(p), None, "{}", p);
+        }
+        assert_eq!(PatternBuilder::new("a").match_options(MatchOptions { unanchored_start: true, ..MatchOptions::new() }).build().unwrap().enumerate(10), None);
+    }
+
+    #[test]
+    fn len_bounds() {
+        let bounds = |p: &str| Pattern::new(p).unwrap().len_bounds();
+
+        assert_eq!(bounds(""), (0, Some(0)));
+        assert_eq!(bounds("a?c"), (3, Some(3)));
+        assert_eq!(bounds("a*c"), (2, None));
+        assert_eq!(bounds("[a-z][!0-9]é"), (3, Some(3)));
+        assert_eq!(bounds("a?(bc|d)"), (1, Some(3)));
+        assert_eq!(bounds("a@(bc|d)"), (2, Some(3)));
+        assert_eq!(bounds("a@(bc|*)"), (1, None));
+        assert_eq!(bounds("a+(bc|d)"), (2, None));
+        assert_eq!(bounds("a*(bc|d)"), (1, None));
+        assert_eq!(bounds("a!(b)"), (1, None));
+        assert_eq!(bounds("@<x>(ab)c"), (3, Some(3)));
+        // the separators after a ** matching nothing are skipped
+        assert_eq!(bounds("**/x"), (1, None));
+        assert_eq!(bounds("a/**/x"), (3, None));
+        assert_eq!(bounds("a/@(b|**)/x"), (3, None));
+
+        // agrees with matching
+        let p = Pattern::new("a/@(b|**)/x").unwrap();
+        assert!(p.matches("a/x"));
+        assert!(p.matches("a/b/x"));
+        let p = Pattern::new("a?c").unwrap();
+        assert!(!p.matches("ac"));
+        assert!(!p.matches("abbc"));
+        assert!(p.matches("aéc"));
+        assert!(!p.matches("aééc"));
+        assert!(Pattern::new("??").unwrap().matches("éé"));
+        assert!(Pattern::new("é?").unwrap().matches("é🦀"));
+
+        let mut options = MatchOptions::new();
+        options.unanchored_start = true;
+        assert!(p.matches_with("xxabc", options));
+
+        // every level is walked once, nesting as deep as allowed doesn't take exponentially long
+        let start = std::time::Instant::now();
+        for (open, expected) in [("@(", (1, Some(1))), ("?(", (0, Some(1))), ("+(", (1, None)), ("*(", (0, None))].iter() {
+            let depth = super::syntax::MAX_NESTING;
+            let p = Pattern::new(&(open.repeat(depth) + "a" + &")".repeat(depth))).unwrap();
+            assert_eq!(p.len_bounds(), *expected, "{}", open);
+            assert!(p.canonical().matches("a"));
+            if let Some(found) = p.enumerate(10) {
+                assert!(found.contains(&String::from("a")));
+            }
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn suffix_min_lens() {
+        use super::analysis::{len_bounds, suffix_len_bounds};
+        use super::MatchExplanation;
+
+        for p in ["", "a*b?c", "**/x", "a/**/b/**/c", "a/@(b|**)//x", "@<n>(**)/a", "*(ab)+(c|de)!(f)g", "**+/a", "?(a/**)//b"] {
+            let tokens = Pattern::new(p).unwrap().matcher.tokens().to_vec();
+            let bounds = suffix_len_bounds(&tokens, super::options::is_separator);
+            assert_eq!(bounds.len(), tokens.len() + 1);
+            for (j, bounds) in bounds.into_iter().enumerate() {
+                assert_eq!(bounds, len_bounds(&tokens[j..], super::options::is_separator), "{} at {}", p, j);
+            }
+        }
+
+        // giving up once the rest can't fit doesn't change what matches, explaining a match never gives up early
+        let patterns = ["@(a)*a*a*a*b", "*(a|b)*@(ab|b)?c", "a/**/@(b|c)/**/d", "!(x)*y*", "+(a*)a?a", "**/é@(é|a)?"];
+        let inputs = ["", "ab", "aaaaab", "aaaaaa", "abbc", "abab_c", "a/b/d", "a/x/c/y/d", "a/d", "xy", "zzyzz", "a/éé", "éa"];
+        for p in &patterns {
+            let pattern = Pattern::new(p).unwrap();
+            for input in &inputs {
+                let explained = matches!(pattern.explain_match(input), MatchExplanation::Matched(_));
+                assert_eq!(pattern.matches(input), explained, "{} {}", p, input);
+            }
+        }
+    }
+
+    #[test]
+    fn literal_segments() {
+        use super::SegmentPos::{End, Floating, Start};
+
+        let segments = |p: &str| Pattern::new(p).unwrap().literal_segments();
+        let owned = |expected: &[(super::SegmentPos, &str)]| expected.iter()
+            .map(|&(pos, s)| (pos, s.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(segments("a/*/b/**/c.rs"), owned(&[(Start(0), "a"), (Start(2), "b"), (End(0), "c.rs")]));
+        assert_eq!(segments("/var/log/*.log"), owned(&[(Start(1), "var"), (Start(2), "log")]));
+        assert_eq!(segments("Cargo.toml"), owned(&[(Start(0), "Cargo.toml")]));
+        assert_eq!(segments("**/src/**/x/*/main.rs"), owned(&[(Floating, "src"), (End(2), "x"), (End(0), "main.rs")]));
+        assert_eq!(segments("a/@(b|c)/d/?(e/)/f"), owned(&[(Start(0), "a"), (Start(2), "d"), (End(0), "f")]));
+        assert_eq!(segments("**/a/**"), owned(&[(Floating, "a")]));
+        assert_eq!(segments("src/"), owned(&[(Start(0), "src")]));
+        assert_eq!(segments("*.rs"), owned(&[]));
+        assert_eq!(segments("a*/[b]c/!(d)"), owned(&[]));
+        assert_eq!(segments(""), owned(&[]));
+    }
+
+    #[test]
+    fn depth() {
+        let depth = |p: &str| Pattern::new(p).unwrap().depth();
+
+        assert_eq!(depth(""), Some(0));
+        assert_eq!(depth("Cargo.toml"), Some(0));
+        assert_eq!(depth("a/b/*.rs"), Some(2));
+        assert_eq!(depth("/var/log/*"), Some(3));
+        assert_eq!(depth("src/"), Some(1));
+        assert_eq!(depth("[a-z]/?/*.@(rs|toml)"), Some(2));
+        assert_eq!(depth("a/!(b|c)/d"), Some(2));
+        assert_eq!(depth("a/@<x>(b)/d"), Some(2));
+
+        assert_eq!(depth("**"), None);
+        assert_eq!(depth("a/**/x"), None);
+        assert_eq!(depth("a/**"), None);
+        assert_eq!(depth("**/x"), None);
+        assert_eq!(depth("a/?(b/)c"), None);
+        assert_eq!(depth("a/@(b|**)/c"), None);
+        assert_eq!(depth("@<x>(a/b)"), None);
+
+        let p = PatternBuilder::new("a::b::*").separators(|c| c == ':').build().unwrap();
+        assert_eq!(p.depth(), Some(4));
+    }
+
+    #[test]
+    fn matches_components() {
+        use std::path::{Component, Path};
+
+        // straight from the path, never joined into a string
+        let lazily = |p: &Pattern, path: &str| p.matches_components(Path::new(path).components().filter_map(|c| match c {
+            Component::Normal(c) => c.to_str(),
+            _ => None,
+        }));
+
+        let p = Pattern::new("src/**/*.rs").unwrap();
+        assert!(lazily(&p, "src/main.rs"));
+        assert!(lazily(&p, "src/bin/tool/main.rs"));
+        assert!(!lazily(&p, "src/main.c"));
+        assert!(!lazily(&p, "lib/main.rs"));
+
+        let p = Pattern::new("src/*.rs").unwrap();
+        assert!(lazily(&p, "src/main.rs"));
+        assert!(!lazily(&p, "src/bin/main.rs"));
+
+        let p = Pattern::new("a?b").unwrap();
+        assert!(p.matches_components(vec!["axb"].into_iter()));
+        assert!(!p.matches_components(vec!["a", "b"].into_iter()));
+        assert!(p.matches("a/b"));
+
+        assert!(Pattern::new("a/@(b|c)/d").unwrap().matches_components(vec!["a", "c", "d"].into_iter()));
+        assert!(Pattern::new("**").unwrap().matches_components(vec!["a", "b"].into_iter()));
+        assert!(!Pattern::new("*").unwrap().matches_components(vec!["a", "b"].into_iter()));
+        assert!(Pattern::new("").unwrap().matches_components(core::iter::empty()));
+        assert!(Pattern::new("a//b").unwrap().matches_components(vec!["a", "", "b"].into_iter()));
+    }
+
+    #[test]
+    fn common_prefix() {
+        let prefix = |a: &str, b: &str| Pattern::new(a).unwrap().common_prefix(&Pattern::new(b).unwrap());
+
+        assert_eq!(prefix("src/foo*.rs", "src/foobar.rs"), Pattern::new("src/foo").unwrap());
+        assert_eq!(prefix("src/**/*.rs", "src/**/*.toml"), Pattern::new("src/**/*.").unwrap());
+        assert_eq!(prefix("[ab]x/@(a|b)c", "[ba]x/@(a|b)d"), Pattern::new("[ab]x/@(a|b)").unwrap());
+        assert_eq!(prefix("a/*", "a/**"), Pattern::new("a/").unwrap());
+        assert_eq!(prefix("abc", "abc"), Pattern::new("abc").unwrap());
+        assert_eq!(prefix("abc", "xyz"), Pattern::new("").unwrap());
+        assert_eq!(prefix("", "abc"), Pattern::new("").unwrap());
+        assert_eq!(prefix("@(a|b)", "@(b|a)"), Pattern::new("").unwrap());
+        assert!(prefix("src/foo*.rs", "src/foobar.rs").is_literal());
+    }
+
+    #[test]
+    fn clone_shares_tokens() {
+        let p = Pattern::new("src/**/@(*.rs|!(mod).toml)").unwrap();
+        let clone = p.clone();
+        assert!(core::ptr::eq(p.matcher.tokens(), clone.matcher.tokens()));
+        assert_eq!(p, clone);
+        for input in ["src/main.rs", "src/a/b/Cargo.toml", "src/mod.toml", "lib/main.rs"].iter() {
+            assert_eq!(clone.matches(input), p.matches(input), "{}", input);
+        }
+        assert!(clone.matches("src/a/b/Cargo.toml"));
+        assert!(!clone.matches("src/mod.toml"));
+
+        let literal = Pattern::new("Cargo.toml").unwrap();
+        let clone = literal.clone();
+        assert!(core::ptr::eq(literal.matcher.literal().unwrap(), clone.matcher.literal().unwrap()));
+        assert!(clone.matches("Cargo.toml"));
+    }
+
+    #[test]
+    fn is_satisfiable() {
+        for p in ["", "*", "[a-z]", "[!a-z]", "[!\\x00-\\u{d7ff}]", "?([2-1])x", "*([2-1])", "+([2-1]|a)", "!(a*)", "@<x>(a)",
+                  "[a-\\u{10ffff}]"].iter() {
+            assert!(Pattern::new(p).unwrap().is_satisfiable(), "{}", p);
+        }
+        for p in ["[2-1]", "a/[z-a]/b", "[!\\x00-\\u{10ffff}]", "[!\\x00-\\u{d7ff}\\u{e000}-\\u{10ffff}]", "+([2-1])",
+                  "@([2-1]|[!\\x00-\\u{10ffff}])", "!(*)", "a!(x|**)", "@<x>([2-1])"].iter() {
+            assert!(!Pattern::new(p).unwrap().is_satisfiable(), "{}", p);
+        }
+
+        // agrees with matching
+        let p = Pattern::new("[2-1]").unwrap();
+        assert!(!p.matches("1") && !p.matches("2") && !p.matches(""));
+    }
+
+    #[test]
+    fn case_fold_ranges() {
+        // only the letters in a range fold, [A-_] also holds [\]^_ but not their neighbours {|}~ past z
+        let p = Pattern::new("[A-_]").unwrap();
+        for c in ["A", "Z", "a", "z", "[", "\\", "]", "^", "_"].iter() {
+            assert!(p.matches_case_fold(c), "{}", c);
+        }
+        for c in ["{", "|", "}", "~", "`", "@", "0"].iter() {
+            assert!(!p.matches_case_fold(c), "{}", c);
+        }
+
+        // ` sits between Z and a, which are the only letters in the range
+        let p = Pattern::new("[Z-a]").unwrap();
+        for c in ["Z", "z", "a", "A", "`", "_"].iter() {
+            assert!(p.matches_case_fold(c), "{}", c);
+        }
+        for c in ["b", "B", "y", "Y", "@", "{"].iter() {
+            assert!(!p.matches_case_fold(c), "{}", c);
+        }
+        assert!(!p.matches("z"));
+        assert!(!p.matches("A"));
+
+        let p = Pattern::new("[a-z]").unwrap();
+        for c in ["a", "z", "A", "Z", "M"].iter() {
+            assert!(p.matches_case_fold(c), "{}", c);
+        }
+        for c in ["@", "[", "`", "{", "_", "0"].iter() {
+            assert!(!p.matches_case_fold(c), "{}", c);
+        }
+
+        let p = Pattern::new("[!A-_]").unwrap();
+        assert!(!p.matches_case_fold("a"));
+        assert!(!p.matches_case_fold("_"));
+        assert!(p.matches_case_fold("{"));
+        assert!(p.matches_case_fold("`"));
+    }
+
+    #[test]
+    fn ranges() {
+        let p = Pattern::new("a[a-z]c").unwrap();
+        assert!(p.matches("aac"));
+        assert!(p.matches("aec"));
+        assert!(p.matches("azc"));
+        assert!(!p.matches("a0c"));
+        assert!(!p.matches("aAc"));
+        assert!(!p.matches("aBc"));
+        assert!(!p.matches("aZc"));
+    }
+
+    #[test]
+    fn ranges_merged() {
+        use CharSpecifier::{Char, Range};
+
+        let merged = [
+            ("[a-cb-d]", vec![Range('a', 'd')]),
+            ("[a-ce-gb-d]", vec![Range('a', 'g')]),
+            ("[x-za-c]", vec![Range('a', 'c'), Range('x', 'z')]),
+            ("[aaa]", vec![Char('a')]),
+            ("[abc]", vec![Range('a', 'c')]),
+            ("[ac]", vec![Char('a'), Char('c')]),
+            ("[a-ca]", vec![Range('a', 'c')]),
+            ("[0-9a-fA-F]", vec![Range('0', '9'), Range('A', 'F'), Range('a', 'f')]),
+            ("[2-1]", vec![]),
+        ];
+        for (p, specifiers) in merged.iter() {
+            assert_eq!(Pattern::new(p).unwrap().matcher.tokens(), &[Token::AnyOf(specifiers.clone())], "{}", p);
+        }
+        assert_eq!(Pattern::new("[!a-cb-d]").unwrap().matcher.tokens(), &[Token::NotAnyOf(vec![Range('a', 'd')])]);
+
+        let p = Pattern::new("[a-ce-gb-d]").unwrap();
+        for c in "abcdefg".chars() {
+            assert!(p.matches(&c.to_string()));
+        }
+        assert!(!p.matches("h"));
+
+        let p = Pattern::new("[!a-ce-gb-d]").unwrap();
+        for c in "abcdefg".chars() {
+            assert!(!p.matches(&c.to_string()));
+        }
+        assert!(p.matches("h"));
+        assert!(Pattern::new("[!2-1]").unwrap().matches("1"));
+    }
+
+    #[test]
+    fn range_escapes() {
+        let p = Pattern::new("[a\\-z]").unwrap();
+        assert!(p.matches("a"));
+        assert!(p.matches("-"));
+        assert!(p.matches("z"));
+        assert!(!p.matches("b"));
+        assert!(!p.matches("\\"));
+
+        let p = Pattern::new("[\\]]").unwrap();
+        assert!(p.matches("]"));
+        assert!(!p.matches("\\"));
+
+        let p = Pattern::new("[!\\]]").unwrap();
+        assert!(!p.matches("]"));
+        assert!(p.matches("a"));
+
+        let p = Pattern::new("[\\\\]").unwrap();
+        assert!(p.matches("\\"));
+        assert!(!p.matches("a"));
+
+        let p = Pattern::new("[\\[\\(\\)\\|]").unwrap();
+        for c in &["[", "(", ")", "|"] {
+            assert!(p.matches(c));
+        }
+
+        // escaped chars can still be the ends of a range
+        let p = Pattern::new("[\\!-\\#]").unwrap();
+        assert!(p.matches("!"));
+        assert!(p.matches("\""));
+        assert!(p.matches("#"));
+        assert!(!p.matches("-"));
+
+        let p = Pattern::new("[\\]\\-\\\\]").unwrap();
+        assert!(p.matches("]"));
+        assert!(p.matches("-"));
+        assert!(p.matches("\\"));
+        assert!(!p.matches("^"));
+
+        let p = Pattern::new("[\\a]").unwrap();
+        assert!(p.matches("a"));
+        assert!(!p.matches("\\"));
+    }
+
+    #[test]
+    fn nul() {
+        let p = Pattern::new("a\\x00b").unwrap();
+        assert!(p.matches("a\0b"));
+        assert!(!p.matches("a0b"));
+        assert!(!p.matches("ab"));
+
+        let p = Pattern::new("*.rs\\x00*.rs").unwrap();
+        assert!(p.matches("src/main.rs\0src/lib.rs"));
+        assert!(!p.matches("src/main.rs src/lib.rs"));
+        assert!(Pattern::new("*").unwrap().matches("\0\0"));
+        assert!(Pattern::new("**/x").unwrap().matches("\0/x"));
+        assert!(Pattern::new("?").unwrap().matches("\0"));
+        assert!(Pattern::new("\\u{0}").unwrap().matches("\0"));
+
+        let p = Pattern::new("[\\x00-\\x1f]").unwrap();
+        assert!(p.matches("\0"));
+        assert!(p.matches("\n"));
+        assert!(!p.matches(" "));
+        assert!(!Pattern::new("[!\\x00]").unwrap().matches("\0"));
+        assert!(Pattern::new("[!\\x00]").unwrap().matches("0"));
+        assert!(Pattern::new("!(\\x00)").unwrap().matches("0"));
+        assert!(!Pattern::new("!(\\x00)").unwrap().matches("\0"));
+
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        assert!(Pattern::new("A\\x00").unwrap().matches_with("a\0", options));
+        assert_eq!(Pattern::new("*\\x00*").unwrap().captures("a\0b"), Some(vec!["a", "b"]));
+    }
+
+    #[test]
+    fn code_points() {
+        let p = Pattern::new("[\\x41-\\x5a]").unwrap();
+        assert_eq!(p, Pattern::new("[A-Z]").unwrap());
+        assert!(p.matches("A"));
+        assert!(p.matches("Z"));
+        assert!(!p.matches("a"));
+        assert!(!p.matches("x"));
+
+        assert!(Pattern::new("\\x00\\x7f\\xFF").unwrap().matches("\u{0}\u{7f}\u{ff}"));
+        assert!(Pattern::new("caf\\u{e9}").unwrap().matches("café"));
+        assert!(Pattern::new("\\u{1F980}*").unwrap().matches("🦀.rs"));
+        assert!(Pattern::new("[!\\x00-\\x1f\\u{7F}]").unwrap().matches("a"));
+        assert!(!Pattern::new("[!\\x00-\\x1f\\u{7F}]").unwrap().matches("\u{7f}"));
+        assert!(Pattern::new("@(\\x2a|\\u{2a}b)").unwrap().matches("*"));
+        // a char named by its code is always literal
+        assert!(!Pattern::new("\\x2a").unwrap().matches("a"));
+        assert!(Pattern::new("[a\\x2dz]").unwrap().matches("-"));
+        assert!(!Pattern::new("[a\\x2dz]").unwrap().matches("b"));
+
+        let errors = [
+            ("\\x", 0),
+            ("\\x4", 0),
+            ("a\\x4g", 1),
+            ("\\xé1", 0),
+            ("\\u", 0),
+            ("\\u41", 0),
+            ("\\u{}", 0),
+            ("\\u{41", 0),
+            ("\\u{1234567}", 0),
+            ("\\u{d800}", 0),
+            ("\\u{110000}", 0),
+            ("[a\\x4]", 2),
+            ("@(a|b\\u{zz})", 5),
+        ];
+        for &(p, i) in errors.iter() {
+            assert_eq!(Pattern::new(p).unwrap_err().inner, Error::IllegalCodePoint(i), "{}", p);
+        }
+    }
+
+    #[test]
+    fn range_leading_bracket() {
+        let p = Pattern::new("[]]").unwrap();
+        assert!(p.matches("]"));
+        assert!(!p.matches("a"));
+        assert!(!p.matches("]]"));
+
+        let p = Pattern::new("[]a]").unwrap();
+        assert!(p.matches("]"));
+        assert!(p.matches("a"));
+        assert!(!p.matches("b"));
+
+        let p = Pattern::new("[!]]").unwrap();
+        assert!(!p.matches("]"));
+        assert!(p.matches("a"));
+
+        let p = Pattern::new("[]-a]").unwrap();
+        assert!(p.matches("]"));
+        assert!(p.matches("_"));
+        assert!(p.matches("a"));
+        assert!(!p.matches("b"));
+
+        let p = Pattern::new("x[]a]y*.rs").unwrap();
+        assert!(p.matches("x]y.rs"));
+        assert!(p.matches("xay_test.rs"));
+
+        let p = Pattern::new("@([]a]|b)").unwrap();
+        assert!(p.matches("]"));
+        assert!(p.matches("a"));
+        assert!(p.matches("b"));
+        assert!(!p.matches("|"));
+
+        let p = Pattern::new("!([!]]).rs").unwrap();
+        assert!(!p.matches("a.rs"));
+        assert!(p.matches("].rs"));
+    }
+
+    #[test]
+    fn range_errors() {
+        assert_eq!(Pattern::new("[!]").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(Pattern::new("[]").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(Pattern::new("[]]]]]").unwrap_err().inner, Error::IllegalChar(3));
+        assert_eq!(Pattern::new("[!]]]").unwrap_err().inner, Error::IllegalChar(4));
+        assert_eq!(Pattern::new("[]a").unwrap_err().inner, Error::EmptyRange(0));
+        assert_eq!(Pattern::new("[dfsfsdfsdf").unwrap_err().inner, Error::UnclosedRange(0));
+        assert_eq!(Pattern::new("[!sdfdsfdf").unwrap_err().inner, Error::UnclosedRange(0));
+        assert_eq!(Pattern::new("abc[def").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("abc[!def").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("abc[").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("abc[!").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("abc[d").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("abc[!d").unwrap_err().inner, Error::UnclosedRange(3));
+        // the error points at the [ that isn't closed, wherever the input ends
+        assert_eq!(Pattern::new("a/b[\\]").unwrap_err().inner, Error::UnclosedRange(3));
+        assert_eq!(Pattern::new("[a]/[b-").unwrap_err().inner, Error::UnclosedRange(4));
+        assert_eq!(Pattern::new("é[a").unwrap_err().inner, Error::UnclosedRange(1));
+        assert_eq!(Pattern::new("abc[]").unwrap_err().inner, Error::EmptyRange(3));
+        assert_eq!(Pattern::new("abc[!]").unwrap_err().inner, Error::EmptyRange(3));
+        assert_eq!(Pattern::new("abc[!]").unwrap_err().inner, Error::EmptyRange(3));
+        assert_eq!(Pattern::new("[adc(]").unwrap_err().inner, Error::IllegalChar(4));
+        assert_eq!(Pattern::new("[adc[]").unwrap_err().inner, Error::IllegalChar(4));
+        assert_eq!(Pattern::new("[adc]]").unwrap_err().inner, Error::IllegalChar(5));
+        assert_eq!(Pattern::new("[adc)]").unwrap_err().inner, Error::IllegalChar(4));
+    }
+
+    #[test]
+    fn pattern_errors() {
+        assert_eq!(Pattern::new(")").unwrap_err().inner, Error::IllegalChar(0));
+        assert_eq!(Pattern::new("a|b").unwrap_err().inner, Error::IllegalChar(1));
+        assert_eq!(Pattern::new("a]").unwrap_err().inner, Error::IllegalChar(1));
+        assert_eq!(Pattern::new("@(a]b)").unwrap_err().inner, Error::IllegalChar(3));
+        assert_eq!(Pattern::new("x@(y|@(a]b))").unwrap_err().inner, Error::IllegalChar(8));
+        assert_eq!(Pattern::new("*(a|@(b|c]))").unwrap_err().inner, Error::IllegalChar(9));
+        assert_eq!(Pattern::new("@(a|(b)|c)").unwrap_err().inner, Error::IllegalChar(4));
+        assert_eq!(Pattern::new("+(a|[b)").unwrap_err().inner, Error::IllegalChar(6));
+        assert_eq!(Pattern::new("!(a|@(b|(c)))").unwrap_err().inner, Error::IllegalChar(8));
+        assert_eq!(Pattern::new("@(a|b**)").unwrap_err().inner, Error::IllegalRecursion(4));
+        assert_eq!(Pattern::new("@(a|?([b-))").unwrap_err().inner, Error::IllegalChar(9));
+        assert_eq!(Pattern::new("!(a|@())").unwrap_err().inner, Error::EmptyPattern(6));
+        assert_eq!(Pattern::new("@()").unwrap_err().inner, Error::EmptyPattern(2));
+        assert_eq!(Pattern::new("@(|a)").unwrap_err().inner, Error::IllegalOr(2));
+        assert_eq!(Pattern::new("@(a||b)").unwrap_err().inner, Error::IllegalOr(4));
+        assert_eq!(Pattern::new("@(a|)").unwrap_err().inner, Error::IllegalOr(4));
+        assert_eq!(Pattern::new("@(a|@(b|)|c)").unwrap_err().inner, Error::IllegalOr(8));
+        assert_eq!(Pattern::new("@(a|@(b)").unwrap_err().inner, Error::UnclosedPattern(7));
+        assert_eq!(Pattern::new("a/@(b|c\\)").unwrap_err().inner, Error::UnclosedPattern(8));
+
+        let err = Pattern::new("x@(y|@(a]b))").unwrap_err();
+        assert!(format!("{}", err).contains("x@(y|@(a]b))\n--------^\n"));
+    }
+
+    #[test]
+    fn empty_alternative() {
+        // a trailing | doesn't make a pattern optional, ?(...) does
+        for pattern in ["@(a|b|)", "*(a|b|)", "+(a|b|)", "?(a|b|)", "!(a|b|)"].iter() {
+            assert_eq!(Pattern::new(pattern).unwrap_err().inner, Error::IllegalOr(6), "{}", pattern);
+        }
+        assert_eq!(Pattern::new("x@(a|b|)y").unwrap_err().inner, Error::IllegalOr(7));
+
+        let optional = Pattern::new("x?(a|b)y").unwrap();
+        for (input, matches) in [("xy", true), ("xay", true), ("xby", true), ("xaby", false), ("xcy", false)].iter() {
+            assert_eq!(optional.matches(input), *matches, "{}", input);
+        }
+        let repeated = Pattern::new("x*(a|b)y").unwrap();
+        assert!(repeated.matches("xy"));
+        assert!(repeated.matches("xby"));
+    }
+
+    #[test]
+    fn malformed() {
+        let errors = [
+            ("\\", Error::IllegalEscape(0)),
+            ("a\\", Error::IllegalEscape(1)),
+            ("[", Error::UnclosedRange(0)),
+            ("[!", Error::UnclosedRange(0)),
+            ("[\\", Error::UnclosedRange(0)),
+            ("[]\\", Error::EmptyRange(0)),
+            ("(", Error::IllegalChar(0)),
+            ("@(", Error::UnclosedPattern(1)),
+            ("*(", Error::UnclosedPattern(1)),
+            ("@(\\", Error::UnclosedPattern(2)),
+            ("@(a\\)", Error::UnclosedPattern(4)),
+            ("@([", Error::UnclosedPattern(2)),
+            ("@(a|[)", Error::IllegalChar(5)),
+            ("**\\", Error::IllegalRecursion(2)),
+        ];
+        for (p, err) in errors.iter() {
+            assert_eq!(&Pattern::new(p).unwrap_err().inner, err, "{}", p);
+        }
+
+        // every short combination of syntax chars either parses or fails, without panicking
+        let alphabet: Vec<char> = "[]()|\\*?!@-a/".chars().collect();
+        let mut pattern = String::new();
+        for mut n in 0..alphabet.len().pow(4) {
+            pattern.clear();
+            while n > 0 {
+                n -= 1;
+                pattern.push(alphabet[n % alphabet.len()]);
+                n /= alphabet.len();
+            }
+            if let Ok(p) = Pattern::new(&pattern) {
+                p.matches("a/-a");
+            }
+        }
+
+        // deeply nested groups, broken or not, parse or fail without taking longer the deeper they go
+        let depth = super::syntax::MAX_NESTING;
+        let patterns = [
+            "@(".repeat(depth) + "a",
+            "@(".repeat(depth) + "a" + &"|)".repeat(depth),
+            "@(a|".repeat(depth) + "[b|c]" + &")".repeat(depth),
+            "+(a|".repeat(depth) + "b**" + &")".repeat(depth),
+            "!(".repeat(depth) + "a" + &")".repeat(depth - 1) + "]",
+            "?(".repeat(depth * 10) + "a" + &")".repeat(depth * 10),
+        ];
+        let start = std::time::Instant::now();
+        for pattern in patterns.iter() {
+            if let Ok(p) = Pattern::new(pattern) {
+                p.matches("a");
+            }
+        }
+        assert!(Pattern::new(&patterns[2]).unwrap().matches("|"));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn pattern_matches() {
+        let txt_pat = Pattern::new("*hello.txt").unwrap();
+        assert!(txt_pat.matches("hello.txt"));
+        assert!(txt_pat.matches("gareth_says_hello.txt"));
+        assert!(txt_pat.matches("some/path/to/hello.txt"));
+        assert!(txt_pat.matches("some\\path\\to\\hello.txt"));
+        assert!(txt_pat.matches("/an/absolute/path/to/hello.txt"));
+        assert!(!txt_pat.matches("hello.txt-and-then-some"));
+        assert!(!txt_pat.matches("goodbye.txt"));
+
+        let dir_pat = Pattern::new("*some/path/to/hello.txt").unwrap();
+        assert!(dir_pat.matches("some/path/to/hello.txt"));
+        assert!(dir_pat.matches("a/bigger/some/path/to/hello.txt"));
+        assert!(!dir_pat.matches("some/path/to/hello.txt-and-then-some"));
+        assert!(!dir_pat.matches("some/other/path/to/hello.txt"));
+    }
+
+    #[test]
+    fn ranges_plus() {
+        let pat = Pattern::new("a[0-9]b").unwrap();
+        for i in 0..10 {
+            assert!(pat.matches(&format!("a{}b", i)));
+        }
+        assert!(!pat.matches("a_b"));
+
+        let pat = Pattern::new("a[!0-9]b").unwrap();
+        for i in 0..10 {
+            assert!(!pat.matches(&format!("a{}b", i)));
+        }
+        assert!(pat.matches("a_b"));
+
+        let pats = ["[a-z123]", "[1a-z23]", "[123a-z]"];
+        for &p in pats.iter() {
+            let pat = Pattern::new(p).unwrap();
+            for c in "abcdefghijklmnopqrstuvwxyz".chars() {
+                assert!(pat.matches(&c.to_string()));
+            }
+            assert!(pat.matches("1"));
+            assert!(pat.matches("2"));
+            assert!(pat.matches("3"));
+        }
+
+        let pats = ["[abc-]", "[-abc]", "[a-c-]"];
+        for &p in pats.iter() {
+            let pat = Pattern::new(p).unwrap();
+            assert!(pat.matches("a"));
+            assert!(pat.matches("b"));
+            assert!(pat.matches("c"));
+            assert!(pat.matches("-"));
+            assert!(!pat.matches("d"));
+        }
+
+        let pat = Pattern::new("[2-1]").unwrap();
+        assert!(!pat.matches("1"));
+        assert!(!pat.matches("2"));
+
+        assert!(Pattern::new("[-]").unwrap().matches("-"));
+        assert!(!Pattern::new("[!-]").unwrap().matches("-"));
+
+        let pat = Pattern::new("[a-c-e]").unwrap();
+        for c in ["a", "b", "c", "-", "e"].iter() {
+            assert!(pat.matches(c));
+        }
+        assert!(!pat.matches("d"));
+        assert_eq!(pat.matcher.tokens(), &[Token::AnyOf(vec![
+            CharSpecifier::Char('-'), CharSpecifier::Range('a', 'c'), CharSpecifier::Char('e'),
+        ])]);
+
+        let pat = Pattern::new("[a-]").unwrap();
+        assert!(pat.matches("a"));
+        assert!(pat.matches("-"));
+        assert!(!pat.matches("b"));
+
+        assert!(!Pattern::new("[-]").unwrap().matches("a"));
+        assert!(Pattern::new("[--/]").unwrap().matches("."));
+        assert!(!Pattern::new("[a-c-e]").unwrap().matches("-c"));
+    }
+
+    #[test]
+    fn zero_or_one() {
         let p = Pattern::new("src/?([a-z]|[a-c]).rs").unwrap();
-        assert!(!p.matches("src/a.rs"));
-        assert!(!p.matches("src/b.rs"));
-        assert!(!p.matches("src/c.rs"));
+        assert!(p.matches("src/a.rs"));
+        assert!(p.matches("src/b.rs"));
+        assert!(p.matches("src/c.rs"));
         assert!(p.matches("src/d.rs"));
         assert!(p.matches("src/e.rs"));
         assert!(p.matches("src/f.rs"));
@@ -339,7 +3053,7 @@ mod tests {
         assert!(p.matches("src/d.rs"));
         assert!(p.matches("src/e.rs"));
         assert!(p.matches("src/f.rs"));
-        assert!(!p.matches("src/ggggggggg.rs"));
+        assert!(p.matches("src/ggggggggg.rs"));
         assert!(!p.matches("src/0.rs"));
         assert!(!p.matches("src/123456789.rs"));
         assert!(p.matches("src/.rs"));
@@ -354,18 +3068,59 @@ mod tests {
         assert!(p.matches("src/d.rs"));
         assert!(p.matches("src/e.rs"));
         assert!(p.matches("src/f.rs"));
-        assert!(!p.matches("src/ggggggggg.rs"));
+        assert!(p.matches("src/ggggggggg.rs"));
         assert!(!p.matches("src/0.rs"));
         assert!(!p.matches("src/123456789.rs"));
         assert!(!p.matches("src/.rs"));
     }
 
+    #[test]
+    fn repeated_patterns() {
+        // as many times as the input allows, handing back what the rest of the pattern needs
+        let p = Pattern::new("+(ab)c").unwrap();
+        assert!(p.matches("abc"));
+        assert!(p.matches("ababc"));
+        assert!(p.matches("abababc"));
+        assert!(!p.matches("abac"));
+        assert!(!p.matches("c"));
+        assert!(p.could_match_prefix("abab"));
+        assert!(!p.could_match_prefix("abb"));
+        let p = Pattern::new("*(ab)").unwrap();
+        assert!(p.matches(""));
+        assert!(p.matches("abab"));
+        assert!(!p.matches("aba"));
+        let p = Pattern::new("*(a|b)").unwrap();
+        assert!(p.matches("ab"));
+        assert!(p.matches("babba"));
+        assert!(!p.matches("abc"));
+        let p = Pattern::new("*(a|ab)bc").unwrap();
+        assert!(p.matches("aabbc"));
+        assert!(p.matches("abaabc"));
+        assert!(!p.matches("abab"));
+        let p = Pattern::new("+(*.)rs").unwrap();
+        assert!(p.matches("a.b.rs"));
+        assert!(!p.matches("ars"));
+
+        // the first repetition picks the alternative, fewer of them when lazy
+        assert_eq!(Pattern::new("+(x|y)z").unwrap().matches_with_branches("yxz"), Some(vec![1]));
+        let mut lazy = MatchOptions::new();
+        lazy.lazy = true;
+        let p = Pattern::new("*+(ab)*").unwrap();
+        assert_eq!(p.captures("xababy"), Some(vec!["xab", "y"]));
+        assert_eq!(p.captures_with("xababy", lazy), Some(vec!["x", "aby"]));
+
+        // long inputs don't take a call for every repetition
+        assert!(Pattern::new("*(a)").unwrap().matches(&"a".repeat(100_000)));
+        assert!(Pattern::new("+([0-9]|x)").unwrap().matches(&"0x".repeat(50_000)));
+        assert!(Pattern::new("+(ab)").unwrap().matches(&"ab".repeat(200)));
+    }
+
     #[test]
     fn exactly_one() {
         let p = Pattern::new("src/@([a-z]|[a-c]).rs").unwrap();
-        assert!(!p.matches("src/a.rs"));
-        assert!(!p.matches("src/b.rs"));
-        assert!(!p.matches("src/c.rs"));
+        assert!(p.matches("src/a.rs"));
+        assert!(p.matches("src/b.rs"));
+        assert!(p.matches("src/c.rs"));
         assert!(p.matches("src/d.rs"));
         assert!(p.matches("src/e.rs"));
         assert!(p.matches("src/f.rs"));
@@ -391,6 +3146,311 @@ mod tests {
         assert!(!p.matches("src/.rs"));
     }
 
+    #[test]
+    fn escaped_alternatives() {
+        let p = Pattern::new("@(a\\|b|c)").unwrap();
+        assert!(p.matches("a|b"));
+        assert!(p.matches("c"));
+        assert!(!p.matches("a"));
+        assert!(!p.matches("b"));
+
+        let matches = [
+            ("@(\\||c)", "|"),
+            ("@(a\\)|c)", "a)"),
+            ("@(\\(a|c)", "(a"),
+            ("@(\\(\\)|c)", "()"),
+            ("@(a|\\\\)", "\\"),
+            ("@(a\\\\|b)", "a\\"),
+            ("@(a|@(b\\|c|d))", "b|c"),
+            ("@(a|@(b\\|c|d))", "d"),
+            ("*(a\\|)", "a|"),
+            ("+(x|\\))", ")"),
+            ("?(\\|)x", "|x"),
+            ("@<pipe>(\\||x)", "|"),
+            ("@(a\\[b\\]|c)", "a[b]"),
+        ];
+        for &(p, input) in matches.iter() {
+            assert!(Pattern::new(p).unwrap().matches(input), "{} {}", p, input);
+        }
+        assert!(!Pattern::new("!(a\\|b)").unwrap().matches("a|b"));
+        assert!(Pattern::new("!(a\\|b)").unwrap().matches("a"));
+
+        assert_eq!(Pattern::new("@(a|\\)").unwrap_err().inner, Error::UnclosedPattern(5));
+        assert_eq!(Pattern::new("@(a\\|||b)").unwrap_err().inner, Error::IllegalOr(6));
+    }
+
+    #[test]
+    fn ranges_in_alternatives() {
+        // a | inside a range belongs to the range, not the alternatives
+        let p = Pattern::new("@(a[b|c]d|e)").unwrap();
+        for (input, matches) in [("abd", true), ("acd", true), ("a|d", true), ("e", true), ("ad", false), ("a", false), ("c]d", false)].iter() {
+            assert_eq!(p.matches(input), *matches, "{}", input);
+        }
+        let p = Pattern::new(&p.to_glob_string_normalized()).unwrap();
+        assert!(p.matches("a|d"));
+        assert!(!p.matches("ad"));
+
+        let matches = [
+            ("@([]|]|x)", "]"),
+            ("@([]|]|x)", "|"),
+            ("@([]|]|x)", "x"),
+            ("@([!|]|x)", "a"),
+            ("@(a[\\)|]b|c)", "a)b"),
+            ("@(a[\\)|]b|c)", "a|b"),
+            ("@(a[\\)|]b|c)", "c"),
+            ("@(a[b\\|c]d|e)", "a|d"),
+            ("@(a[b\\\\]|c)", "a\\"),
+            ("@(a|@(b[|]c|d)|e)", "b|c"),
+            ("@(a|@(b[|]c|d)|e)", "d"),
+            ("@(a|@(b[|]c|d)|e)", "e"),
+            ("@(\\[|b\\]|c)", "["),
+            ("@(\\[|b\\]|c)", "b]"),
+            ("[a|b]", "|"),
+        ];
+        for &(p, input) in matches.iter() {
+            assert!(Pattern::new(p).unwrap().matches(input), "{} {}", p, input);
+        }
+        assert!(!Pattern::new("@(a[b\\\\]|c)").unwrap().matches("a]|c"));
+        assert!(!Pattern::new("@([!|]|x)").unwrap().matches("|"));
+        assert!(!Pattern::new("@(a|@(b[|]c|d)|e)").unwrap().matches("b"));
+
+        // the range still has to be closed inside the pattern, and (, ) and [ still have to be escaped in it
+        assert_eq!(Pattern::new("@(a[b|c)").unwrap_err().inner, Error::IllegalChar(7));
+        assert_eq!(Pattern::new("@(a[b)|c]d)").unwrap_err().inner, Error::IllegalChar(5));
+        assert_eq!(Pattern::new("@(a[b|c]||d)").unwrap_err().inner, Error::IllegalOr(9));
+        assert_eq!(Pattern::new("@(a[b|c]d|)").unwrap_err().inner, Error::IllegalOr(10));
+
+        // every pattern is parsed as if the input started and ended with it, errors still point into the whole input
+        let p = Pattern::new("x/@(**/a|b/**)/y").unwrap();
+        assert!(p.matches("x/a/y"));
+        assert!(p.matches("x/c/a/y"));
+        assert!(p.matches("x/b/c/y"));
+        assert_eq!(Pattern::new("@(a|@(b|c**))").unwrap_err().inner, Error::IllegalRecursion(8));
+        assert_eq!(Pattern::new("@(a|@(b|**c))").unwrap_err().inner, Error::IllegalRecursion(10));
+        assert_eq!(Pattern::new("@(a|@(b|[c))").unwrap_err().inner, Error::IllegalChar(10));
+        assert_eq!(Pattern::new("@(a|@(b|\\x4))").unwrap_err().inner, Error::IllegalCodePoint(8));
+    }
+
+    #[test]
+    fn alternative_order() {
+        for &p in ["@(ab|a)bc", "@(a|ab)bc"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("abc"));
+            assert!(p.matches("abbc"));
+            assert!(!p.matches("bc"));
+            assert!(!p.matches("ababc"));
+        }
+
+        for &p in ["+(ab|a)bc", "+(a|ab)bc", "*(ab|a)bc", "*(a|ab)bc"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("abc"));
+            assert!(p.matches("abbc"));
+        }
+
+        // an alternative that takes the start of what follows the group has to give it back
+        for &p in ["@(ab|a)b", "@(a|ab)b"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("ab"));
+            assert!(p.matches("abb"));
+            assert!(!p.matches("abbb"));
+        }
+        assert!(Pattern::new("@(b*|b)b").unwrap().matches("bb"));
+        assert!(Pattern::new("@(b*|b)b").unwrap().matches("bxb"));
+        for &p in ["?(ab|a)bc", "?(a|ab)bc"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("bc"));
+            assert!(p.matches("abc"));
+            assert!(p.matches("abbc"));
+            assert!(!p.matches("aabc"));
+        }
+        let p = Pattern::new("*.@(tar.gz|tar)?(.gz)").unwrap();
+        assert!(p.matches("x.tar.gz"));
+        assert!(p.matches("x.tar"));
+        assert!(p.matches("x.tar.gz.gz"));
+        assert!(!p.matches("x.gz"));
+
+        // nested groups give back input to the rest of the pattern after the outer group too
+        let p = Pattern::new("@(x@(ab|a)|y)bc").unwrap();
+        assert!(p.matches("xabc"));
+        assert!(p.matches("xabbc"));
+        assert!(p.matches("ybc"));
+
+        // a capture holds what the alternative took in the match that was found
+        let p = Pattern::new("@<x>(ab|a)bc").unwrap();
+        assert_eq!(p.captures_named("abc").unwrap()["x"], "a");
+        assert_eq!(p.captures_named("abbc").unwrap()["x"], "ab");
+
+        // both alternatives can match a, either one is enough
+        for &p in ["@(a|a*)bc", "@(a*|a)bc", "?(a|a*)bc", "?(a*|a)bc"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("abc"));
+            assert!(p.matches("axbc"));
+            assert!(!p.matches("xbc"));
+        }
+
+        // overlapping ranges
+        for &p in ["@([a-c]|[b-d])", "@([b-d]|[a-c])", "?([a-c]|[b-d])", "@(b|[a-c])", "@(b|b)"].iter() {
+            let p = Pattern::new(p).unwrap();
+            assert!(p.matches("b"), "{:?}", p);
+            assert!(!p.matches("e"), "{:?}", p);
+            assert!(!p.matches("bb"), "{:?}", p);
+        }
+        assert!(Pattern::new("?([a-c]|[b-d])").unwrap().matches(""));
+        assert!(Pattern::new("@([a-c]|[b-d])").unwrap().matches("a"));
+        assert!(Pattern::new("@([a-c]|[b-d])").unwrap().matches("d"));
+    }
+
+    #[test]
+    fn suffix() {
+        let p = Pattern::new("*hello.txt").unwrap();
+        assert!(p.matches("hello.txt"));
+        assert!(p.matches("gareth_says_hello.txt"));
+        assert!(p.matches("a/b/hello.txt"));
+        assert!(!p.matches("hello.txt.bak"));
+        assert!(!p.matches("ello.txt"));
+        assert!(!p.matches(""));
+
+        let mut options = MatchOptions::new();
+        options.require_literal_separator = true;
+        assert!(p.matches_with("says_hello.txt", options));
+        assert!(!p.matches_with("a/hello.txt", options));
+        options = MatchOptions::new();
+        options.case_insensitive = true;
+        assert!(p.matches_with("SAYS_HELLO.TXT", options));
+        assert!(!p.matches("SAYS_HELLO.TXT"));
+
+        // a * followed only by chars is settled by the end of the input alone
+        let p = Pattern::new("*.rs").unwrap();
+        assert!(p.matcher.is_star_suffix());
+        assert!(p.matches("/src/test.rs"));
+        assert!(p.matches(".rs"));
+        assert!(!p.matches("/src/test.c"));
+        assert!(!p.matches("rs"));
+        assert!(Pattern::new("*").unwrap().matcher.is_star_suffix());
+        for p in ["src/*.rs", "*a*.rs", "**/*.rs", "*.[rs]", "main.rs", "?.rs"].iter() {
+            assert!(!Pattern::new(p).unwrap().matcher.is_star_suffix(), "{}", p);
+        }
+
+        // the suffix has to be matched by the chars at the end, not by a wildcard
+        for &(p, input, matches) in [
+            ("src/*.rs", "src/main.rs", true),
+            ("src/*.rs", "lib/main.rs", false),
+            ("src/*.rs", "src/main.c", false),
+            ("a*ab", "ab", false),
+            ("a*ab", "aab", true),
+            ("*a", "a", true),
+            ("?(x)yz", "yz", true),
+            ("*", "", true),
+            ("**/", "a/", true),
+            ("**/x", "x", true),
+            ("**//x", "x", true),
+            ("@(a|**)/x", "x", true),
+            ("*(**/)x", "x", true),
+            ("[a-z]é", "aé", true),
+            ("[a-z]é", "ae", false),
+        ].iter() {
+            assert_eq!(Pattern::new(p).unwrap().matches(input), matches, "{} {}", p, input);
+        }
+    }
+
+    #[test]
+    fn pathological() {
+        // would backtrack through every way of splitting the input between the wildcards
+        let input = "a".repeat(200);
+        assert!(!Pattern::new("a*a*a*a*a*a*a*a*a*a*a*a*b").unwrap().matches(&input));
+        assert!(!Pattern::new("*a*a*a*a*a*a*a*a*a*a*a*a*?b").unwrap().matches(&input));
+        assert!(Pattern::new("a*a*a*a*a*a*a*a*a*a*a*a*a").unwrap().matches(&input));
+
+        let path = "a/".repeat(100);
+        assert!(!Pattern::new("**/a/**/a/**/a/**/a/**/a/**/b").unwrap().matches(&path));
+
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        options.normalize_separators = true;
+        let p = Pattern::new("A*/*B*c").unwrap();
+        assert!(p.matches_with("ax\\yBzC", options));
+        assert!(!p.matches("ax\\yBzC"));
+        options.require_literal_separator = true;
+        assert!(p.matches_with("ax\\ybzC", options));
+        assert!(!p.matches_with("ax/y/bzC", options));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn timeout() {
+        use std::time::{Duration, Instant};
+        use super::Timeout;
+
+        // every wildcard tries every offset since @(b) never runs out of input
+        let p = Pattern::new("*@(a)*@(a)*@(a)*@(a)*@(a)*@(a)*@(a)@(b)").unwrap();
+        let input = "a".repeat(100);
+        let start = Instant::now();
+        assert_eq!(p.matches_with_timeout(&input, Duration::from_millis(10)), Err(Timeout));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        assert_eq!(p.matches_with_timeout("aaaaaaab", Duration::from_secs(60)), Ok(true));
+        assert_eq!(p.matches_with_timeout("aaab", Duration::from_secs(60)), Ok(false));
+        assert_eq!(Pattern::new("a*a*a*b").unwrap().matches_with_timeout(&input, Duration::from_secs(0)), Ok(false));
+        assert_eq!(Pattern::new("src/*.rs").unwrap().matches_with_timeout("src/main.rs", Duration::from_secs(0)), Ok(true));
+    }
+
+    #[test]
+    fn matches_counted() {
+        let input = "a".repeat(20);
+        let (matched, linear) = Pattern::new(&input).unwrap().matches_counted(&input);
+        assert!(matched);
+        assert_eq!(linear.token_visits, 20);
+        assert_eq!(linear.backtracks, 0);
+        assert_eq!(linear.max_depth, 1);
+
+        // every wildcard retries the rest of the pattern at every offset
+        let (matched, heavy) = Pattern::new("*@(a)*@(a)*@(a)b").unwrap().matches_counted(&input);
+        assert!(!matched);
+        assert!(heavy.token_visits > 1000, "{:?}", heavy);
+        assert!(heavy.backtracks > 1000, "{:?}", heavy);
+        assert!(heavy.max_depth > linear.max_depth, "{:?}", heavy);
+
+        // the counts don't change what matches
+        assert!(Pattern::new("*@(a)*@(a)*@(a)b").unwrap().matches_counted("xaxaxab").0);
+        assert!(!Pattern::new("src/*.rs").unwrap().matches_counted("src/main.c").0);
+    }
+
+    #[test]
+    fn literal_scan() {
+        // matching a string searches for the chars after a *, matching chars tries every offset
+        let patterns = ["*needle*", "a*needle*needle", "*needle?", "*/needle/*", "*(x|needle)needle*", "**/needle.rs",
+            "*nee*dle", "*néédle", "*needle", "a*b*c", "*needle!(*needle)"];
+        let inputs = ["needle", "xneedley", "aneedleneedle", "aneedlexneedle", "needl", "neeneedle", "a/needle/b",
+            "needleneedle", "x/y/needle.rs", "néédle", "xnéédle", "nee/dle", "needleneedlex", ""];
+        for p in patterns.iter() {
+            let pattern = Pattern::new(p).unwrap();
+            for input in inputs.iter() {
+                assert_eq!(pattern.matches(input), pattern.matches_chars(input.chars()), "{} {}", p, input);
+                assert_eq!(pattern.matches_counted(input).0, pattern.matches_chars(input.chars()), "{} {}", p, input);
+            }
+        }
+
+        let p = PatternBuilder::new("*/needle").require_literal_separator(true).build().unwrap();
+        assert!(p.matches("a/needle"));
+        assert!(!p.matches("a/b/needle"));
+        assert!(Pattern::new("*aaab").unwrap().matches(&("a".repeat(1000) + "b")));
+    }
+
+    #[test]
+    fn wildcard_greediness() {
+        // shortest or longest, the whole input has to be consumed
+        let p = Pattern::new("*a*").unwrap();
+        assert!(p.matches("a"));
+        assert!(p.matches("aaa"));
+        assert!(p.matches("bab"));
+        assert!(!p.matches("bbb"));
+
+        let p = Pattern::new("*.*").unwrap();
+        assert!(p.matches("archive.tar.gz"));
+        assert!(p.matches("."));
+        assert!(!p.matches("archive"));
+    }
+
     #[test]
     fn extra() {
         let p = Pattern::new("/var/log/!(containers)*/**").unwrap();
@@ -0,0 +1,150 @@
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use crate::Pattern;
+use crate::syntax::Token;
+
+/// Patterns mapped to values, looking up the value of the pattern an input matches, like a router.
+///
+/// By default the pattern inserted first wins when several match,
+/// see [`prefer_most_specific`](GlobMap::prefer_most_specific) to pick by the literal start of the patterns instead.
+/// ```rust
+/// # use globber::{GlobMap, Pattern};
+/// let mut map = GlobMap::new();
+/// map.insert(Pattern::new("src/bin/*.rs").unwrap(), "binary");
+/// map.insert(Pattern::new("src/**/*.rs").unwrap(), "source");
+/// assert_eq!(map.lookup("src/bin/tool.rs"), Some(&"binary"));
+/// assert_eq!(map.lookup("src/lib.rs"), Some(&"source"));
+/// assert_eq!(map.lookup("Cargo.toml"), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GlobMap<T> {
+    entries: Vec<Entry<T>>,
+    most_specific: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    pattern: Pattern,
+    // how many chars the pattern starts with before its first wildcard, range or pattern
+    prefix: usize,
+    value: T,
+}
+
+impl<T> GlobMap<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            most_specific: false,
+        }
+    }
+
+    /// When several patterns match, let the one starting with the longest literal win,
+    /// e.g. `src/bin/*` over `src/*`. Patterns starting with equally many chars still go by insertion order.
+    ///
+    /// Defaults to `false`, the first pattern inserted wins.
+    pub fn prefer_most_specific(&mut self, yes: bool) -> &mut Self {
+        self.most_specific = yes;
+        self
+    }
+
+    /// Adds a pattern with its value, returning its index.
+    pub fn insert(&mut self, pattern: Pattern, value: T) -> usize {
+        let prefix = pattern.matcher.tokens().iter()
+            .take_while(|t| matches!(t, Token::Char(_)))
+            .count();
+        self.entries.push(Entry { pattern, prefix, value });
+        self.entries.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the value of the pattern matching the input with the highest priority.
+    pub fn lookup(&self, input: &str) -> Option<&T> {
+        self.lookup_index(input).map(|i| &self.entries[i].value)
+    }
+
+    /// Returns the index of the pattern matching the input with the highest priority.
+    pub fn lookup_index(&self, input: &str) -> Option<usize> {
+        let mut matches = self.entries.iter()
+            .enumerate()
+            .filter(|(_, e)| e.pattern.matches(input));
+
+        if !self.most_specific {
+            return matches.next().map(|(i, _)| i);
+        }
+
+        // the first of the longest, max_by_key would return the last
+        matches.fold(None, |best: Option<(usize, &Entry<T>)>, (i, e)| match best {
+            Some((_, b)) if b.prefix >= e.prefix => best,
+            _ => Some((i, e)),
+        }).map(|(i, _)| i)
+    }
+}
+
+impl<T> Default for GlobMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(Pattern, T)> for GlobMap<T> {
+    fn from_iter<I: IntoIterator<Item=(Pattern, T)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<T> Extend<(Pattern, T)> for GlobMap<T> {
+    fn extend<I: IntoIterator<Item=(Pattern, T)>>(&mut self, iter: I) {
+        for (pattern, value) in iter {
+            self.insert(pattern, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GlobMap;
+    use crate::Pattern;
+
+    fn map(patterns: &[(&str, u32)]) -> GlobMap<u32> {
+        patterns.iter()
+            .map(|&(p, v)| (Pattern::new(p).unwrap(), v))
+            .collect()
+    }
+
+    #[test]
+    fn first_wins() {
+        let map = map(&[("*.rs", 1), ("src/*.rs", 2), ("src/main.rs", 3), ("**", 4)]);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.lookup("src/main.rs"), Some(&1));
+        assert_eq!(map.lookup("src/lib.rs"), Some(&1));
+        assert_eq!(map.lookup("Cargo.toml"), Some(&4));
+        assert_eq!(map.lookup_index("Cargo.toml"), Some(3));
+        assert_eq!(GlobMap::<u32>::new().lookup(""), None);
+        assert!(GlobMap::<u32>::default().is_empty());
+    }
+
+    #[test]
+    fn most_specific() {
+        let mut map = map(&[("**", 1), ("*.rs", 2), ("src/*.rs", 3), ("src/main.rs", 4), ("src/*", 5), ("src/bin/*", 6)]);
+        map.prefer_most_specific(true);
+        assert_eq!(map.lookup("src/main.rs"), Some(&4));
+        assert_eq!(map.lookup("src/lib.rs"), Some(&3));
+        assert_eq!(map.lookup("src/lib.c"), Some(&5));
+        assert_eq!(map.lookup("src/bin/a.rs"), Some(&6));
+        assert_eq!(map.lookup("lib.rs"), Some(&1));
+        assert_eq!(map.lookup("Cargo.toml"), Some(&1));
+
+        map.prefer_most_specific(false);
+        assert_eq!(map.lookup("src/main.rs"), Some(&1));
+    }
+}
\ No newline at end of file
@@ -1,184 +1,793 @@
-use std::path::is_separator;
-use std::str::Chars;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
 
+use crate::analysis::{complexity, suffix_len_bounds, Complexity};
+use crate::explain::{MatchExplanation, MatchMetrics};
 use crate::matcher::Status::*;
+use crate::nfa::Nfa;
+use crate::NO_BRANCH;
+use crate::options::MatchOptions;
 use crate::syntax::{CharSpecifier, Token};
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+// shared so that cloning a pattern, e.g. into every set it is part of, never copies its tokens
+#[derive(Clone, Debug)]
 pub(crate) struct Matcher {
-    tokens: Vec<Token>,
+    tokens: Arc<[Token]>,
+    literal: Option<Arc<str>>,
+    // the chars after the last token that isn't a char, every match ends with them
+    // except for separators a ** can skip
+    suffix: Arc<str>,
+    // a single * before the suffix, like *.rs, which matches exactly when the input ends with the suffix
+    star_suffix: bool,
+    // backtracking over several wildcards can take polynomial time, without groups
+    // every position in the tokens can be tracked at once instead, which is linear
+    simulate: bool,
+    // the fewest and most chars a match can have
+    len_bounds: (usize, Option<usize>),
+    // the fewest chars the tokens from each index on need, which stops backtracking that can't fit the input
+    min_lens: Arc<[usize]>,
+    is_separator: fn(char) -> bool,
+}
+
+// the separator predicate is compared by address
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.tokens == other.tokens && self.is_separator as usize == other.is_separator as usize
+    }
+}
+
+impl Eq for Matcher {}
+
+impl Hash for Matcher {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tokens.hash(state);
+        (self.is_separator as usize).hash(state);
+    }
+}
+
+struct Context<'a> {
+    options: &'a MatchOptions,
+    is_separator: fn(char) -> bool,
+    // the top level tokens, only wildcards among them are captured
+    root: &'a [Token],
+    // the byte length of the whole input, where a trailing wildcard capture ends, 0 when it isn't known
+    len: usize,
+    // the whole input when it is a string, which a wildcard searches for the chars following it
+    text: Option<&'a str>,
+    // the fewest chars the top level tokens from each index on need, empty to not give up early
+    min_lens: &'a [usize],
+    // for every byte offset into the input whether a grapheme cluster starts or ends there,
+    // empty when every char is matched on its own
+    clusters: &'a [bool],
+    // try the longest match of a wildcard first, which only matters for captures
+    greedy: bool,
+    // (what, start, end) of everything captured on the path that is currently matching
+    captures: Option<Vec<Capture<'a>>>,
+    // (offset, top level token) of the failure that got the furthest while capturing,
+    // no token means the input was longer than the pattern
+    failure: Option<Failure>,
+    // asked every so often whether to give up, after that every call fails right away
+    expired: Option<&'a dyn Fn() -> bool>,
+    calls: usize,
+    aborted: bool,
+    // running out of input means more of it could still match instead of failing
+    partial: bool,
+    // counts the work done when asked for, along with how many calls deep matching is right now
+    metrics: Option<MatchMetrics>,
+    depth: usize,
+}
+
+type Capture<'a> = (Slot<'a>, usize, usize);
+type Failure = (usize, Option<usize>);
+
+// what a capture belongs to
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+enum Slot<'a> {
+    // the index of a top level wildcard
+    Wildcard(usize),
+    Named(&'a str),
+    // the index of a top level token, only its start is known
+    Token(usize),
+    // the index of a top level pattern like @(...) and of the alternative in it that matched
+    Branch(usize, usize),
+}
+
+impl<'a> Context<'a> {
+    fn capture(&mut self, token: &Token, start: usize, end: usize) {
+        if let Some(captures) = &mut self.captures {
+            let root = self.root.as_ptr_range();
+            if root.contains(&(token as *const Token)) {
+                let index = (token as *const Token as usize - root.start as usize) / core::mem::size_of::<Token>();
+                captures.push((Slot::Wildcard(index), start, end));
+            }
+        }
+    }
+
+    // a pattern counts as top level also when it is named
+    fn capture_branch(&mut self, group: &Token, branch: usize, offset: usize) {
+        let index = self.root.iter()
+            .position(|t| core::ptr::eq(t, group) || matches!(t, Token::Named(_, p) if core::ptr::eq(&**p, group)));
+        if let (Some(captures), Some(index)) = (&mut self.captures, index) {
+            captures.push((Slot::Branch(index, branch), offset, offset));
+        }
+    }
+
+    fn capture_named(&mut self, name: &'a str, start: usize, end: usize) {
+        if let Some(captures) = &mut self.captures {
+            captures.push((Slot::Named(name), start, end));
+        }
+    }
+
+    // remembers where a top level token started matching
+    fn enter(&mut self, index: usize, offset: usize) {
+        if let Some(captures) = &mut self.captures {
+            captures.push((Slot::Token(index), offset, offset));
+        }
+    }
+
+    // keeps the failure furthest into the pattern, then furthest into the input
+    fn fail(&mut self, offset: usize, token: Option<usize>) {
+        let progress = |offset, token: Option<usize>| (token.unwrap_or(usize::MAX), offset);
+        let further = match self.failure {
+            Some((o, t)) => progress(offset, token) > progress(o, t),
+            None => true,
+        };
+        if self.captures.is_some() && further {
+            self.failure = Some((offset, token));
+        }
+    }
+
+    // the input ended where tokens[i] needed another char
+    fn ran_out(&mut self, offset: usize, token: usize) -> Status {
+        if self.partial {
+            return Match;
+        }
+        self.fail(offset, Some(token));
+        NoMatch
+    }
+
+    fn abort(&mut self) -> bool {
+        if let Some(expired) = self.expired {
+            self.calls += 1;
+            if !self.aborted && self.calls >= 1024 {
+                self.calls = 0;
+                self.aborted = expired();
+            }
+        }
+        self.aborted
+    }
+
+    // drops captures recorded after mark, for matches that are thrown away again
+    fn rewind(&mut self, mark: usize) {
+        if let Some(captures) = &mut self.captures {
+            captures.truncate(mark);
+        }
+    }
+
+    fn mark(&self) -> usize {
+        self.captures.as_ref().map_or(0, |c| c.len())
+    }
+
+    // true when the offset is in the middle of a grapheme cluster
+    fn in_cluster(&self, offset: usize) -> bool {
+        matches!(self.clusters.get(offset), Some(false))
+    }
+
+    // takes the rest of the grapheme cluster the last char started
+    fn finish_cluster<I: Iterator<Item=char>>(&self, input: &mut Input<I>) {
+        while self.in_cluster(input.offset) && input.next().is_some() {}
+    }
+}
+
+// the chars left to match, counting the bytes of input before them for captures
+#[derive(Clone)]
+struct Input<I> {
+    chars: I,
+    offset: usize,
+    // the char before them, which decides whether a . starts a path component
+    last: Option<char>,
+}
+
+impl<I: Iterator<Item=char>> Iterator for Input<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        self.last = Some(c);
+        Some(c)
+    }
+}
+
+// the tokens still to be matched after the pattern inside a group, a group can't own them
+// without copying, so they are chained on the stack instead
+#[derive(Clone, Copy)]
+struct Rest<'t, 'r> {
+    tokens: &'t [Token],
+    i: usize,
+    next: Option<&'r Rest<'t, 'r>>,
+    // the name of a named pattern and where it started, it ends where the rest starts
+    name: Option<(&'t str, usize)>,
+    // where a repetition of the *(...) or +(...) at i - 1 started, the pattern can match again after it
+    repeat: Option<usize>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Status {
+pub(crate) enum Status {
     Match,
     Retryable,
     NoMatch,
 }
 
 impl Matcher {
-    pub(crate) fn new(tokens: Vec<Token>) -> Self {
+    pub(crate) fn new(tokens: Vec<Token>, is_separator: fn(char) -> bool) -> Self {
+        let literal = tokens.iter()
+            .map(|t| match t {
+                Token::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect::<Option<String>>()
+            .map(Arc::from);
+        let mut suffix_start = tokens.len() - tokens.iter().rev().take_while(|t| matches!(t, Token::Char(_))).count();
+        // a ** matching nothing takes the separators after it along, also when it ends a group
+        let before = suffix_start.checked_sub(1).map(|i| &tokens[i]);
+        if !matches!(before, None | Some(Token::AnyChar) | Some(Token::AnySequence) | Some(Token::AtLeastOneSegment)
+            | Some(Token::AnyOf(_)) | Some(Token::NotAnyOf(_))) {
+            suffix_start += tokens[suffix_start..].iter()
+                .take_while(|t| matches!(t, Token::Char(c) if is_separator(*c)))
+                .count();
+        }
+        let suffix = tokens[suffix_start..].iter()
+            .filter_map(|t| match t {
+                Token::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect::<String>()
+            .into();
+
+        let star_suffix = suffix_start == 1 && tokens[0] == Token::AnySequence;
+        let simulate = complexity(&tokens) != Complexity::Linear && Nfa::new(&tokens, is_separator, MatchOptions::new()).is_some();
+        let suffix_bounds = suffix_len_bounds(&tokens, is_separator);
+        let len_bounds = suffix_bounds[0];
+        let min_lens = suffix_bounds.iter().map(|(min, _)| *min).collect();
+
         Self {
-            tokens,
+            tokens: tokens.into(),
+            literal,
+            suffix,
+            star_suffix,
+            simulate,
+            len_bounds,
+            min_lens,
+            is_separator,
         }
     }
 
-    pub(crate) fn matches(&self, input: Chars) -> bool {
-        match_index(&self.tokens, 0, input) == Status::Match
+    pub(crate) fn tokens(&self) -> &[Token] {
+        &self.tokens
     }
-}
 
-fn match_index(tokens: &Vec<Token>, i: usize, mut input: Chars) -> Status {
-    for (ti, token) in tokens[i..].iter().enumerate() {
-        match token {
-            Token::AnyRecursive | Token::AnySequence => {
-                let result = match_index(tokens, i + ti + 1, input.clone());
-                match result {
-                    Status::Retryable => {}
-                    _ => return result,
-                }
+    pub(crate) fn is_separator(&self) -> fn(char) -> bool {
+        self.is_separator
+    }
 
-                if *token == Token::AnyRecursive {
-                    if let Some(t) = tokens.get(i + ti + 1) {
-                        match t {
-                            Token::Char(c) if is_separator(*c) => {
-                                match match_index(tokens, i + ti + 2, input.clone()) {
-                                    Status::Retryable => {}
-                                    m => return m,
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+    pub(crate) fn is_literal(&self) -> bool {
+        self.literal.is_some()
+    }
 
-                while let Some(_) = input.next() {
-                    match match_index(tokens, i + ti + 1, input.clone()) {
-                        Status::Retryable => {}
-                        m => return m,
-                    }
-                }
-            }
-            Token::Char(c) => {
-                let next = match input.next() {
-                    Some(c) => c,
-                    None => return Status::NoMatch,
-                };
+    pub(crate) fn literal(&self) -> Option<&str> {
+        self.literal.as_deref()
+    }
 
-                if *c != next {
-                    return Status::Retryable;
-                }
+    pub(crate) fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    pub(crate) fn len_bounds(&self) -> (usize, Option<usize>) {
+        self.len_bounds
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_star_suffix(&self) -> bool {
+        self.star_suffix
+    }
+
+    /// The chars after the `*` of a pattern like `*.rs` if it matches exactly the input ending with them
+    /// under these options.
+    pub(crate) fn star_suffix(&self, options: &MatchOptions) -> Option<&str> {
+        let plain = options.exact_chars() && !options.require_literal_separator && !options.newline_is_boundary
+            && !options.require_literal_leading_dot;
+        if self.star_suffix && plain {
+            Some(&self.suffix)
+        } else {
+            None
+        }
+    }
+
+    // a char takes one to four bytes, so the byte length alone often settles it
+    fn fits_len_bounds(&self, input: &str, options: &MatchOptions) -> bool {
+        let (min, max) = self.len_bounds;
+        if input.len() < min {
+            return false;
+        }
+        match max {
+            Some(max) if !options.unanchored_start && input.len() > max => input.chars().count() <= max,
+            _ => true,
+        }
+    }
+
+    pub(crate) fn matches<I: Iterator<Item=char> + Clone>(&self, input: I, options: &MatchOptions) -> bool {
+        self.matches_text(input, None, options)
+    }
+
+    // the input as a string, if known, lets backtracking give up once the rest of the pattern can't fit
+    // and wildcards search for the chars after them
+    fn matches_text<I: Iterator<Item=char> + Clone>(&self, input: I, text: Option<&str>, options: &MatchOptions) -> bool {
+        // only chars, no need to walk the tokens
+        if let Some(literal) = &self.literal {
+            if options.exact_chars() && !options.unanchored_start {
+                return input.eq(literal.chars());
             }
-            Token::AnyChar => { return Status::Match; }
-            Token::AnyOf(specifiers) => {
-                let next = match input.next() {
-                    Some(c) => c,
-                    None => return NoMatch,
-                };
-
-                match match_specifiers(specifiers, next) {
-                    Match => {}
-                    Retryable => return Retryable,
-                    _ => { unreachable!() }
-                }
+        }
+
+        if self.simulate {
+            // None when the options need counting, like max_depth
+            if let Some(nfa) = Nfa::new(&self.tokens, self.is_separator, *options) {
+                return nfa.matches(input);
             }
-            Token::NotAnyOf(specifiers) => {
-                let next = match input.next() {
-                    Some(c) => c,
-                    None => return NoMatch,
-                };
+        }
 
-                match match_specifiers(specifiers, next) {
-                    Retryable => {}
-                    Match => return Retryable,
-                    _ => { unreachable!() }
-                };
+        self.match_tokens(&self.tokens, input, text, options)
+    }
+
+    /// Like [`matches`](Matcher::matches) but checks the end of the input against the chars the pattern ends with
+    /// first, which settles patterns like `*.rs` entirely and rejects most other input without backtracking.
+    pub(crate) fn matches_str(&self, input: &str, options: &MatchOptions) -> bool {
+        if let (Some(literal), true) = (&self.literal, options.unanchored_start) {
+            if options.exact_chars() {
+                return input.ends_with(&**literal);
             }
-            Token::ZeroOrOne(patterns) => {
-                let mut matches = 0;
+        }
 
-                for t in patterns {
-                    let mut t = t.clone();
-                    t.extend_from_slice(&tokens[i + ti + 1..]);
+        // a * can take any input before the suffix
+        if let Some(suffix) = self.star_suffix(options) {
+            return input.ends_with(suffix);
+        }
 
-                    match match_index(&mut t, 0, input.clone()) {
-                        Match => matches += 1,
-                        _ => {}
-                    }
+        if !self.fits_len_bounds(input, options) {
+            return false;
+        }
 
-                    if matches > 1 {
-                        return Retryable;
-                    }
-                }
+        if self.literal.is_none() && options.exact_chars() && !input.ends_with(&*self.suffix) {
+            return false;
+        }
 
-                if matches == 1 {
-                    return Match;
-                }
+        self.matches_text(input.chars(), Some(input), options)
+    }
 
-                return match_index(tokens, i + ti + 1, input);
-            }
-            Token::ZeroOrMore(patterns) => {
-                for t in patterns {
-                    let mut t = t.clone();
-                    t.extend_from_slice(&tokens[i + ti + 1..]);
+    /// The non overlapping byte ranges of the input the pattern matches, going from left to right
+    /// and taking the longest match at each start. Empty matches are left out.
+    pub(crate) fn match_positions(&self, input: &str, options: &MatchOptions) -> Vec<(usize, usize)> {
+        let mut options = *options;
+        options.unanchored_start = false;
+        let nfa = Nfa::new(&self.tokens, self.is_separator, options);
 
-                    match match_index(&mut t, 0, input.clone()) {
-                        Match => return Match,
-                        _ => {}
-                    }
+        let mut positions = Vec::new();
+        let mut start = 0;
+        while start < input.len() {
+            let rest = &input[start..];
+            let longest = match &nfa {
+                Some(nfa) => nfa.longest_match(rest),
+                None => (1..=rest.len()).rev()
+                    .filter(|end| rest.is_char_boundary(*end))
+                    .find(|end| self.matches_str(&rest[..*end], &options)),
+            };
+
+            match longest {
+                Some(len) if len > 0 => {
+                    positions.push((start, start + len));
+                    start += len;
                 }
+                _ => start += rest.chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        positions
+    }
+
+    /// True when the pattern ends with a separator, meaning it only matches directories.
+    pub(crate) fn is_dir_only(&self) -> bool {
+        self.tokens.len() > 1 && matches!(self.tokens.last(), Some(Token::Char(c)) if (self.is_separator)(*c))
+    }
 
-                return match_index(tokens, i + ti + 1, input);
+    /// Matches the name of a directory against a dir only pattern, ignoring its trailing separator.
+    pub(crate) fn matches_dir<I: Iterator<Item=char> + Clone>(&self, input: I, options: &MatchOptions) -> bool {
+        debug_assert!(self.is_dir_only());
+        self.match_tokens(&self.tokens[..self.tokens.len() - 1], input, None, options)
+    }
+
+    // the input as a string can only be given along with all of the tokens
+    fn match_tokens<I: Iterator<Item=char> + Clone>(&self, tokens: &[Token], input: I, text: Option<&str>, options: &MatchOptions) -> bool {
+        debug_assert!(text.is_none() || tokens.len() == self.tokens.len());
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: tokens,
+            len: text.map_or(0, str::len),
+            text,
+            min_lens: if text.is_some() { &self.min_lens } else { &[] },
+            clusters: &[],
+            greedy: false,
+            captures: None,
+            failure: None,
+            expired: None,
+            calls: 0,
+            aborted: false,
+            partial: false,
+            metrics: None,
+            depth: 0,
+        };
+        match_start(tokens, Input { chars: input, offset: 0, last: None }, &mut ctx) == Status::Match
+    }
+
+    /// Matches like [`matches_str`](Matcher::matches_str) with `?` and ranges taking a whole grapheme cluster
+    /// and wildcards only ending between clusters.
+    #[cfg(feature = "unicode")]
+    pub(crate) fn matches_graphemes(&self, input: &str, options: &MatchOptions) -> bool {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let mut clusters = vec![false; input.len() + 1];
+        for (start, _) in input.grapheme_indices(true) {
+            clusters[start] = true;
+        }
+        clusters[input.len()] = true;
+
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: &self.tokens,
+            len: input.len(),
+            text: None,
+            min_lens: &self.min_lens,
+            clusters: &clusters,
+            greedy: false,
+            captures: None,
+            failure: None,
+            expired: None,
+            calls: 0,
+            aborted: false,
+            partial: false,
+            metrics: None,
+            depth: 0,
+        };
+        match_start(&self.tokens, Input { chars: input.chars(), offset: 0, last: None }, &mut ctx) == Status::Match
+    }
+
+    /// True if some more input appended to the input could make it match.
+    pub(crate) fn matches_prefix(&self, input: &str, options: &MatchOptions) -> bool {
+        if let Some(literal) = &self.literal {
+            if options.exact_chars() && !options.unanchored_start {
+                return literal.starts_with(input);
             }
-            Token::OneOrMore(patterns) => {
-                for t in patterns {
-                    let mut t = t.clone();
-                    t.extend_from_slice(&tokens[i + ti + 1..]);
+        }
 
-                    match match_index(&mut t, 0, input.clone()) {
-                        Match => return Match,
-                        _ => {}
-                    }
+        if self.simulate {
+            if let Some(nfa) = Nfa::new(&self.tokens, self.is_separator, *options) {
+                return nfa.matches_prefix(input.chars());
+            }
+        }
+
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: &self.tokens,
+            len: 0,
+            text: None,
+            min_lens: &[],
+            clusters: &[],
+            greedy: false,
+            captures: None,
+            failure: None,
+            expired: None,
+            calls: 0,
+            aborted: false,
+            partial: true,
+            metrics: None,
+            depth: 0,
+        };
+        match_start(&self.tokens, Input { chars: input.chars(), offset: 0, last: None }, &mut ctx) == Status::Match
+    }
+
+    /// Like [`matches_str`](Matcher::matches_str) but gives up with `None` once `expired` returns true,
+    /// which is only checked every so often.
+    #[cfg(feature = "std")]
+    pub(crate) fn matches_until<'a>(&'a self, input: &'a str, options: &'a MatchOptions, expired: &'a dyn Fn() -> bool) -> Option<bool> {
+        // these never backtrack
+        if self.literal.is_some() || self.simulate {
+            return Some(self.matches_str(input, options));
+        }
+
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: &self.tokens,
+            len: input.len(),
+            text: Some(input),
+            min_lens: &self.min_lens,
+            clusters: &[],
+            greedy: false,
+            captures: None,
+            failure: None,
+            expired: Some(expired),
+            calls: 0,
+            aborted: false,
+            partial: false,
+            metrics: None,
+            depth: 0,
+        };
+        let status = match_start(&self.tokens, Input { chars: input.chars(), offset: 0, last: None }, &mut ctx);
+        if ctx.aborted {
+            return None;
+        }
+        Some(status == Match)
+    }
+
+    /// Matches by backtracking like [`matches_str`](Matcher::matches_str) falls back to, counting the work done.
+    pub(crate) fn matches_counted(&self, input: &str, options: &MatchOptions) -> (bool, MatchMetrics) {
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: &self.tokens,
+            len: input.len(),
+            text: Some(input),
+            min_lens: &self.min_lens,
+            clusters: &[],
+            greedy: false,
+            captures: None,
+            failure: None,
+            expired: None,
+            calls: 0,
+            aborted: false,
+            partial: false,
+            metrics: Some(MatchMetrics::default()),
+            depth: 0,
+        };
+        let status = match_start(&self.tokens, Input { chars: input.chars(), offset: 0, last: None }, &mut ctx);
+        (status == Match, ctx.metrics.unwrap_or_default())
+    }
+
+    /// The input matched by every top level wildcard in order, if the input matches.
+    pub(crate) fn captures<'s>(&self, input: &'s str, options: &MatchOptions) -> Option<Vec<&'s str>> {
+        let mut captures = self.trace(input, options).ok()?;
+        captures.sort_unstable();
+        Some(captures.into_iter()
+            .filter(|(slot, _, _)| matches!(slot, Slot::Wildcard(_)))
+            .map(|(_, start, end)| &input[start..end])
+            .collect())
+    }
+
+    /// The input matched by every named pattern, if the input matches.
+    pub(crate) fn captures_named<'s>(&self, input: &'s str, options: &MatchOptions) -> Option<BTreeMap<String, &'s str>> {
+        let mut captures = self.trace(input, options).ok()?;
+        // when a name is used more than once the last one in the input wins
+        captures.sort_unstable_by_key(|&(_, start, end)| (start, end));
+        Some(captures.into_iter()
+            .filter_map(|(slot, start, end)| match slot {
+                Slot::Named(name) => Some((name.into(), &input[start..end])),
+                Slot::Wildcard(_) | Slot::Token(_) | Slot::Branch(..) => None,
+            })
+            .collect())
+    }
+
+    /// The alternative that matched for every top level pattern like `@(...)` in order if the input matches,
+    /// `NO_BRANCH` for a pattern that matched nothing or is a `!(...)`.
+    pub(crate) fn branches(&self, input: &str, options: &MatchOptions) -> Option<Vec<usize>> {
+        let captures = self.trace(input, options).ok()?;
+        let mut branches: Vec<_> = self.tokens.iter()
+            .map(|t| match t {
+                Token::Named(_, pattern) => &**pattern,
+                t => t,
+            })
+            .enumerate()
+            .filter(|(_, t)| matches!(t, Token::ZeroOrOne(_) | Token::ZeroOrMore(_) | Token::OneOrMore(_)
+                | Token::ExactlyOne(_) | Token::NoneOf(_)))
+            .map(|(i, _)| (i, NO_BRANCH))
+            .collect();
+        for (slot, _, _) in captures {
+            if let Slot::Branch(index, branch) = slot {
+                if let Ok(i) = branches.binary_search_by_key(&index, |(i, _)| *i) {
+                    branches[i].1 = branch;
                 }
+            }
+        }
+        Some(branches.into_iter().map(|(_, branch)| branch).collect())
+    }
 
-                return Retryable;
+    /// The input consumed by every top level token if the input matches,
+    /// otherwise the furthest offset into the input that was reached and the token that failed there.
+    pub(crate) fn explain_match(&self, input: &str, options: &MatchOptions) -> MatchExplanation {
+        let captures = match self.trace(input, options) {
+            Ok(captures) => captures,
+            Err((offset, token)) => return MatchExplanation::Failed { token, offset },
+        };
+
+        let mut starts = vec![None; self.tokens.len()];
+        for (slot, start, _) in captures {
+            if let Slot::Token(i) = slot {
+                starts[i] = Some(start);
             }
-            Token::ExactlyOne(patterns) => {
-                let mut matches = 0;
+        }
 
-                for t in patterns {
-                    let mut t = t.clone();
-                    t.extend_from_slice(&tokens[i + ti + 1..]);
+        // tokens that were skipped, like the separator after a ** matching nothing, consumed nothing
+        let mut spans = vec![0..0; self.tokens.len()];
+        let mut end = input.len();
+        for (i, start) in starts.into_iter().enumerate().rev() {
+            let start = start.unwrap_or(end);
+            spans[i] = start..end;
+            end = start;
+        }
+        MatchExplanation::Matched(spans)
+    }
 
-                    match match_index(&mut t, 0, input.clone()) {
-                        Match => matches += 1,
-                        _ => {}
-                    }
+    fn trace<'a>(&'a self, input: &str, options: &'a MatchOptions) -> Result<Vec<Capture<'a>>, Failure> {
+        let mut ctx = Context {
+            options,
+            is_separator: self.is_separator,
+            root: &self.tokens,
+            len: input.len(),
+            text: None,
+            // giving up early would leave out how far the input got
+            min_lens: &[],
+            clusters: &[],
+            greedy: !options.lazy,
+            captures: Some(Vec::new()),
+            failure: None,
+            expired: None,
+            calls: 0,
+            aborted: false,
+            partial: false,
+            metrics: None,
+            depth: 0,
+        };
+        let chars = Input { chars: input.chars(), offset: 0, last: None };
+        if match_start(&self.tokens, chars, &mut ctx) != Status::Match {
+            return Err(ctx.failure.unwrap_or((0, None)));
+        }
+        Ok(ctx.captures.unwrap_or_default())
+    }
+}
+
+// matches the whole input, or any suffix of it when the start isn't anchored
+fn match_start<'t, I>(tokens: &'t [Token], mut input: Input<I>, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    loop {
+        let status = match_index(tokens, 0, input.clone(), None, ctx);
+        if status == Match || !ctx.options.unanchored_start || input.next().is_none() {
+            return status;
+        }
+        ctx.finish_cluster(&mut input);
+    }
+}
+
+fn match_index<'t, I>(tokens: &'t [Token], i: usize, input: Input<I>, rest: Option<&Rest<'t, '_>>, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    if ctx.abort() {
+        return NoMatch;
+    }
+
+    // a char takes at least a byte, using up more input by backtracking leaves even less room
+    if rest.is_none() && matches!(ctx.min_lens.get(i), Some(&min) if ctx.len - input.offset < min) {
+        return NoMatch;
+    }
+
+    if let Some(metrics) = &mut ctx.metrics {
+        ctx.depth += 1;
+        metrics.max_depth = metrics.max_depth.max(ctx.depth);
+    }
+
+    // the starts of top level tokens are recorded before knowing whether they match
+    let mark = ctx.mark();
+    let status = match_from(tokens, i, input, rest, ctx);
+    if status != Match {
+        ctx.rewind(mark);
+    }
+
+    if let Some(metrics) = &mut ctx.metrics {
+        ctx.depth -= 1;
+        if status != Match {
+            metrics.backtracks += 1;
+        }
+    }
+    status
+}
+
+fn match_from<'t, I>(tokens: &'t [Token], i: usize, mut input: Input<I>, rest: Option<&Rest<'t, '_>>, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    for (ti, token) in tokens[i..].iter().enumerate() {
+        let offset = input.offset;
+        if rest.is_none() {
+            ctx.enter(i + ti, offset);
+        }
+        if let Some(metrics) = &mut ctx.metrics {
+            metrics.token_visits += 1;
+        }
 
-                    if matches > 1 {
+        match token {
+            Token::AnyRecursive | Token::AtLeastOneSegment | Token::AnySequence => {
+                return match_wildcard(tokens, i + ti, input, rest, ctx);
+            }
+            Token::Char(_) | Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_) => {
+                match match_char(token, &mut input, ctx) {
+                    Some(true) => {}
+                    Some(false) => {
+                        ctx.fail(offset, Some(top_level(i + ti, rest)));
                         return Retryable;
                     }
+                    None => return ctx.ran_out(offset, top_level(i + ti, rest)),
+                }
+            }
+            Token::ZeroOrOne(patterns) => {
+                let after = Rest { tokens, i: i + ti + 1, next: rest, name: None, repeat: None };
+                for (branch, t) in patterns.iter().enumerate() {
+                    if match_index(t, 0, input.clone(), Some(&after), ctx) == Match {
+                        ctx.capture_branch(token, branch, offset);
+                        return Match;
+                    }
                 }
 
-                if matches == 1 {
-                    return Match;
+                return match_index(tokens, i + ti + 1, input, rest, ctx);
+            }
+            Token::ZeroOrMore(_) | Token::OneOrMore(_) => {
+                return match_repeat(tokens, i + ti, input, rest, false, ctx);
+            }
+            Token::ExactlyOne(patterns) => {
+                let after = Rest { tokens, i: i + ti + 1, next: rest, name: None, repeat: None };
+                for (branch, t) in patterns.iter().enumerate() {
+                    if match_index(t, 0, input.clone(), Some(&after), ctx) == Match {
+                        ctx.capture_branch(token, branch, offset);
+                        return Match;
+                    }
                 }
 
                 return Retryable;
             }
+            Token::Named(name, pattern) => {
+                let after = Rest { tokens, i: i + ti + 1, next: rest, name: Some((name, input.offset)), repeat: None };
+                return match_index(core::slice::from_ref(&**pattern), 0, input, Some(&after), ctx);
+            }
             Token::NoneOf(patterns) => {
-                for t in patterns {
-                    let mut t = t.clone();
-                    t.extend_from_slice(&tokens[i + ti + 1..]);
+                // whether more input keeps it from matching the patterns isn't known yet
+                if ctx.partial {
+                    return Match;
+                }
 
-                    match match_index(&mut t, 0, input.clone()) {
-                        Match => return Retryable,
-                        _ => {}
+                let after = Rest { tokens, i: i + ti + 1, next: rest, name: None, repeat: None };
+                let mark = ctx.mark();
+                for t in patterns {
+                    if match_index(t, 0, input.clone(), Some(&after), ctx) == Match {
+                        ctx.rewind(mark);
+                        ctx.fail(offset, Some(top_level(i + ti, rest)));
+                        return Retryable;
                     }
                 }
 
-                while let Some(_) = input.next() {
-                    match match_index(tokens, i + ti + 1, input.clone()) {
+                let mut last = input.last;
+                while let Some(c) = input.next() {
+                    // it takes chars like a * does
+                    if ctx.options.is_hidden(c, last.replace(c), ctx.is_separator) {
+                        ctx.fail(offset, Some(top_level(i + ti, rest)));
+                        return Retryable;
+                    }
+                    if ctx.in_cluster(input.offset) {
+                        continue;
+                    }
+                    match match_index(tokens, i + ti + 1, input.clone(), rest, ctx) {
                         Status::Retryable => {}
                         m => return m,
                     }
@@ -187,26 +796,414 @@ fn match_index(tokens: &Vec<Token>, i: usize, mut input: Chars) -> Status {
         }
     }
 
+    // the pattern inside a group matched, carry on with whatever follows the group
+    if let Some(rest) = rest {
+        let end = input.offset;
+        let status = match rest.repeat {
+            // repeating what matched nothing would never end
+            Some(start) if end > start => match_repeat(rest.tokens, rest.i - 1, input, rest.next, true, ctx),
+            _ => match_index(rest.tokens, rest.i, input, rest.next, ctx),
+        };
+        if let (Some((name, start)), Match) = (rest.name, status) {
+            ctx.capture_named(name, start, end);
+        }
+        return status;
+    }
+
+    let offset = input.offset;
     match input.next() {
-        Some(_) => { Status::Retryable }
+        Some(_) => {
+            ctx.fail(offset, None);
+            Status::Retryable
+        }
         None => { Status::Match }
     }
 }
 
-fn match_specifiers(specifiers: &Vec<CharSpecifier>, c: char) -> Status {
-    for specifier in specifiers {
-        match specifier {
-            CharSpecifier::Char(c1) => {
-                if c == *c1 {
-                    return Match;
-                }
+// the index of the top level token that tokens[i] is part of,
+// which is the group the outermost rest continues after
+fn top_level(i: usize, rest: Option<&Rest>) -> usize {
+    match core::iter::successors(rest, |r| r.next).last() {
+        Some(r) => r.i - 1,
+        None => i,
+    }
+}
+
+// takes the next char for a token that matches exactly one, None when the input ran out
+fn match_char<I>(token: &Token, input: &mut Input<I>, ctx: &Context) -> Option<bool>
+    where I: Iterator<Item=char> + Clone,
+{
+    let last = input.last;
+    let next = input.next()?;
+    let matched = match token {
+        Token::Char(c) => return Some(ctx.options.char_eq(*c, next)),
+        // like * a ? can't match a separator or a leading dot that has to be written out
+        Token::AnyChar => !ctx.options.is_boundary(next, ctx.is_separator) && !ctx.options.is_hidden(next, last, ctx.is_separator),
+        // a range looks at the first char of a grapheme cluster
+        Token::AnyOf(specifiers) => !ctx.options.is_hidden(next, last, ctx.is_separator)
+            && match_specifiers_with(specifiers, next, ctx.options) == Match,
+        Token::NotAnyOf(specifiers) => !ctx.options.is_hidden(next, last, ctx.is_separator)
+            && match_specifiers_with(specifiers, next, ctx.options) == Retryable,
+        _ => unreachable!(),
+    };
+    if matched {
+        ctx.finish_cluster(input);
+    }
+    Some(matched)
+}
+
+// matches the *(...) or +(...) at tokens[i] as many times as it can before everything after it, backtracking
+// to fewer times when the rest doesn't match, or the other way around when lazy.
+// Once it matched, a +(...) can also match no more times.
+fn match_repeat<'t, I>(tokens: &'t [Token], i: usize, input: Input<I>, rest: Option<&Rest<'t, '_>>, repeated: bool, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    let token = &tokens[i];
+    let (patterns, optional) = match token {
+        Token::ZeroOrMore(patterns) => (patterns, true),
+        Token::OneOrMore(patterns) => (patterns, repeated),
+        _ => unreachable!(),
+    };
+
+    if !repeated && patterns.iter().all(|t| matches!(t[..], [Token::Char(_) | Token::AnyChar | Token::AnyOf(_) | Token::NotAnyOf(_)])) {
+        return match_repeat_chars(tokens, i, input, rest, ctx);
+    }
+
+    if optional && !ctx.greedy && match_index(tokens, i + 1, input.clone(), rest, ctx) == Match {
+        return Match;
+    }
+
+    let offset = input.offset;
+    let after = Rest { tokens, i: i + 1, next: rest, name: None, repeat: Some(offset) };
+    for (branch, t) in patterns.iter().enumerate() {
+        if match_index(t, 0, input.clone(), Some(&after), ctx) == Match {
+            // the alternative the first repetition took
+            if !repeated {
+                ctx.capture_branch(token, branch, offset);
             }
-            CharSpecifier::Range(start, end) => {
-                if c >= *start && c <= *end {
-                    return Match;
+            return Match;
+        }
+    }
+
+    if optional && ctx.greedy {
+        return match_index(tokens, i + 1, input, rest, ctx);
+    }
+    ctx.fail(offset, Some(top_level(i, rest)));
+    Retryable
+}
+
+// the same for a group of alternatives that each match a single char, which repeats without
+// going a call deeper for every char by collecting where each repetition ends up first
+fn match_repeat_chars<'t, I>(tokens: &'t [Token], i: usize, input: Input<I>, rest: Option<&Rest<'t, '_>>, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    let token = &tokens[i];
+    let (patterns, min) = match token {
+        Token::ZeroOrMore(patterns) => (patterns, 0),
+        Token::OneOrMore(patterns) => (patterns, 1),
+        _ => unreachable!(),
+    };
+
+    let start = input.offset;
+    let mut ends = vec![input];
+    let mut first = None;
+    loop {
+        let current = &ends[ends.len() - 1];
+        let offset = current.offset;
+        let mut next = None;
+        for (branch, t) in patterns.iter().enumerate() {
+            let mut input = current.clone();
+            match match_char(&t[0], &mut input, ctx) {
+                Some(true) => {
+                    first = first.or(Some(branch));
+                    next = Some(input);
+                    break;
                 }
+                Some(false) => ctx.fail(offset, Some(top_level(i, rest))),
+                None if ends.len() > min && !ctx.partial => {}
+                // more input could repeat it again
+                None => return ctx.ran_out(offset, top_level(i, rest)),
+            }
+        }
+        match next {
+            Some(input) => ends.push(input),
+            None => break,
+        }
+    }
+
+    // backtracking to fewer repetitions leaves more input for the rest, so only a match ends it
+    let mut counts = min..ends.len();
+    while let Some(n) = if ctx.greedy { counts.next_back() } else { counts.next() } {
+        if match_index(tokens, i + 1, ends[n].clone(), rest, ctx) == Match {
+            if let (Some(branch), true) = (first, n > 0) {
+                ctx.capture_branch(token, branch, start);
             }
+            return Match;
         }
     }
     Retryable
+}
+
+// matches the *, ** or **+ at tokens[i] followed by everything after it
+fn match_wildcard<'t, I>(tokens: &'t [Token], i: usize, mut input: Input<I>, rest: Option<&Rest<'t, '_>>, ctx: &mut Context<'t>) -> Status
+    where I: Iterator<Item=char> + Clone,
+{
+    let token = &tokens[i];
+    let start = input.offset;
+
+    // a * that can't cross separators or newlines has to stop at the next one, a ** stops
+    // before entering more path components than allowed, counted in components
+    let stops_at = |c: char, last: Option<char>, components: &mut usize, ctx: &Context| {
+        if ctx.options.is_hidden(c, last, ctx.is_separator) {
+            return true;
+        }
+        let is_separator = (ctx.is_separator)(c);
+        if *token == Token::AnySequence {
+            return ctx.options.is_boundary(c, ctx.is_separator);
+        }
+
+        if *components == 0 || is_separator {
+            *components += 1;
+        }
+        matches!(ctx.options.max_depth, Some(max) if *components > max)
+    };
+
+    // a wildcard can't take the rest of a grapheme cluster that a char in the pattern started,
+    // it can only match nothing there
+    let trailing = i + 1 == tokens.len() && rest.is_none();
+    if ctx.in_cluster(start) && (trailing || *token == Token::AtLeastOneSegment) {
+        ctx.fail(start, Some(top_level(i, rest)));
+        return Retryable;
+    }
+
+    // a **+ takes its first char before going on like a **, so it never matches nothing
+    let mut entered = 0;
+    if *token == Token::AtLeastOneSegment {
+        let last = input.last;
+        match input.next() {
+            None => return ctx.ran_out(start, top_level(i, rest)),
+            Some(c) if stops_at(c, last, &mut entered, ctx) => {
+                ctx.fail(start, Some(top_level(i, rest)));
+                return Retryable;
+            }
+            Some(_) => {}
+        }
+    }
+
+    // nothing left to match after a trailing wildcard, it takes the rest of the input
+    if trailing {
+        let mut components = entered;
+        let mut last = input.last;
+        if input.clone().any(|c| stops_at(c, last.replace(c), &mut components, ctx)) {
+            ctx.fail(start, Some(top_level(i, rest)));
+            return Retryable;
+        }
+        ctx.capture(token, start, ctx.len);
+        return Match;
+    }
+
+    // ** can also match no segments at all by skipping the separators that follow it
+    let separators = match token {
+        Token::AnyRecursive => following(tokens, i + 1, rest)
+            .take_while(|t| matches!(t, Token::Char(c) if (ctx.is_separator)(*c)))
+            .count(),
+        _ => 0,
+    };
+    let skip = |input: Input<I>, ctx: &mut Context<'t>| {
+        let end = input.offset;
+        let (skipped, i, after, crossed) = advance(tokens, i + 1, rest, separators);
+        let status = match_index(skipped, i, input, after, ctx);
+
+        // named patterns the skipped separators stepped out of end here
+        if status == Match {
+            for r in core::iter::successors(rest, |r| r.next).take(crossed) {
+                if let Some((name, start)) = r.name {
+                    ctx.capture_named(name, start, end);
+                }
+            }
+        }
+        status
+    };
+
+    let attempt = |input: Input<I>, ctx: &mut Context<'t>| {
+        let end = input.offset;
+        let status = match_index(tokens, i + 1, input, rest, ctx);
+        if status == Match {
+            ctx.capture(token, start, end);
+        }
+        status
+    };
+
+    if ctx.in_cluster(start) {
+        return attempt(input, ctx);
+    }
+
+    if ctx.greedy {
+        let mut ends = Vec::new();
+        let mut end = input.clone();
+        let mut components = entered;
+        loop {
+            if !ctx.in_cluster(end.offset) {
+                ends.push(end.clone());
+            }
+            let last = end.last;
+            match end.next() {
+                Some(c) if !stops_at(c, last, &mut components, ctx) => {}
+                _ => break,
+            }
+        }
+
+        // the longest match first, a shorter one can still succeed after running out of input
+        let mut status = Retryable;
+        for end in ends.into_iter().rev() {
+            status = attempt(end, ctx);
+            if status == Match {
+                return Match;
+            }
+        }
+        if separators > 0 && skip(input, ctx) == Match {
+            ctx.capture(token, start, start);
+            return Match;
+        }
+        return status;
+    }
+
+    let first = attempt(input.clone(), ctx);
+    if first == Match {
+        return Match;
+    }
+
+    // skipping the separators needs less input, so it can still match when the attempt above ran out
+    if separators > 0 {
+        match skip(input.clone(), ctx) {
+            Status::Retryable => {}
+            m => {
+                if m == Match {
+                    ctx.capture(token, start, start);
+                }
+                return m;
+            }
+        }
+    }
+
+    if first == NoMatch {
+        return NoMatch;
+    }
+
+    // an attempt where the chars following the wildcard don't start can only fail on them, so when there are
+    // enough of them only the places they are found at are attempted
+    let mut buf = [0; 64];
+    let literal = match ctx.text {
+        Some(_) if ctx.options.exact_chars() && !ctx.partial && ctx.captures.is_none() => literal_after(tokens, i, rest, &mut buf),
+        _ => "",
+    };
+    let mut next = Some(input.offset);
+    // nothing can stop the wildcard before them, so it can go there right away
+    let free = !ctx.options.require_literal_leading_dot && ctx.clusters.is_empty() && match token {
+        Token::AnySequence => !ctx.options.require_literal_separator && !ctx.options.newline_is_boundary,
+        _ => ctx.options.max_depth.is_none(),
+    };
+
+    let mut status = Retryable;
+    let mut components = entered;
+    let mut last = input.last;
+    while let Some(c) = input.next() {
+        if stops_at(c, last.replace(c), &mut components, ctx) {
+            break;
+        }
+        if ctx.in_cluster(input.offset) {
+            continue;
+        }
+        if let (Some(text), true) = (ctx.text, literal.len() >= MIN_SCAN_LEN) {
+            if matches!(next, Some(at) if at < input.offset) {
+                next = find(&text[input.offset..], literal).map(|at| input.offset + at);
+            }
+            match next {
+                None => break,
+                Some(at) if at > input.offset && free => {
+                    while input.offset < at && input.next().is_some() {}
+                    last = input.last;
+                }
+                Some(at) if at > input.offset => continue,
+                Some(_) => {}
+            }
+        }
+        status = attempt(input.clone(), ctx);
+        if status != Retryable {
+            return status;
+        }
+    }
+    status
+}
+
+// every token that is matched after tokens[i], including the ones following the enclosing groups
+fn following<'t: 'r, 'r>(tokens: &'t [Token], i: usize, rest: Option<&'r Rest<'t, 'r>>) -> impl Iterator<Item=&'t Token> + 'r {
+    let rests = core::iter::successors(rest, |r| r.next);
+    tokens[i..].iter().chain(rests.flat_map(|r| r.tokens[r.i..].iter()))
+}
+
+// the fewest bytes of chars following a wildcard worth searching the input for
+const MIN_SCAN_LEN: usize = 3;
+
+// the chars following tokens[i] as a string, only as many as fit into buf
+fn literal_after<'b>(tokens: &[Token], i: usize, rest: Option<&Rest>, buf: &'b mut [u8]) -> &'b str {
+    let mut len = 0;
+    for token in following(tokens, i + 1, rest) {
+        match token {
+            Token::Char(c) if len + c.len_utf8() <= buf.len() => len += c.encode_utf8(&mut buf[len..]).len(),
+            _ => break,
+        }
+    }
+    core::str::from_utf8(&buf[..len]).unwrap_or_default()
+}
+
+// the byte offset the needle first starts at
+fn find(haystack: &str, needle: &str) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    return memchr::memmem::find(haystack.as_bytes(), needle.as_bytes());
+    #[cfg(not(feature = "memchr"))]
+    return haystack.find(needle);
+}
+
+// moves n tokens past tokens[i], stepping out of groups when their tokens run out,
+// also returning how many groups were stepped out of
+fn advance<'t, 'r>(mut tokens: &'t [Token], mut i: usize, mut rest: Option<&'r Rest<'t, 'r>>, mut n: usize)
+    -> (&'t [Token], usize, Option<&'r Rest<'t, 'r>>, usize)
+{
+    let mut crossed = 0;
+    while i + n > tokens.len() {
+        let r = match rest {
+            Some(r) => r,
+            None => break,
+        };
+        n -= tokens.len() - i;
+        tokens = r.tokens;
+        i = r.i;
+        rest = r.next;
+        crossed += 1;
+    }
+    (tokens, i + n, rest, crossed)
+}
+
+pub(crate) fn match_specifiers_with(specifiers: &[CharSpecifier], c: char, options: &MatchOptions) -> Status {
+    match match_specifiers(specifiers, c) {
+        // only letters are folded, e.g. [A-_] must not match {
+        Retryable if options.case_insensitive && c.is_ascii_alphabetic() => {
+            let folded = if c.is_ascii_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() };
+            match_specifiers(specifiers, folded)
+        }
+        status => status,
+    }
+}
+
+// specifiers are sorted and never overlap, see `normalize_specifiers`
+pub(crate) fn match_specifiers(specifiers: &[CharSpecifier], c: char) -> Status {
+    let i = specifiers.partition_point(|s| match *s {
+        CharSpecifier::Char(start) | CharSpecifier::Range(start, _) => start <= c,
+    });
+
+    match i.checked_sub(1).map(|i| &specifiers[i]) {
+        Some(s) if s.matches(c) => Match,
+        _ => Retryable,
+    }
 }
\ No newline at end of file
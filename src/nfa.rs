@@ -0,0 +1,156 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::swap;
+
+use crate::matcher::{match_specifiers_with, Status};
+use crate::options::MatchOptions;
+use crate::syntax::Token;
+
+/// Simulates every possible position in the tokens at once instead of backtracking,
+/// every token is a state and being past the last token means a match.
+/// Only works for globs without patterns since those don't form a flat sequence,
+/// and not for ** limited by max_depth since a state can't count how deep it went.
+#[derive(Clone, Debug)]
+pub(crate) struct Nfa<'a> {
+    tokens: &'a [Token],
+    is_separator: fn(char) -> bool,
+    options: MatchOptions,
+}
+
+impl<'a> Nfa<'a> {
+    pub(crate) fn new(tokens: &'a [Token], is_separator: fn(char) -> bool, options: MatchOptions) -> Option<Self> {
+        // a leading dot depends on the char before it, which the states don't keep
+        if options.require_literal_leading_dot
+            || tokens.iter().any(|t| !is_flat(t) || (options.max_depth.is_some() && *t == Token::AnyRecursive)) {
+            return None;
+        }
+
+        Some(Self {
+            tokens,
+            is_separator,
+            options,
+        })
+    }
+
+    pub(crate) fn start(&self) -> Vec<bool> {
+        let mut states = vec![false; self.tokens.len() + 1];
+        self.add(&mut states, 0);
+        states
+    }
+
+    pub(crate) fn step(&self, states: &[bool], next: &mut Vec<bool>, c: char) {
+        next.clear();
+        next.resize(states.len(), false);
+
+        for (i, _) in states.iter().enumerate().filter(|(_, active)| **active) {
+            let to = match self.tokens.get(i) {
+                Some(Token::AnySequence) if self.options.is_boundary(c, self.is_separator) => None,
+                Some(Token::AnySequence) | Some(Token::AnyRecursive) => {
+                    self.add_looped(next, i);
+                    None
+                }
+                Some(Token::AnyChar) if !self.options.is_boundary(c, self.is_separator) => Some(i + 1),
+                Some(Token::Char(t)) if self.options.char_eq(*t, c) => Some(i + 1),
+                Some(Token::AnyOf(s)) if match_specifiers_with(s, c, &self.options) == Status::Match => Some(i + 1),
+                Some(Token::NotAnyOf(s)) if match_specifiers_with(s, c, &self.options) != Status::Match => Some(i + 1),
+                _ => None,
+            };
+
+            if let Some(to) = to {
+                self.add(next, to);
+            }
+        }
+
+        // a match can start after every char
+        if self.options.unanchored_start {
+            self.add(next, 0);
+        }
+    }
+
+    pub(crate) fn is_match(&self, states: &[bool]) -> bool {
+        states[self.tokens.len()]
+    }
+
+    pub(crate) fn matches<I: Iterator<Item=char>>(&self, input: I) -> bool {
+        match self.run(input) {
+            Some(states) => self.is_match(&states),
+            None => false,
+        }
+    }
+
+    /// True if more input could still make it match, every active state can be satisfied by some char.
+    pub(crate) fn matches_prefix<I: Iterator<Item=char>>(&self, input: I) -> bool {
+        self.run(input).is_some()
+    }
+
+    /// The byte length of the longest start of the input that matches.
+    pub(crate) fn longest_match(&self, input: &str) -> Option<usize> {
+        let mut states = self.start();
+        let mut next = Vec::with_capacity(states.len());
+        let mut longest = if self.is_match(&states) { Some(0) } else { None };
+        for (i, c) in input.char_indices() {
+            self.step(&states, &mut next, c);
+            swap(&mut states, &mut next);
+            if self.is_match(&states) {
+                longest = Some(i + c.len_utf8());
+            } else if !states.contains(&true) {
+                break;
+            }
+        }
+        longest
+    }
+
+    // the states after all the input, None once none are left
+    fn run<I: Iterator<Item=char>>(&self, input: I) -> Option<Vec<bool>> {
+        self.resume(self.start(), input)
+    }
+
+    /// Goes on from states an earlier part of the input left, None once none are left.
+    pub(crate) fn resume<I: Iterator<Item=char>>(&self, mut states: Vec<bool>, input: I) -> Option<Vec<bool>> {
+        let mut next = Vec::with_capacity(states.len());
+        for c in input {
+            self.step(&states, &mut next, c);
+            swap(&mut states, &mut next);
+            if !states.contains(&true) {
+                return None;
+            }
+        }
+        Some(states)
+    }
+
+    // activates a state and everything reachable from it without consuming a char
+    fn add(&self, states: &mut [bool], i: usize) {
+        self.add_looped(states, i);
+
+        // ** can match no segments at all by skipping the separator that follows it,
+        // but only when it is entered and not after it already took some chars
+        if let Some(Token::AnyRecursive) = self.tokens.get(i) {
+            let separators = self.tokens[i + 1..].iter()
+                .take_while(|t| matches!(t, Token::Char(c) if (self.is_separator)(*c)))
+                .count();
+            if separators > 0 {
+                self.add(states, i + 1 + separators);
+            }
+        }
+    }
+
+    fn add_looped(&self, states: &mut [bool], i: usize) {
+        if states[i] {
+            return;
+        }
+        states[i] = true;
+
+        if let Some(Token::AnySequence) | Some(Token::AnyRecursive) = self.tokens.get(i) {
+            self.add(states, i + 1);
+        }
+    }
+}
+
+fn is_flat(token: &Token) -> bool {
+    match token {
+        Token::AnyChar | Token::AnySequence | Token::AnyRecursive | Token::AnyOf(_) | Token::NotAnyOf(_) | Token::Char(_) => true,
+        // **+ would need a second state to count its first char
+        Token::AtLeastOneSegment | Token::ZeroOrOne(_) | Token::ZeroOrMore(_) | Token::OneOrMore(_) | Token::ExactlyOne(_)
+        | Token::NoneOf(_) | Token::Named(..) => false,
+    }
+}
@@ -0,0 +1,93 @@
+/// Options changing how a [`Pattern`](crate::Pattern) matches its input.
+/// ```rust
+/// # use globber::{MatchOptions, Pattern};
+/// let options = MatchOptions {
+///     normalize_separators: true,
+///     ..MatchOptions::new()
+/// };
+/// let pattern = Pattern::new("src/*.rs").unwrap();
+/// assert!(pattern.matches_with("src\\main.rs", options));
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default)]
+pub struct MatchOptions {
+    /// Treat `/` and `\` as the same separator, a `/` in the pattern matches a `\` in the input and vice versa.
+    ///
+    /// Defaults to `false`.
+    pub normalize_separators: bool,
+    /// Ignore the case of ASCII letters, both for chars and ranges. Other chars still have to match exactly
+    /// which avoids pulling in Unicode case tables, e.g. `*.RS` matches `main.rs` but `É` never matches `é`.
+    ///
+    /// Defaults to `false`.
+    pub case_insensitive: bool,
+    /// Only let `**` and separators written in the pattern match separators, so `*` stops at the end of a path component
+    /// and `?` doesn't match one, e.g. `src/*.rs` matches `src/main.rs` but not `src/bin/main.rs`.
+    ///
+    /// Defaults to `false`.
+    pub require_literal_separator: bool,
+    /// Only let a `.` written in the pattern match a `.` starting a path component, so `*`, `?`, ranges and `**`
+    /// skip hidden files and directories, e.g. `*` doesn't match `.bashrc` and `**/*.rs` doesn't match `.git/x.rs`
+    /// while `.*` matches `.bashrc`.
+    ///
+    /// Defaults to `false`.
+    pub require_literal_leading_dot: bool,
+    /// The most path components a `**` may match, e.g. with `Some(1)` the pattern `a/**/b` matches `a/x/b`
+    /// but not `a/x/y/b`. Matching no components at all, like `a/b`, is always allowed.
+    ///
+    /// Defaults to `None`, no limit.
+    pub max_depth: Option<usize>,
+    /// Let the pattern match the end of the input instead of all of it, as if it started with a `*`
+    /// that can also cross separators, e.g. `foo.txt` matches `a/foo.txt` and `afoo.txt` but not `foo.txt.bak`.
+    ///
+    /// Defaults to `false`.
+    pub unanchored_start: bool,
+    /// Don't let `*` and `?` match a newline while `**` still can, e.g. to match lines of a text joined by `\n`,
+    /// `*.rs` then doesn't match `a\nb.rs` but `**/*.rs` matches `a\nsrc/b.rs`.
+    ///
+    /// Defaults to `false`.
+    pub newline_is_boundary: bool,
+    /// Let `*` and `**` take as few chars as possible when capturing instead of as many as possible,
+    /// see [`Pattern::captures`](crate::Pattern::captures). This never changes whether the input matches.
+    ///
+    /// Defaults to `false`.
+    pub lazy: bool,
+}
+
+impl MatchOptions {
+    /// Creates options with the defaults used by [`Pattern::matches`](crate::Pattern::matches).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True when a char in the pattern only ever matches the very same char in the input.
+    pub(crate) fn exact_chars(&self) -> bool {
+        !self.normalize_separators && !self.case_insensitive
+    }
+
+    /// True when the char is a `.` only the pattern spelling it out can match, `last` is the char before it.
+    pub(crate) fn is_hidden(&self, c: char, last: Option<char>, is_separator: fn(char) -> bool) -> bool {
+        self.require_literal_leading_dot && c == '.' && !matches!(last, Some(last) if !is_separator(last))
+    }
+
+    /// True when `*` and `?` can't match the char.
+    pub(crate) fn is_boundary(&self, c: char, is_separator: fn(char) -> bool) -> bool {
+        (self.require_literal_separator && is_separator(c)) || (self.newline_is_boundary && c == '\n')
+    }
+
+    pub(crate) fn char_eq(&self, pattern: char, input: char) -> bool {
+        pattern == input
+            || (self.case_insensitive && pattern.eq_ignore_ascii_case(&input))
+            || (self.normalize_separators && is_any_separator(pattern) && is_any_separator(input))
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) use std::path::is_separator;
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+pub(crate) fn is_any_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
@@ -0,0 +1,164 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::matcher::Matcher;
+use crate::nfa::Nfa;
+use crate::options::MatchOptions;
+use crate::syntax::{CharSpecifier, Token};
+
+// whether some input matches both, exact when both can be simulated and conservative otherwise
+pub(crate) fn overlaps(a: &Matcher, b: &Matcher) -> bool {
+    if !may_overlap(a, b) {
+        return false;
+    }
+
+    let options = MatchOptions::new();
+    match (Nfa::new(a.tokens(), a.is_separator(), options), Nfa::new(b.tokens(), b.is_separator(), options)) {
+        (Some(a_nfa), Some(b_nfa)) => intersects(&a_nfa, &b_nfa, &alphabet(&[a.tokens(), b.tokens()])),
+        _ => true,
+    }
+}
+
+// rules out patterns whose fixed start, end or length can't agree
+fn may_overlap(a: &Matcher, b: &Matcher) -> bool {
+    let (a_min, a_max) = a.len_bounds();
+    let (b_min, b_max) = b.len_bounds();
+    let shorter = |max: Option<usize>, min| matches!(max, Some(max) if max < min);
+    if shorter(a_max, b_min) || shorter(b_max, a_min) {
+        return false;
+    }
+
+    let prefix = |m: &Matcher| -> Vec<char> {
+        m.tokens().iter()
+            .map_while(|t| match t {
+                Token::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    };
+    let (a_prefix, b_prefix) = (prefix(a), prefix(b));
+    if a_prefix.iter().zip(&b_prefix).any(|(a, b)| a != b) {
+        return false;
+    }
+
+    let (a_suffix, b_suffix) = (a.suffix(), b.suffix());
+    a_suffix.ends_with(b_suffix) || b_suffix.ends_with(a_suffix)
+}
+
+// searches every pair of states both patterns can be in after the same input for one where both match
+fn intersects(a: &Nfa, b: &Nfa, alphabet: &[char]) -> bool {
+    let start = (a.start(), b.start());
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![start.clone()];
+    seen.insert(start);
+
+    let (mut a_next, mut b_next) = (Vec::new(), Vec::new());
+    while let Some((a_states, b_states)) = pending.pop() {
+        if a.is_match(&a_states) && b.is_match(&b_states) {
+            return true;
+        }
+
+        for &c in alphabet {
+            a.step(&a_states, &mut a_next, c);
+            b.step(&b_states, &mut b_next, c);
+            if !a_next.contains(&true) || !b_next.contains(&true) {
+                continue;
+            }
+
+            let next = (a_next.clone(), b_next.clone());
+            if seen.insert(next.clone()) {
+                pending.push(next);
+            }
+        }
+    }
+    false
+}
+
+// one char out of every run of chars that all tokens treat alike, along with all of ascii for the separators
+fn alphabet(patterns: &[&[Token]]) -> Vec<char> {
+    let mut bounds: BTreeSet<u32> = (0..=128).collect();
+    let mut add = |start: char, end: char| {
+        bounds.insert(start as u32);
+        bounds.insert(end as u32 + 1);
+    };
+    for tokens in patterns {
+        for token in tokens.iter() {
+            match token {
+                Token::Char(c) => add(*c, *c),
+                Token::AnyOf(specifiers) | Token::NotAnyOf(specifiers) => {
+                    for specifier in specifiers {
+                        match *specifier {
+                            CharSpecifier::Char(c) => add(c, c),
+                            CharSpecifier::Range(start, end) => add(start, end),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // the run starting in the surrogates is represented by the first char after them
+    bounds.into_iter()
+        .filter_map(|b| core::char::from_u32(b).or_else(|| if (0xD800..0xE000).contains(&b) { Some('\u{E000}') } else { None }))
+        .collect::<BTreeSet<char>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Pattern, PatternBuilder};
+
+    fn overlaps(a: &str, b: &str) -> bool {
+        let (a, b) = (Pattern::new(a).unwrap(), Pattern::new(b).unwrap());
+        assert_eq!(a.overlaps(&b), b.overlaps(&a));
+        a.overlaps(&b)
+    }
+
+    #[test]
+    fn exact() {
+        assert!(overlaps("*.rs", "main.*"));
+        assert!(overlaps("*.rs", "*"));
+        assert!(overlaps("src/**/*.rs", "**/bin/*"));
+        assert!(overlaps("src/**/*.rs", "src/*.rs"));
+        assert!(overlaps("a*b*c", "*bbb*"));
+        assert!(overlaps("[a-m]?", "[!a-f]x"));
+        assert!(overlaps("**/x", "x"));
+        assert!(overlaps("", ""));
+        assert!(overlaps("é*", "?🦀"));
+
+        assert!(!overlaps("*.rs", "*.toml"));
+        assert!(!overlaps("src/*", "lib/*"));
+        assert!(!overlaps("a?", "a"));
+        assert!(!overlaps("[a-m]", "[n-z]"));
+        assert!(!overlaps("[!a-z]", "[a-z]"));
+        assert!(!overlaps("*a", "*b*c"));
+        assert!(!overlaps("a*b*c", "*x"));
+        assert!(!overlaps("[2-1]", "*"));
+        assert!(!overlaps("x/**/a", "x/**/b"));
+        assert!(!overlaps("", "?"));
+    }
+
+    #[test]
+    fn separators() {
+        let colons = PatternBuilder::new("a::**::b").separators(|c| c == ':').build().unwrap();
+        assert!(colons.overlaps(&Pattern::new("a::b").unwrap()));
+        assert!(colons.overlaps(&Pattern::new("a:*:x::b").unwrap()));
+        assert!(!colons.overlaps(&Pattern::new("a::c").unwrap()));
+    }
+
+    #[test]
+    fn conservative() {
+        assert!(overlaps("@(*.rs|*.c)", "main.*"));
+        assert!(overlaps("!(*.rs)", "*.toml"));
+        // can't tell without simulating the patterns
+        assert!(overlaps("*.@(rs|c)", "*.toml"));
+
+        // but the start, end and length of a pattern are always known
+        assert!(!overlaps("src/@(a|b)", "lib/*"));
+        assert!(!overlaps("@(a|b).rs", "*.toml"));
+        assert!(!overlaps("@(a|b)", "???"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use crate::{Error, Pattern};
+use crate::analysis::stats;
+use crate::syntax::Token;
+
+/// A set of patterns matched against the same input at once.
+///
+/// Literal patterns are looked up directly instead of being matched one at a time.
+/// ```rust
+/// # use globber::GlobSet;
+/// let set = GlobSet::from_lines("*.rs\nCargo.toml".lines()).unwrap();
+/// assert!(set.is_match("src/lib.rs"));
+/// assert!(set.is_match("Cargo.toml"));
+/// assert!(!set.is_match("Cargo.lock"));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GlobSet {
+    patterns: Vec<Pattern>,
+    literals: BTreeMap<String, Vec<usize>>,
+    globs: Vec<usize>,
+}
+
+impl GlobSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses every line as a pattern, on failure the error reports the line that failed starting at 1.
+    pub fn from_lines<I, S>(lines: I) -> Result<Self, Error>
+        where I: IntoIterator<Item=S>,
+              S: AsRef<str>,
+    {
+        let mut set = Self::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            let pattern = Pattern::new(line.as_ref())
+                .map_err(|mut e| {
+                    e.line = Some(i + 1);
+                    e
+                })?;
+            set.insert(pattern);
+        }
+        Ok(set)
+    }
+
+    /// Adds a pattern to the set, returning its index.
+    pub fn insert(&mut self, pattern: Pattern) -> usize {
+        let index = self.patterns.len();
+        match pattern.matcher.literal() {
+            Some(literal) => self.literals.entry(literal.into()).or_default().push(index),
+            None => self.globs.push(index),
+        }
+        self.patterns.push(pattern);
+        index
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Pattern> {
+        self.patterns.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns true if any pattern in the set matches.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.literals.contains_key(input) || self.globs.iter().any(|&i| self.patterns[i].matches(input))
+    }
+
+    /// Returns the indices of every pattern that matches in ascending order.
+    pub fn matches(&self, input: &str) -> Vec<usize> {
+        let mut matches: Vec<usize> = self.globs.iter()
+            .copied()
+            .filter(|&i| self.patterns[i].matches(input))
+            .collect();
+        if let Some(literals) = self.literals.get(input) {
+            matches.extend_from_slice(literals);
+            matches.sort_unstable();
+        }
+        matches
+    }
+
+    /// Returns the index of the most specific pattern that matches, e.g. to let a rule for `src/bin/*.rs`
+    /// override one for `*.rs`.
+    ///
+    /// The pattern spelling out the most chars wins, counting the chars outside of patterns like `@(...)`.
+    /// Between those the one with the fewest `*` and `**` wins, anywhere in the pattern, and after that the one
+    /// inserted first. A literal pattern matching the input spells out all of it, so nothing beats it.
+    /// ```rust
+    /// # use globber::GlobSet;
+    /// let set = GlobSet::from_lines(["*.rs", "src/**/*.rs", "src/bin/*.rs"].iter()).unwrap();
+    /// assert_eq!(set.longest_match("src/bin/tool.rs"), Some(2));
+    /// assert_eq!(set.longest_match("src/lib.rs"), Some(1));
+    /// assert_eq!(set.longest_match("build.rs"), Some(0));
+    /// assert_eq!(set.longest_match("Cargo.toml"), None);
+    /// ```
+    pub fn longest_match(&self, input: &str) -> Option<usize> {
+        let specificity = |i: usize| {
+            let tokens = self.patterns[i].matcher.tokens();
+            let chars = tokens.iter().filter(|t| matches!(t, Token::Char(_))).count();
+            (chars, core::cmp::Reverse(stats(tokens).wildcards))
+        };
+
+        // the first of the most specific, max_by_key would return the last
+        self.matches(input).into_iter()
+            .map(|i| (i, specificity(i)))
+            .fold(None, |best: Option<(usize, _)>, (i, s)| match best {
+                Some((_, b)) if b >= s => best,
+                _ => Some((i, s)),
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+impl FromIterator<Pattern> for GlobSet {
+    fn from_iter<T: IntoIterator<Item=Pattern>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Pattern> for GlobSet {
+    fn extend<T: IntoIterator<Item=Pattern>>(&mut self, iter: T) {
+        for pattern in iter {
+            self.insert(pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GlobSet;
+    use crate::Pattern;
+    use crate::syntax::Error;
+
+    #[test]
+    fn from_lines() {
+        let set = GlobSet::from_lines(["*.rs", "Cargo.toml", "src/**/mod.rs", "README.md"].iter()).unwrap();
+        assert_eq!(set.len(), 4);
+        assert!(set.is_match("main.rs"));
+        assert!(set.is_match("Cargo.toml"));
+        assert!(set.is_match("README.md"));
+        assert!(!set.is_match("README"));
+        assert_eq!(set.matches("src/a/mod.rs"), vec![0, 2]);
+        assert_eq!(set.matches("Cargo.toml"), vec![1]);
+        assert_eq!(set.matches("Cargo.lock"), Vec::<usize>::new());
+        assert_eq!(set.get(3), Some(&Pattern::new("README.md").unwrap()));
+    }
+
+    #[test]
+    fn from_lines_error() {
+        let lines = "*.rs\nsrc/**/mod.rs\nsrc/[a-\n*.toml";
+        let err = GlobSet::from_lines(lines.lines()).unwrap_err();
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.inner, Error::UnclosedRange(4));
+        assert_eq!(err.input, "src/[a-");
+        assert!(format!("{}", err).starts_with("Glob syntax error on line 3\n"));
+
+        assert_eq!(Pattern::new("src/[a-").unwrap_err().line(), None);
+    }
+
+    #[test]
+    fn longest_match() {
+        let set = GlobSet::from_lines(["**", "*.toml", "config/*.toml", "config/@(local|prod).toml", "config/prod.toml"].iter()).unwrap();
+        assert_eq!(set.longest_match("config/prod.toml"), Some(4));
+        assert_eq!(set.longest_match("config/local.toml"), Some(3));
+        assert_eq!(set.longest_match("config/other.toml"), Some(2));
+        assert_eq!(set.longest_match("Cargo.toml"), Some(1));
+        assert_eq!(set.longest_match("README.md"), Some(0));
+        assert_eq!(GlobSet::new().longest_match("a"), None);
+
+        // as many chars, fewer wildcards
+        let set = GlobSet::from_lines(["a*b*", "a*b?", "a?b?"].iter()).unwrap();
+        assert_eq!(set.longest_match("axby"), Some(2));
+        assert_eq!(set.longest_match("axxby"), Some(1));
+        assert_eq!(set.longest_match("axxbyy"), Some(0));
+
+        // ties go to the pattern inserted first
+        let set = GlobSet::from_lines(["*.rs", "ma*", "*.rs"].iter()).unwrap();
+        assert_eq!(set.longest_match("main.rs"), Some(0));
+        assert_eq!(set.longest_match("mainx"), Some(1));
+    }
+
+    #[test]
+    fn collect() {
+        let set: GlobSet = ["*.rs", "*.toml", "*.rs"].iter()
+            .map(|p| Pattern::new(p))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(set.matches("lib.rs"), vec![0, 2]);
+        assert!(set.is_match("Cargo.toml"));
+        assert!(!GlobSet::new().is_match(""));
+        assert!(GlobSet::new().is_empty());
+
+        let set: GlobSet = vec![Pattern::new("a").unwrap(), Pattern::new("a").unwrap()].into_iter().collect();
+        assert_eq!(set.matches("a"), vec![0, 1]);
+    }
+}
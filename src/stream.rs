@@ -0,0 +1,240 @@
+use alloc::vec::Vec;
+use core::mem::swap;
+
+use crate::{MatchOptions, Pattern};
+use crate::nfa::Nfa;
+
+/// Matches input that arrives in chunks, without ever buffering it.
+///
+/// Instead of backtracking it keeps track of every position in the pattern the input so far could have reached,
+/// so each char is only looked at once. This only works for basic globs,
+/// patterns like `@(...)` or `!(...)` are not supported and [`new`](StreamMatcher::new) returns `None` for them.
+/// Neither is `**` together with [`MatchOptions::max_depth`], nor [`MatchOptions::require_literal_leading_dot`].
+/// ```rust
+/// # use globber::{Pattern, StreamMatcher};
+/// let pattern = Pattern::new("src/**/*.rs").unwrap();
+/// let mut matcher = StreamMatcher::new(&pattern).unwrap();
+/// matcher.feed("src/bin");
+/// matcher.feed("/main.rs");
+/// assert!(matcher.finish());
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamMatcher<'a> {
+    nfa: Nfa<'a>,
+    states: Vec<bool>,
+    next: Vec<bool>,
+}
+
+impl<'a> StreamMatcher<'a> {
+    pub fn new(pattern: &'a Pattern) -> Option<Self> {
+        Self::with_options(pattern, pattern.options)
+    }
+
+    pub fn with_options(pattern: &'a Pattern, options: MatchOptions) -> Option<Self> {
+        let nfa = Nfa::new(pattern.matcher.tokens(), pattern.matcher.is_separator(), options)?;
+        let states = nfa.start();
+
+        Some(Self {
+            nfa,
+            next: Vec::with_capacity(states.len()),
+            states,
+        })
+    }
+
+    /// Feeds the next chunk of input.
+    pub fn feed(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            self.nfa.step(&self.states, &mut self.next, c);
+            swap(&mut self.states, &mut self.next);
+        }
+    }
+
+    /// Returns true if all the input fed so far matches the pattern.
+    pub fn finish(self) -> bool {
+        self.nfa.is_match(&self.states)
+    }
+}
+
+/// A pattern matched against the start of an input, to be resumed with the rest of it later,
+/// see [`Pattern::match_prefix_state`].
+///
+/// It owns everything it needs, so a directory walker can match a directory once and hand the state
+/// to other threads that go on with the entries inside it. Like [`StreamMatcher`] it only supports basic globs.
+/// ```rust
+/// # use globber::Pattern;
+/// let pattern = Pattern::new("src/**/*.rs").unwrap();
+/// let state = pattern.match_prefix_state("src/bin").unwrap();
+/// assert!(state.advance("/main.rs").unwrap().is_match());
+/// assert!(!state.advance("/main.c").unwrap().is_match());
+/// assert!(pattern.match_prefix_state("tests").is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MatchState {
+    pattern: Pattern,
+    options: MatchOptions,
+    states: Vec<bool>,
+}
+
+impl MatchState {
+    pub(crate) fn new(pattern: &Pattern, prefix: &str, options: MatchOptions) -> Option<Self> {
+        let nfa = Nfa::new(pattern.matcher.tokens(), pattern.matcher.is_separator(), options)?;
+        let states = nfa.resume(nfa.start(), prefix.chars())?;
+        Some(Self {
+            pattern: pattern.clone(),
+            options,
+            states,
+        })
+    }
+
+    /// Matches more of the input after what was already matched, without changing this state so it can be
+    /// advanced with other continuations too. Returns `None` once no continuation can match anymore.
+    pub fn advance(&self, more: &str) -> Option<MatchState> {
+        let nfa = Nfa::new(self.pattern.matcher.tokens(), self.pattern.matcher.is_separator(), self.options)?;
+        let states = nfa.resume(self.states.clone(), more.chars())?;
+        Some(Self {
+            pattern: self.pattern.clone(),
+            options: self.options,
+            states,
+        })
+    }
+
+    /// Returns true if all the input matched so far matches the pattern.
+    pub fn is_match(&self) -> bool {
+        self.states[self.states.len() - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamMatcher;
+    use crate::{MatchOptions, Pattern};
+
+    fn stream(pattern: &Pattern, chunks: &[&str]) -> bool {
+        let mut matcher = StreamMatcher::new(pattern).unwrap();
+        for chunk in chunks {
+            matcher.feed(chunk);
+        }
+        matcher.finish()
+    }
+
+    #[test]
+    fn same_as_matches() {
+        let patterns = [
+            "", "*", "**", "abc", "*.rs", "a*b*c", "some/**/needle.txt", "some/**/**/needle.txt", "**/test",
+            "/**/test", "**/.*", "**/*", "x/**/*", "a[a-z]c", "a[!0-9]b", "[]a]*", "/var/log/**", "a*a*a*a*a*a*a*a*a",
+        ];
+        let inputs = [
+            "", "a", "abc", "main.rs", "src/main.rs", "a_b_c", "abcd", "some/needle.txt", "some/one/two/needle.txt",
+            "some/other/notthis.txt", "test", "one/two/test", "/test", "/one/notthis", ".abc", "abc/.abc", "ab.c",
+            "x/", "x", "aec", "a0c", "a_b", "a1b", "]x", "/var/log/a/b", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        ];
+
+        for p in patterns.iter() {
+            let pattern = Pattern::new(p).unwrap();
+            for input in inputs.iter() {
+                let expected = pattern.matches(input);
+                assert_eq!(stream(&pattern, &[input]), expected, "{} {}", p, input);
+                // captures always backtrack
+                assert_eq!(pattern.captures(input).is_some(), expected, "{} {}", p, input);
+
+                // split in every possible place
+                for (i, _) in input.char_indices().skip(1) {
+                    assert_eq!(stream(&pattern, &[&input[..i], &input[i..]]), expected, "{} {}", p, input);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn literal_separator() {
+        let mut options = MatchOptions::new();
+        options.require_literal_separator = true;
+
+        for p in ["*", "*/*", "src/*.rs", "src/**/*.rs", "**/*", "*a*", "a?b", "?/?", "*?*"].iter() {
+            let pattern = Pattern::new(p).unwrap();
+            for input in ["", "a", "a/b", "a/b/c", "src/main.rs", "src/bin/main.rs", "ba/ab", "axb", "/"].iter() {
+                let mut matcher = StreamMatcher::with_options(&pattern, options).unwrap();
+                matcher.feed(input);
+                assert_eq!(matcher.finish(), pattern.matches_with(input, options), "{} {}", p, input);
+            }
+        }
+    }
+
+    #[test]
+    fn unanchored_start() {
+        let mut options = MatchOptions::new();
+        options.unanchored_start = true;
+
+        for p in ["foo.txt", "*.rs", "a*a*b", "**/x", "?b", ""].iter() {
+            let pattern = Pattern::new(p).unwrap();
+            for input in ["", "foo.txt", "a/foo.txt", "foo.txt.bak", "src/main.rs", "aab", "xab", "a/x", "ax", "b"].iter() {
+                let mut matcher = StreamMatcher::with_options(&pattern, options).unwrap();
+                matcher.feed(input);
+                assert_eq!(matcher.finish(), pattern.captures_with(input, options).is_some(), "{} {}", p, input);
+            }
+        }
+    }
+
+    #[test]
+    fn any_char() {
+        let p = Pattern::new("a?c").unwrap();
+        assert!(stream(&p, &["a", "b", "c"]));
+        assert!(!stream(&p, &["ac"]));
+        assert!(!stream(&p, &["abbc"]));
+    }
+
+    #[test]
+    fn options() {
+        let p = Pattern::new("src/*.RS").unwrap();
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        options.normalize_separators = true;
+
+        let mut matcher = StreamMatcher::with_options(&p, options).unwrap();
+        matcher.feed("SRC\\");
+        matcher.feed("main.rs");
+        assert!(matcher.finish());
+    }
+
+    #[test]
+    fn unsupported() {
+        for p in ["@(a|b)", "?(a)", "*(a)", "+(a)", "!(a)", "src/!(*.rs)", "a/**+/b"].iter() {
+            assert!(StreamMatcher::new(&Pattern::new(p).unwrap()).is_none());
+        }
+
+        let mut options = MatchOptions::new();
+        options.max_depth = Some(2);
+        assert!(StreamMatcher::with_options(&Pattern::new("a/**").unwrap(), options).is_none());
+        assert!(StreamMatcher::with_options(&Pattern::new("a/*").unwrap(), options).is_some());
+    }
+    #[test]
+    fn match_state() {
+        let patterns = ["src/**/*.rs", "*.rs", "a*b", "**/x", "src/*", "?", ""];
+        let inputs = ["src/main.rs", "src/bin/main.rs", "main.rs", "src/main.c", "ab", "axb", "a/x", "x", "src/", ""];
+
+        for p in patterns.iter() {
+            let pattern = Pattern::new(p).unwrap();
+            for input in inputs.iter() {
+                for (i, _) in input.char_indices().chain(Some((input.len(), ' '))) {
+                    let state = pattern.match_prefix_state(&input[..i]);
+                    let matches = matches!(state.and_then(|s| s.advance(&input[i..])), Some(s) if s.is_match());
+                    assert_eq!(matches, pattern.matches(input), "{} {} {}", p, input, i);
+                }
+            }
+        }
+
+        let pattern = Pattern::new("src/*/*.rs").unwrap();
+        let state = pattern.match_prefix_state("src/").unwrap();
+        assert!(!state.is_match());
+        let bin = state.advance("bin/").unwrap();
+        assert!(bin.advance("main.rs").unwrap().is_match());
+        assert!(!bin.advance("main.c").unwrap().is_match());
+        assert!(bin.advance("main.rs/").unwrap().advance("a.rs").unwrap().is_match());
+        assert!(state.advance("lib/a.rs").unwrap().is_match());
+
+        let mut options = MatchOptions::new();
+        options.case_insensitive = true;
+        assert!(pattern.match_prefix_state_with("SRC/", options).unwrap().advance("x/Y.RS").unwrap().is_match());
+        assert!(Pattern::new("@(a|b)").unwrap().match_prefix_state("").is_none());
+    }
+}
\ No newline at end of file
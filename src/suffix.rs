@@ -0,0 +1,186 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use crate::{Error, Pattern};
+
+/// A set of patterns like [`GlobSet`](crate::GlobSet) built for filters made of many patterns like `*.rs`.
+///
+/// Patterns that are a `*` followed by chars are kept in a tree of their chars from last to first,
+/// which the input is walked through from its end, so an input is checked against all of them in one pass
+/// no matter how many there are. Any other pattern is matched one at a time.
+/// ```rust
+/// # use globber::SuffixGlobSet;
+/// let set = SuffixGlobSet::from_lines(["*.rs", "*.toml", "*.tar.gz", "src/*"].iter()).unwrap();
+/// assert_eq!(set.matches("src/main.rs"), vec![0, 3]);
+/// assert!(set.is_match("a.tar.gz"));
+/// assert!(!set.is_match("a.gz"));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SuffixGlobSet {
+    patterns: Vec<Pattern>,
+    // the root is the empty suffix, every node is one more char from the end
+    nodes: Vec<Node>,
+    globs: Vec<usize>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+struct Node {
+    children: BTreeMap<char, usize>,
+    // the patterns whose suffix ends at this node
+    patterns: Vec<usize>,
+}
+
+impl SuffixGlobSet {
+    pub fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+            nodes: vec![Node::default()],
+            globs: Vec::new(),
+        }
+    }
+
+    /// Parses every line as a pattern, on failure the error reports the line that failed starting at 1.
+    pub fn from_lines<I, S>(lines: I) -> Result<Self, Error>
+        where I: IntoIterator<Item=S>,
+              S: AsRef<str>,
+    {
+        let mut set = Self::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            let pattern = Pattern::new(line.as_ref())
+                .map_err(|mut e| {
+                    e.line = Some(i + 1);
+                    e
+                })?;
+            set.insert(pattern);
+        }
+        Ok(set)
+    }
+
+    /// Adds a pattern to the set, returning its index.
+    pub fn insert(&mut self, pattern: Pattern) -> usize {
+        let index = self.patterns.len();
+        match pattern.matcher.star_suffix(&pattern.options) {
+            Some(suffix) => {
+                let mut node = 0;
+                for c in suffix.chars().rev() {
+                    node = match self.nodes[node].children.get(&c) {
+                        Some(&child) => child,
+                        None => {
+                            self.nodes.push(Node::default());
+                            let child = self.nodes.len() - 1;
+                            self.nodes[node].children.insert(c, child);
+                            child
+                        }
+                    };
+                }
+                self.nodes[node].patterns.push(index);
+            }
+            None => self.globs.push(index),
+        }
+        self.patterns.push(pattern);
+        index
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Pattern> {
+        self.patterns.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns true if any pattern in the set matches.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.suffixes(input).any(|node| !self.nodes[node].patterns.is_empty())
+            || self.globs.iter().any(|&i| self.patterns[i].matches(input))
+    }
+
+    /// Returns the indices of every pattern that matches in ascending order.
+    pub fn matches(&self, input: &str) -> Vec<usize> {
+        let mut matches: Vec<usize> = self.globs.iter()
+            .copied()
+            .filter(|&i| self.patterns[i].matches(input))
+            .collect();
+        for node in self.suffixes(input) {
+            matches.extend_from_slice(&self.nodes[node].patterns);
+        }
+        matches.sort_unstable();
+        matches
+    }
+
+    // the nodes of every suffix of the tree the input ends with, shortest first
+    fn suffixes<'a>(&'a self, input: &'a str) -> impl Iterator<Item=usize> + 'a {
+        let mut chars = input.chars().rev();
+        core::iter::successors(Some(0), move |&node| self.nodes[node].children.get(&chars.next()?).copied())
+    }
+}
+
+impl Default for SuffixGlobSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<Pattern> for SuffixGlobSet {
+    fn from_iter<T: IntoIterator<Item=Pattern>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Pattern> for SuffixGlobSet {
+    fn extend<T: IntoIterator<Item=Pattern>>(&mut self, iter: T) {
+        for pattern in iter {
+            self.insert(pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuffixGlobSet;
+    use crate::{GlobSet, Pattern, PatternBuilder};
+
+    #[test]
+    fn matches() {
+        let lines = ["*.rs", "*.toml", "*.tar.gz", "*.gz", "*", "src/**/*.rs", "*.rs", "Cargo.lock", "*(a|b).c"];
+        let set = SuffixGlobSet::from_lines(lines.iter()).unwrap();
+        let general = GlobSet::from_lines(lines.iter()).unwrap();
+        assert_eq!(set.len(), 9);
+        assert_eq!(set.get(1), Some(&Pattern::new("*.toml").unwrap()));
+
+        let inputs = ["main.rs", "src/a/main.rs", "a.tar.gz", "a.gz", "gz", "Cargo.lock", "a.c", "ab.c", "", "é.rs", "rs"];
+        for input in inputs.iter() {
+            assert_eq!(set.matches(input), general.matches(input), "{}", input);
+            assert_eq!(set.is_match(input), general.is_match(input), "{}", input);
+        }
+        assert_eq!(set.matches("a.tar.gz"), vec![2, 3, 4]);
+        assert_eq!(set.matches("src/a/main.rs"), vec![0, 4, 5, 6]);
+
+        let set = SuffixGlobSet::from_lines(["*.rs", "*.toml"].iter()).unwrap();
+        assert!(!set.is_match("main.c"));
+        assert!(!set.is_match(""));
+        assert!(SuffixGlobSet::new().is_empty());
+    }
+
+    #[test]
+    fn options() {
+        // a * that can't cross separators isn't just a suffix anymore
+        let pattern = PatternBuilder::new("*.rs").require_literal_separator(true).build().unwrap();
+        let set: SuffixGlobSet = vec![pattern, Pattern::new("*.toml").unwrap()].into_iter().collect();
+        assert_eq!(set.matches("main.rs"), vec![0]);
+        assert_eq!(set.matches("src/main.rs"), Vec::<usize>::new());
+        assert_eq!(set.matches("src/Cargo.toml"), vec![1]);
+
+        let pattern = PatternBuilder::new("*.RS").case_insensitive(true).build().unwrap();
+        let set: SuffixGlobSet = vec![pattern].into_iter().collect();
+        assert!(set.is_match("main.rs"));
+    }
+}
\ No newline at end of file
@@ -1,3 +1,9 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub(crate) enum Token {
     // ?
@@ -6,10 +12,13 @@ pub(crate) enum Token {
     AnySequence,
     // **
     AnyRecursive,
+    // **+
+    AtLeastOneSegment,
     // [abc-z123]
     AnyOf(Vec<CharSpecifier>),
     // [!abc-z123]
     NotAnyOf(Vec<CharSpecifier>),
+    // the specifiers of both ranges above are always normalized, see normalize_specifiers
     // v a r l o g
     Char(char),
     // ?(pattern|pattern|pattern)
@@ -22,14 +31,35 @@ pub(crate) enum Token {
     ExactlyOne(Vec<Vec<Token>>),
     // !(pattern|pattern|pattern)
     NoneOf(Vec<Vec<Token>>),
+    // @<name>(pattern|pattern|pattern), any of the patterns above with a name capturing what it matched
+    Named(String, Box<Token>),
 }
 
+/// One entry of a range like `[a-z_]`, see [`CharClass::specifiers`](crate::CharClass::specifiers).
+/// ```rust
+/// # use globber::CharSpecifier;
+/// assert!(CharSpecifier::Range('a', 'z').matches('q'));
+/// assert!(!CharSpecifier::Char('_').matches('-'));
+/// ```
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub(crate) enum CharSpecifier {
+pub enum CharSpecifier {
+    /// Exactly this char, like `_`.
     Char(char),
+    /// Every char from the first to the second one inclusively, like `a-z`.
+    /// A reversed range never matches.
     Range(char, char),
 }
 
+impl CharSpecifier {
+    /// Returns true if `c` is this char or inside this range, comparing chars exactly.
+    pub fn matches(&self, c: char) -> bool {
+        match *self {
+            CharSpecifier::Char(s) => s == c,
+            CharSpecifier::Range(start, end) => start <= c && c <= end,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub(crate) enum Error {
     EmptyPattern(usize),
@@ -41,47 +71,246 @@ pub(crate) enum Error {
     IllegalWildcard(usize),
     IllegalRecursion(usize),
     IllegalEscape(usize),
+    IllegalCodePoint(usize),
+    NestedTooDeep(usize),
+}
+
+/// What went wrong parsing a pattern, see [`Error::kind`](crate::Error::kind).
+///
+/// Match on it to describe errors in your own words, e.g. in another language,
+/// [`desc`](ErrorKind::desc) is the description used by `Display`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A pattern without any alternatives, like `@()`.
+    EmptyPattern,
+    /// A pattern like `@(a` missing its `)`.
+    UnclosedPattern,
+    /// A `(`, `)`, `|`, `[` or `]` where it can't be, which has to be escaped.
+    IllegalChar,
+    /// An empty alternative, like `@(a|)`. A pattern that can also match nothing is written `?(a)`.
+    IllegalOr,
+    /// A range like `[a-z` missing its `]`.
+    UnclosedRange,
+    /// A range without any chars, like `[!]`.
+    EmptyRange,
+    /// More than two `*` in a row.
+    IllegalWildcard,
+    /// A `**` that isn't a path component of its own, like `a**`.
+    IllegalRecursion,
+    /// A `\` at the very end.
+    IllegalEscape,
+    /// A `\x` or `\u` escape that isn't the hex code of a char.
+    IllegalCodePoint,
+    /// Patterns nested deeper than allowed, see [`PatternBuilder::max_nesting`](crate::PatternBuilder::max_nesting).
+    NestedTooDeep,
+}
+
+impl ErrorKind {
+    /// The description of the error in English, which is what `Display` shows.
+    pub fn desc(&self) -> &'static str {
+        match self {
+            ErrorKind::EmptyPattern => "patterns must not be empty! e.g !(), *(), @(), etc",
+            ErrorKind::UnclosedPattern => "patterns must be ended with a ), consider adding one!",
+            ErrorKind::IllegalChar => "illegal character found! consider escaping with \\",
+            ErrorKind::IllegalOr => "| must have a pattern on the left and right! e.g (|), (a|), (|a), (a||b), use ?(a|b) for a, b or nothing",
+            ErrorKind::UnclosedRange => "ranges must be ended with a ], consider adding one!",
+            ErrorKind::EmptyRange => "ranges must not be empty! e.g [], [!], etc",
+            ErrorKind::IllegalWildcard => "only *, ** and **+ are allowed, eg ***, ****, etc",
+            ErrorKind::IllegalRecursion => "** must be a single path component, e.g a/**b, a/bc**, a/b**c**d, a**/b, etc",
+            ErrorKind::IllegalEscape => "\\ must be followed be a character, for windows separators use \\\\",
+            ErrorKind::IllegalCodePoint => "\\x must be followed by two hex digits and \\u by the hex code of a char in braces, e.g \\x41, \\u{e9}, etc",
+            ErrorKind::NestedTooDeep => "patterns are nested too deep! e.g !(!(!(...))), consider raising the limit of the builder",
+        }
+    }
 }
 
 impl Error {
+    pub(crate) fn kind(&self) -> ErrorKind {
+        match self {
+            Error::EmptyPattern(_) => ErrorKind::EmptyPattern,
+            Error::UnclosedPattern(_) => ErrorKind::UnclosedPattern,
+            Error::IllegalChar(_) => ErrorKind::IllegalChar,
+            Error::IllegalOr(_) => ErrorKind::IllegalOr,
+            Error::UnclosedRange(_) => ErrorKind::UnclosedRange,
+            Error::EmptyRange(_) => ErrorKind::EmptyRange,
+            Error::IllegalWildcard(_) => ErrorKind::IllegalWildcard,
+            Error::IllegalRecursion(_) => ErrorKind::IllegalRecursion,
+            Error::IllegalEscape(_) => ErrorKind::IllegalEscape,
+            Error::IllegalCodePoint(_) => ErrorKind::IllegalCodePoint,
+            Error::NestedTooDeep(_) => ErrorKind::NestedTooDeep,
+        }
+    }
+
     pub(crate) fn desc(&self) -> &str {
+        self.kind().desc()
+    }
+
+    /// The offset of the char the error points at.
+    pub(crate) fn position(&self) -> usize {
+        match *self {
+            Error::EmptyPattern(i) | Error::UnclosedPattern(i)
+            | Error::IllegalChar(i) | Error::IllegalOr(i)
+            | Error::UnclosedRange(i) | Error::EmptyRange(i)
+            | Error::IllegalWildcard(i) | Error::IllegalRecursion(i)
+            | Error::IllegalEscape(i) | Error::IllegalCodePoint(i)
+            | Error::NestedTooDeep(i) => i,
+        }
+    }
+
+    pub(crate) fn shifted(self, by: usize) -> Self {
         match self {
-            Error::EmptyPattern(_) => "patterns must not be empty! e.g !(), *(), @(), etc",
-            Error::UnclosedPattern(_) => "patterns must be ended with a ), consider adding one!",
-            Error::IllegalChar(_) => "illegal character found! consider escaping with \\",
-            Error::IllegalOr(_) => "| must have a pattern on the left and right! e.g (|), (a|), (|a), (a||b)",
-            Error::UnclosedRange(_) => "ranges must be ended with a ], consider adding one!",
-            Error::EmptyRange(_) => "ranges must not be empty! e.g [], [!], etc",
-            Error::IllegalWildcard(_) => "only * and ** are allowed, eg ***, ****, etc",
-            Error::IllegalRecursion(_) => "** must be a single path component, e.g a/**b, a/bc**, a/b**c**d, a**/b, etc",
-            Error::IllegalEscape(_) => "\\ must be followed be a character, for windows separators use \\\\"
+            Error::EmptyPattern(i) => Error::EmptyPattern(i + by),
+            Error::UnclosedPattern(i) => Error::UnclosedPattern(i + by),
+            Error::IllegalChar(i) => Error::IllegalChar(i + by),
+            Error::IllegalOr(i) => Error::IllegalOr(i + by),
+            Error::UnclosedRange(i) => Error::UnclosedRange(i + by),
+            Error::EmptyRange(i) => Error::EmptyRange(i + by),
+            Error::IllegalWildcard(i) => Error::IllegalWildcard(i + by),
+            Error::IllegalRecursion(i) => Error::IllegalRecursion(i + by),
+            Error::IllegalEscape(i) => Error::IllegalEscape(i + by),
+            Error::IllegalCodePoint(i) => Error::IllegalCodePoint(i + by),
+            Error::NestedTooDeep(i) => Error::NestedTooDeep(i + by),
         }
     }
 }
 
-pub(crate) fn parse(input: &str) -> Result<Vec<Token>, Error> {
-    Parser::new(input).parse()
+/// How deep patterns like `@(...)` can nest by default, deeper ones could overflow the stack
+/// both while parsing and matching.
+pub(crate) const MAX_NESTING: usize = 128;
+
+pub(crate) fn parse_with(input: &str, is_separator: fn(char) -> bool) -> Result<Vec<Token>, Error> {
+    Syntax::new(is_separator).parse(input)
+}
+
+/// The settings a pattern is parsed with.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Syntax {
+    pub(crate) is_separator: fn(char) -> bool,
+    // whether ?, * and + can follow what they apply to, like in a regex
+    pub(crate) postfix: bool,
+    pub(crate) max_nesting: usize,
+    // the char escaping the one after it, \ unless that is a separator
+    pub(crate) escape: char,
+    // whether patterns like @(...) are read, otherwise (, ) and | are plain chars
+    pub(crate) extended: bool,
+}
+
+impl Syntax {
+    pub(crate) fn new(is_separator: fn(char) -> bool) -> Self {
+        Self {
+            is_separator,
+            postfix: false,
+            max_nesting: MAX_NESTING,
+            escape: '\\',
+            extended: true,
+        }
+    }
+
+    pub(crate) fn parse(&self, input: &str) -> Result<Vec<Token>, Error> {
+        Parser::new(input, *self).parse()
+    }
+}
+
+/// Checks that every `**` and `**+`, also inside patterns like `@(...)`, is a whole path component with only
+/// separators or the ends of its pattern next to it. Like the parser points at the char next to a `**` that
+/// isn't a separator, this returns the index of the token next to it, or of the top level pattern containing it.
+pub(crate) fn validate_recursive_placement(tokens: &[Token], is_separator: fn(char) -> bool) -> Result<(), usize> {
+    let separator = |t: &Token| match t {
+        Token::Char(c) => is_separator(*c),
+        _ => false,
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        let token = match token {
+            Token::Named(_, pattern) => &**pattern,
+            t => t,
+        };
+        match token {
+            Token::AnyRecursive | Token::AtLeastOneSegment => {
+                if let Some(before) = i.checked_sub(1).filter(|before| !separator(&tokens[*before])) {
+                    return Err(before);
+                }
+                if matches!(tokens.get(i + 1), Some(t) if !separator(t)) {
+                    return Err(i + 1);
+                }
+            }
+            Token::ZeroOrOne(p) | Token::ZeroOrMore(p) | Token::OneOrMore(p) | Token::ExactlyOne(p) | Token::NoneOf(p)
+                if !p.iter().all(|p| validate_recursive_placement(p, is_separator).is_ok()) => return Err(i),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_class(input: &str) -> Result<Token, Error> {
+    // separators don't matter inside a range
+    let mut parser = Parser::new(input, Syntax::new(|_| false));
+    match parser.chars.first() {
+        Some('[') => {}
+        Some(_) => { return Err(Error::IllegalChar(0)); }
+        None => { return Err(Error::EmptyRange(0)); }
+    }
+
+    let token = parser.parse_range()?;
+    if parser.i < parser.chars.len() {
+        return Err(Error::IllegalChar(parser.i));
+    }
+
+    Ok(token)
 }
 
 struct Parser {
     chars: Vec<char>,
+    // where the pattern being parsed starts and ends, patterns like @(...) parse their alternatives in place
+    start: usize,
+    end: usize,
+    // the | between the patterns of every ( found while scanning the one around it and the ) closing it
+    groups: BTreeMap<usize, (Vec<usize>, usize)>,
     i: usize,
+    syntax: Syntax,
+    // how many patterns this one is nested in
+    depth: usize,
 }
 
 impl Parser {
-    fn new(input: &str) -> Self {
+    fn new(input: &str, syntax: Syntax) -> Self {
+        let chars: Vec<char> = input.chars().collect();
         Self {
-            chars: input.chars().collect(),
+            start: 0,
+            end: chars.len(),
+            groups: BTreeMap::new(),
+            chars,
             i: 0,
+            syntax,
+            depth: 0,
         }
     }
 
+    // the chars up to the end of the pattern being parsed, indexed like the whole input
+    fn chars(&self) -> &[char] {
+        &self.chars[..self.end]
+    }
+
     fn parse(&mut self) -> Result<Vec<Token>, Error> {
         let mut tokens = Vec::new();
 
-        while self.i < self.chars.len() {
-            if self.i + 1 < self.chars.len() {
-                let token = match (self.chars[self.i], self.chars[self.i + 1]) {
+        while self.i < self.chars().len() {
+            if self.syntax.extended && self.i + 1 < self.chars().len() {
+                let start = self.i;
+                let op = self.chars()[self.i];
+                let name = self.pattern_name();
+                if let Some((_, close)) = name {
+                    // the ( now follows like it does for a pattern without a name
+                    self.i = close;
+                }
+
+                let opens = matches!((op, self.chars()[self.i + 1]), ('?', '(') | ('*', '(') | ('+', '(') | ('@', '(') | ('!', '('));
+                if opens && self.depth >= self.syntax.max_nesting {
+                    return Err(Error::NestedTooDeep(start));
+                }
+
+                let token = match (op, self.chars()[self.i + 1]) {
                     ('?', '(') => Some(Token::ZeroOrOne(self.parse_patterns()?)),
                     ('*', '(') => Some(Token::ZeroOrMore(self.parse_patterns()?)),
                     ('+', '(') => Some(Token::OneOrMore(self.parse_patterns()?)),
@@ -91,20 +320,29 @@ impl Parser {
                 };
 
                 if let Some(t) = token {
-                    tokens.push(t);
+                    match name {
+                        Some((name, _)) => tokens.push(Token::Named(name, Box::new(t))),
+                        None => tokens.push(t),
+                    }
                     continue;
                 }
             }
 
-            let token = match self.chars[self.i] {
+            if let Some(token) = self.parse_quantifier(&mut tokens) {
+                tokens.push(token);
+                continue;
+            }
+
+            let token = match self.chars()[self.i] {
                 '?' => {
                     self.i += 1;
                     Token::AnyChar
                 }
                 '*' => self.parse_wildcards()?,
-                '\\' => self.parse_escape()?,
+                c if c == self.syntax.escape => self.parse_escape()?,
                 '[' => self.parse_range()?,
-                ']' | '(' | ')' | '|' => { return Err(Error::IllegalChar(self.i)); }
+                ']' => { return Err(Error::IllegalChar(self.i)); }
+                '(' | ')' | '|' if self.syntax.extended => { return Err(Error::IllegalChar(self.i)); }
                 c => {
                     self.i += 1;
                     Token::Char(c)
@@ -114,33 +352,87 @@ impl Parser {
             tokens.push(token);
         }
 
+        // checked char by char above already, which points at the exact char
+        debug_assert_eq!(validate_recursive_placement(&tokens, self.syntax.is_separator), Ok(()));
         Ok(tokens)
     }
 
+    // a postfix ?, * or + takes the token before it, separators are left alone so src/*.rs still means what it says
+    fn parse_quantifier(&mut self, tokens: &mut Vec<Token>) -> Option<Token> {
+        if !self.syntax.postfix {
+            return None;
+        }
+
+        let quantified = match tokens.last()? {
+            Token::Char(c) => !(self.syntax.is_separator)(*c),
+            Token::AnyOf(_) | Token::NotAnyOf(_) | Token::ZeroOrOne(_) | Token::ZeroOrMore(_) | Token::OneOrMore(_)
+            | Token::ExactlyOne(_) | Token::NoneOf(_) | Token::Named(..) => true,
+            Token::AnyChar | Token::AnySequence | Token::AnyRecursive | Token::AtLeastOneSegment => false,
+        };
+        if !quantified {
+            return None;
+        }
+
+        let quantifier: fn(Vec<Vec<Token>>) -> Token = match self.chars()[self.i] {
+            '?' => Token::ZeroOrOne,
+            '*' => Token::ZeroOrMore,
+            '+' => Token::OneOrMore,
+            _ => return None,
+        };
+        self.i += 1;
+        let token = tokens.pop()?;
+        Some(quantifier(vec![vec![token]]))
+    }
+
+    // the name of a pattern like @<name>(...) and the index of the > closing it,
+    // anything else including a < without a valid name and ( is left alone
+    fn pattern_name(&self) -> Option<(String, usize)> {
+        match self.chars().get(self.i..self.i + 2) {
+            Some(['?', '<']) | Some(['*', '<']) | Some(['+', '<']) | Some(['@', '<']) | Some(['!', '<']) => {}
+            _ => return None,
+        }
+
+        let start = self.i + 2;
+        let len = self.chars()[start..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+        let close = start + len;
+        if len == 0 || self.chars().get(close..close + 2) != Some(&['>', '(']) {
+            return None;
+        }
+
+        Some((self.chars()[start..close].iter().collect(), close))
+    }
+
     fn parse_wildcards(&mut self) -> Result<Token, Error> {
         let mut token = Token::AnySequence;
         let start = self.i;
         let next = self.i + 1;
 
         // check if the next char is a *, if so we found **
-        if next < self.chars.len() && self.chars[next] == '*' {
+        if next < self.chars().len() && self.chars()[next] == '*' {
             token = Token::AnyRecursive;
 
-            // check that to the left of the first * is either no char or a /
-            if start > 0 && self.chars[start - 1] != '/' {
+            // check that to the left of the first * is either no char or a separator
+            if start > self.start && !(self.syntax.is_separator)(self.chars()[start - 1]) {
                 return Err(Error::IllegalRecursion(start - 1));
             }
 
-            // check that to the right of the last * is either no char or a /
-            if next + 1 < self.chars.len() {
-                match self.chars[next + 1] {
-                    '/' => {}
-                    '*' => { return Err(Error::IllegalWildcard(next + 1)); }
-                    _ => { return Err(Error::IllegalRecursion(next + 1)); }
+            // **+ takes at least one segment
+            let mut end = next + 1;
+            if self.chars().get(end) == Some(&'+') {
+                token = Token::AtLeastOneSegment;
+                end += 1;
+            }
+
+            // check that to the right of the last * or + is either no char or a separator
+            if end < self.chars().len() {
+                match self.chars()[end] {
+                    '*' => { return Err(Error::IllegalWildcard(end)); }
+                    c if (self.syntax.is_separator)(c) => {}
+                    _ => { return Err(Error::IllegalRecursion(end)); }
                 }
             }
 
-            self.i = next + 1;
+            self.i = end;
             Ok(token)
         } else {
             self.i = next;
@@ -149,165 +441,258 @@ impl Parser {
     }
 
     fn parse_escape(&mut self) -> Result<Token, Error> {
-        if self.i + 1 >= self.chars.len() {
-            return Err(Error::IllegalEscape(self.i));
-        }
-
-        self.i += 2;
-        Ok(Token::Char(self.chars[self.i - 1]))
+        let (c, len) = unescape(self.chars(), self.i)?;
+        self.i += len;
+        Ok(Token::Char(c))
     }
 
     fn parse_range(&mut self) -> Result<Token, Error> {
         let start = self.i;
         let mut first_char = self.i + 1;
 
-        if first_char >= self.chars.len() {
-            return Err(Error::UnclosedRange(start));
+        let negated = match self.chars().get(first_char) {
+            Some(c) => *c == '!',
+            None => { return Err(Error::UnclosedRange(start)); }
+        };
+        if negated {
+            first_char += 1;
         }
 
-        let negated = match self.chars[first_char] {
-            '!' => {
-                first_char += 1;
-                true
-            }
-            ']' => { return Err(Error::EmptyRange(start)); }
-            _ => false,
-        };
+        let end = self.range_end(start);
 
         let mut escaped = false;
-        let mut chars = None;
-        for (i, c) in self.chars[first_char..].iter().enumerate() {
+        let body = self.chars().get(first_char..end.unwrap_or(self.chars().len())).unwrap_or(&[]);
+        for (i, c) in body.iter().enumerate() {
             match c {
                 _ if escaped => escaped = false,
-                '\\' => escaped = true,
-                ']' => {
-                    chars = Some(&self.chars[first_char..first_char + i]);
-                    break;
-                }
-                '[' | '(' | ')' | '|' => { return Err(Error::IllegalChar(first_char + i)); }
+                c if *c == self.syntax.escape => escaped = true,
+                // a | can't end the range or the pattern it is in, unlike these
+                '[' | '(' | ')' => { return Err(Error::IllegalChar(first_char + i)); }
                 _ => {}
             }
         }
 
-        let chars = match chars {
-            Some(c) if c.is_empty() => { return Err(Error::EmptyRange(start)); }
-            None => { return Err(Error::UnclosedRange(self.chars.len() - 1)); }
-            Some(c) => c,
+        let end = match end {
+            Some(end) => end,
+            // a single ] can't be both the first char and the end of the range
+            None if self.chars().get(first_char) == Some(&']') => { return Err(Error::EmptyRange(start)); }
+            None => { return Err(Error::UnclosedRange(start)); }
         };
 
-        self.i = first_char + chars.len() + 1;
+        self.i = end + 1;
 
+        let specifiers = parse_char_specifiers(&self.chars()[first_char..end], self.syntax.escape)
+            .map_err(|e| e.shifted(first_char))?;
+        let specifiers = normalize_specifiers(&specifiers);
         if negated {
-            Ok(Token::NotAnyOf(parse_char_specifiers(chars)))
+            Ok(Token::NotAnyOf(specifiers))
         } else {
-            Ok(Token::AnyOf(parse_char_specifiers(chars)))
+            Ok(Token::AnyOf(specifiers))
         }
     }
 
-    fn parse_patterns(&mut self) -> Result<Vec<Vec<Token>>, Error> {
-        let start = self.i + 2;
-        let mut paren_stack = Vec::new();
+    // finds the ] closing the range opened at start, POSIX style a ] right after [ or [! is part of the range
+    fn range_end(&self, start: usize) -> Option<usize> {
+        let mut i = start + 1;
+        if self.chars().get(i) == Some(&'!') {
+            i += 1;
+        }
+        if self.chars().get(i) == Some(&']') {
+            i += 1;
+        }
 
         let mut escaped = false;
-        let mut chars = None;
-        for (i, c) in self.chars[start..].iter().enumerate() {
-            match c {
+        while i < self.chars().len() {
+            match self.chars()[i] {
                 _ if escaped => escaped = false,
-                '\\' => escaped = true,
-                ']' => {
-                    match paren_stack.last() {
-                        Some(c) if *c != '[' => return Err(Error::IllegalChar(start + i)),
-                        None => return Err(Error::IllegalChar(start + i)),
-                        _ => paren_stack.pop(),
-                    };
-                }
-                ')' if paren_stack.is_empty() => {
-                    chars = Some(&self.chars[start..start + i]);
-                    break;
-                }
-                ')' => {
-                    match paren_stack.last() {
-                        Some(c) if *c != '(' => return Err(Error::IllegalChar(start + i)),
-                        None => return Err(Error::IllegalChar(start + i)),
-                        _ => paren_stack.pop(),
-                    };
-                }
-                '(' | '[' => paren_stack.push(*c),
+                c if c == self.syntax.escape => escaped = true,
+                ']' => { return Some(i); }
                 _ => {}
             }
+            i += 1;
         }
 
-        let chars = match chars {
-            Some(c) if c.is_empty() => { return Err(Error::EmptyPattern(start)); }
-            None => { return Err(Error::UnclosedPattern(self.chars.len() - 1)); }
-            Some(c) => c,
+        None
+    }
+
+    fn parse_patterns(&mut self) -> Result<Vec<Vec<Token>>, Error> {
+        let start = self.i + 2;
+
+        // the patterns nested in others were found while scanning those
+        let (ors, end) = match self.groups.remove(&start) {
+            Some((ors, end)) => (ors, Some(end)),
+            None => self.scan(start)?,
         };
 
-        let mut pattern_parts = Vec::new();
-        let mut last_pattern = 0;
-        for (i, c) in chars.iter().enumerate() {
-            match c {
+        let end = match end {
+            Some(end) if end == start => { return Err(Error::EmptyPattern(start)); }
+            None => { return Err(Error::UnclosedPattern(self.chars().len() - 1)); }
+            Some(end) => end,
+        };
+
+        let mut pattern_parts = Vec::with_capacity(ors.len() + 1);
+        let mut last_pattern = start;
+        for i in ors.into_iter().chain(Some(end)) {
+            if i == last_pattern {
+                return Err(Error::IllegalOr(last_pattern));
+            }
+            pattern_parts.push(last_pattern..i);
+            last_pattern = i + 1;
+        }
+
+        // every pattern is parsed in place, as if the input ended where it does
+        let (outer_start, outer_end) = (self.start, self.end);
+        self.depth += 1;
+        let mut tokens = Vec::with_capacity(pattern_parts.len());
+        for part in pattern_parts.into_iter() {
+            self.start = part.start;
+            self.end = part.end;
+            self.i = part.start;
+            tokens.push(self.parse()?);
+        }
+        self.depth -= 1;
+        self.start = outer_start;
+        self.end = outer_end;
+
+        self.i = end + 1;
+
+        Ok(tokens)
+    }
+
+    // one pass finds both the ) that closes the patterns starting at start and the | between them,
+    // anything inside a range or nested parentheses belongs to a single pattern.
+    // The patterns nested in these are kept in groups, so parsing them doesn't scan the same chars again.
+    fn scan(&mut self, start: usize) -> Result<(Vec<usize>, Option<usize>), Error> {
+        // every ( and [ still open, with where the patterns of a ( start and the | between them found so far
+        let mut open: Vec<(char, usize, Vec<usize>)> = Vec::new();
+        let mut ors = Vec::new();
+        let mut escaped = false;
+        let mut i = start;
+        while i < self.chars().len() {
+            match self.chars()[i] {
                 _ if escaped => escaped = false,
-                '\\' => escaped = true,
-                ']' => {
-                    match paren_stack.last() {
-                        Some(c) if *c != '[' => return Err(Error::IllegalChar(start + i)),
-                        None => return Err(Error::IllegalChar(start + i)),
-                        _ => paren_stack.pop(),
-                    };
-                }
-                ')' => {
-                    match paren_stack.last() {
-                        Some(c) if *c != '(' => return Err(Error::IllegalChar(start + i)),
-                        None => return Err(Error::IllegalChar(start + i)),
-                        _ => paren_stack.pop(),
-                    };
-                }
-                '(' | '[' => paren_stack.push(*c),
-                '|' => if paren_stack.len() == 0 {
-                    let part = &self.chars[start + last_pattern..start + i];
-                    if part.is_empty() {
-                        return Err(Error::IllegalOr(start + last_pattern));
+                c if c == self.syntax.escape => escaped = true,
+                '|' => match open.last_mut() {
+                    None => ors.push(i),
+                    Some(('(', _, nested)) => nested.push(i),
+                    Some(_) => {}
+                },
+                ')' => match open.pop() {
+                    None => return Ok((ors, Some(i))),
+                    Some(('(', nested_start, nested)) => {
+                        self.groups.insert(nested_start, (nested, i));
                     }
-                    last_pattern = i + 1;
-                    pattern_parts.push(part);
-                }
+                    Some(_) => return Err(Error::IllegalChar(i)),
+                },
+                ']' => match open.pop() {
+                    Some(('[', ..)) => {}
+                    _ => return Err(Error::IllegalChar(i)),
+                },
+                // skip whole ranges, they can contain a ] that doesn't close anything
+                '[' => match self.range_end(i) {
+                    Some(end) => i = end,
+                    None => open.push(('[', i, Vec::new())),
+                },
+                '(' => open.push(('(', i + 1, Vec::new())),
                 _ => {}
             }
+            i += 1;
         }
+        Ok((ors, None))
+    }
+}
 
-        let part = &self.chars[start + last_pattern..start + chars.len()];
-        if part.is_empty() {
-            return Err(Error::IllegalOr(start + last_pattern));
+// the char escaped by the \ at chars[i] and how many chars the escape takes up,
+// \xHH and \u{HHHHHH} name a char by its code point and any other char is taken literally
+fn unescape(chars: &[char], i: usize) -> Result<(char, usize), Error> {
+    let hex = |digits: &[char]| {
+        digits.iter().try_fold(0u32, |code, c| Some(code * 16 + c.to_digit(16)?))
+            .and_then(core::char::from_u32)
+    };
+
+    match chars.get(i + 1) {
+        Some('x') => {
+            let c = chars.get(i + 2..i + 4).and_then(hex).ok_or(Error::IllegalCodePoint(i))?;
+            Ok((c, 4))
         }
-        pattern_parts.push(part);
-
-        let mut tokens = Vec::new();
-        for part in pattern_parts.into_iter() {
-            let mut pattern = String::new();
-            for c in part {
-                pattern.push(*c)
+        Some('u') => {
+            if chars.get(i + 2) != Some(&'{') {
+                return Err(Error::IllegalCodePoint(i));
+            }
+            let digits = chars[i + 3..].iter().take(7).take_while(|c| **c != '}').count();
+            if digits == 0 || digits > 6 || chars.get(i + 3 + digits) != Some(&'}') {
+                return Err(Error::IllegalCodePoint(i));
             }
-            tokens.push(parse(&pattern)?)
+            let c = hex(&chars[i + 3..i + 3 + digits]).ok_or(Error::IllegalCodePoint(i))?;
+            Ok((c, digits + 4))
         }
-
-        self.i = start + chars.len() + 1;
-
-        Ok(tokens)
+        Some(&c) => Ok((c, 2)),
+        None => Err(Error::IllegalEscape(i)),
     }
 }
 
-fn parse_char_specifiers(s: &[char]) -> Vec<CharSpecifier> {
-    let mut cs = Vec::new();
+fn parse_char_specifiers(s: &[char], escape: char) -> Result<Vec<CharSpecifier>, Error> {
+    // (char, escaped), an escaped char is always taken literally
+    let mut chars = Vec::with_capacity(s.len());
     let mut i = 0;
     while i < s.len() {
-        if i + 3 <= s.len() && s[i + 1] == '-' {
-            cs.push(CharSpecifier::Range(s[i], s[i + 2]));
-            i += 3;
+        if s[i] == escape {
+            let (c, len) = unescape(s, i)?;
+            chars.push((c, true));
+            i += len;
         } else {
-            cs.push(CharSpecifier::Char(s[i]));
+            chars.push((s[i], false));
             i += 1;
         }
     }
-    cs
+
+    // a - is only a range operator with a char on both sides of it, so a - at the start or end
+    // or right after another range like the second one in [a-c-e] is taken literally
+    let mut cs = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1), chars.get(i + 2)) {
+            ((start, _), Some(('-', false)), Some(&(end, _))) => {
+                cs.push(CharSpecifier::Range(start, end));
+                i += 3;
+            }
+            ((c, _), _, _) => {
+                cs.push(CharSpecifier::Char(c));
+                i += 1;
+            }
+        }
+    }
+    Ok(cs)
+}
+
+/// Sorts specifiers and merges the ones that overlap or touch, dropping reversed ranges since they can never match.
+pub(crate) fn normalize_specifiers(specifiers: &[CharSpecifier]) -> Vec<CharSpecifier> {
+    let mut bounds: Vec<(char, char)> = specifiers.iter()
+        .map(|s| match *s {
+            CharSpecifier::Char(c) => (c, c),
+            CharSpecifier::Range(start, end) => (start, end),
+        })
+        .filter(|(start, end)| start <= end)
+        .collect();
+    bounds.sort();
+
+    let mut merged: Vec<(char, char)> = Vec::with_capacity(bounds.len());
+    for (start, end) in bounds {
+        match merged.last_mut() {
+            Some(last) if start as u32 <= last.1 as u32 + 1 => {
+                if end > last.1 {
+                    last.1 = end;
+                }
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged.into_iter()
+        .map(|(start, end)| if start == end {
+            CharSpecifier::Char(start)
+        } else {
+            CharSpecifier::Range(start, end)
+        })
+        .collect()
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{Ast, Error, Pattern};
+use crate::explain::render;
+use crate::options::is_separator;
+use crate::syntax::{normalize_specifiers, validate_recursive_placement, CharSpecifier, Error as SyntaxError, Token, MAX_NESTING};
+
+/// One part of a pattern, for tools that generate patterns as tokens instead of strings,
+/// see [`Pattern::from_tokens`].
+///
+/// Unlike [`Ast`] these are checked like a parsed pattern is, e.g. a `**` has to be a whole path component.
+/// ```rust
+/// # use globber::{Pattern, PublicToken};
+/// let pattern = Pattern::from_tokens(vec![PublicToken::AnySequence, PublicToken::Char('.'), PublicToken::Char('r'), PublicToken::Char('s')]).unwrap();
+/// assert_eq!(pattern, Pattern::new("*.rs").unwrap());
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PublicToken {
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnySequence,
+    /// `**`
+    AnyRecursive,
+    /// `**+`
+    AtLeastOneSegment,
+    /// `[abc-z]`
+    AnyOf(Vec<CharSpecifier>),
+    /// `[!abc-z]`
+    NotAnyOf(Vec<CharSpecifier>),
+    /// A char matched literally.
+    Char(char),
+    /// `?(a|b)`
+    ZeroOrOne(Vec<Vec<PublicToken>>),
+    /// `*(a|b)`
+    ZeroOrMore(Vec<Vec<PublicToken>>),
+    /// `+(a|b)`
+    OneOrMore(Vec<Vec<PublicToken>>),
+    /// `@(a|b)`
+    ExactlyOne(Vec<Vec<PublicToken>>),
+    /// `!(a|b)`
+    NoneOf(Vec<Vec<PublicToken>>),
+    /// `@<name>(a|b)`, a name made of letters, digits and `_` for one of the patterns above.
+    Named(String, Box<PublicToken>),
+}
+
+impl PublicToken {
+    fn into_token(self) -> Token {
+        let patterns = |patterns: Vec<Vec<PublicToken>>| patterns.into_iter()
+            .map(|p| p.into_iter().map(PublicToken::into_token).collect())
+            .collect();
+
+        match self {
+            PublicToken::AnyChar => Token::AnyChar,
+            PublicToken::AnySequence => Token::AnySequence,
+            PublicToken::AnyRecursive => Token::AnyRecursive,
+            PublicToken::AtLeastOneSegment => Token::AtLeastOneSegment,
+            PublicToken::AnyOf(s) => Token::AnyOf(normalize_specifiers(&s)),
+            PublicToken::NotAnyOf(s) => Token::NotAnyOf(normalize_specifiers(&s)),
+            PublicToken::Char(c) => Token::Char(c),
+            PublicToken::ZeroOrOne(p) => Token::ZeroOrOne(patterns(p)),
+            PublicToken::ZeroOrMore(p) => Token::ZeroOrMore(patterns(p)),
+            PublicToken::OneOrMore(p) => Token::OneOrMore(patterns(p)),
+            PublicToken::ExactlyOne(p) => Token::ExactlyOne(patterns(p)),
+            PublicToken::NoneOf(p) => Token::NoneOf(patterns(p)),
+            PublicToken::Named(name, t) => Token::Named(name, Box::new(t.into_token())),
+        }
+    }
+
+    // whether this or a token inside it is a range without anything in it, like [] would be
+    fn has_empty_range(&self) -> bool {
+        match self {
+            PublicToken::AnyOf(s) | PublicToken::NotAnyOf(s) => s.is_empty(),
+            PublicToken::ZeroOrOne(p) | PublicToken::ZeroOrMore(p) | PublicToken::OneOrMore(p)
+            | PublicToken::ExactlyOne(p) | PublicToken::NoneOf(p) => p.iter().flatten().any(PublicToken::has_empty_range),
+            PublicToken::Named(_, t) => t.has_empty_range(),
+            _ => false,
+        }
+    }
+}
+
+/// Builds a pattern from tokens, errors point into the tokens written out as a pattern.
+pub(crate) fn compile(tokens: Vec<PublicToken>) -> Result<Pattern, Error> {
+    // reversed ranges are dropped when converting, a range that was empty to begin with has to be caught before
+    let empty_range = tokens.iter().position(PublicToken::has_empty_range);
+    let tokens: Vec<Token> = tokens.into_iter().map(PublicToken::into_token).collect();
+    if let Some(i) = empty_range {
+        return Err(token_error(&tokens, i, SyntaxError::EmptyRange(0)));
+    }
+    validate_recursive_placement(&tokens, is_separator)
+        .map_err(|i| token_error(&tokens, i, SyntaxError::IllegalRecursion(0)))?;
+    validate(&tokens, 0).map_err(|(i, error)| token_error(&tokens, i, error))?;
+    Ok(Ast::from_tokens(tokens).compile_with(is_separator))
+}
+
+/// An error at offset 0 moved to where tokens[i] starts once the tokens are written out as a pattern.
+pub(crate) fn token_error(tokens: &[Token], i: usize, error: SyntaxError) -> Error {
+    let position = render(&tokens[..i]).chars().count();
+    Error::new(error.shifted(position), &render(tokens))
+}
+
+// the index of the top level token breaking the rules a parsed pattern follows besides where ** can go,
+// and the error for it at offset 0
+fn validate(tokens: &[Token], depth: usize) -> Result<(), (usize, SyntaxError)> {
+    for (i, token) in tokens.iter().enumerate() {
+        let (token, named) = match token {
+            Token::Named(name, token) => (&**token, Some(name)),
+            t => (t, None),
+        };
+
+        let patterns = match token {
+            Token::ZeroOrOne(p) | Token::ZeroOrMore(p) | Token::OneOrMore(p) | Token::ExactlyOne(p) | Token::NoneOf(p) => p,
+            _ if named.is_some() => return Err((i, SyntaxError::IllegalChar(0))),
+            _ => continue,
+        };
+
+        if let Some(name) = named {
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err((i, SyntaxError::IllegalChar(0)));
+            }
+        }
+        if patterns.is_empty() || patterns.len() == 1 && patterns[0].is_empty() {
+            return Err((i, SyntaxError::EmptyPattern(0)));
+        }
+        if patterns.iter().any(Vec::is_empty) {
+            return Err((i, SyntaxError::IllegalOr(0)));
+        }
+        if depth >= MAX_NESTING {
+            return Err((i, SyntaxError::NestedTooDeep(0)));
+        }
+        for pattern in patterns {
+            // errors inside a pattern are reported on the whole pattern
+            validate(pattern, depth + 1).map_err(|(_, error)| (i, error))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublicToken::{self, *};
+    use crate::{CharSpecifier, Pattern};
+    use crate::syntax::Error;
+
+    fn literal(s: &str) -> Vec<PublicToken> {
+        s.chars().map(Char).collect()
+    }
+
+    #[test]
+    fn from_tokens() {
+        let mut tokens = vec![AnySequence];
+        tokens.extend(literal(".rs"));
+        let p = Pattern::from_tokens(tokens).unwrap();
+        assert_eq!(p, Pattern::new("*.rs").unwrap());
+        assert!(p.matches("main.rs"));
+        assert!(!p.matches("main.c"));
+
+        let mut tokens = literal("src/");
+        tokens.push(AnyRecursive);
+        tokens.push(Char('/'));
+        tokens.push(Named("name".into(), Box::new(ExactlyOne(vec![literal("lib"), vec![NotAnyOf(vec![CharSpecifier::Range('0', '9')]), AnySequence]]))));
+        tokens.extend(literal(".rs"));
+        let p = Pattern::from_tokens(tokens).unwrap();
+        assert_eq!(p, Pattern::new("src/**/@<name>(lib|[!0-9]*).rs").unwrap());
+
+        // chars are taken literally
+        let p = Pattern::from_tokens(literal("*[a]")).unwrap();
+        assert!(p.matches("*[a]"));
+        assert!(!p.matches("xa"));
+        assert!(Pattern::from_tokens(vec![]).unwrap().matches(""));
+    }
+
+    #[test]
+    fn invalid() {
+        let err = |tokens: Vec<PublicToken>| Pattern::from_tokens(tokens).unwrap_err();
+
+        let e = err(vec![Char('a'), AnyRecursive]);
+        assert_eq!(e.inner, Error::IllegalRecursion(0));
+        assert_eq!(e.input(), "a**");
+        assert_eq!(err(vec![AtLeastOneSegment, Char('b')]).inner, Error::IllegalRecursion(3));
+        assert_eq!(err(vec![Char('\\'), Char('('), AnyRecursive, AnySequence]).inner, Error::IllegalRecursion(2));
+        assert_eq!(err(vec![Char('a'), ExactlyOne(vec![])]).inner, Error::EmptyPattern(1));
+        assert_eq!(err(vec![Char('a'), ZeroOrMore(vec![vec![]])]).inner, Error::EmptyPattern(1));
+        assert_eq!(err(vec![ExactlyOne(vec![literal("a"), vec![]])]).inner, Error::IllegalOr(0));
+        assert_eq!(err(vec![Char('a'), AnyOf(vec![])]).inner, Error::EmptyRange(1));
+        assert_eq!(err(vec![NoneOf(vec![vec![NotAnyOf(vec![])]])]).inner, Error::EmptyRange(0));
+        // like [z-a] a reversed range is allowed, it just never matches
+        assert!(!Pattern::from_tokens(vec![AnyOf(vec![CharSpecifier::Range('z', 'a')])]).unwrap().matches("q"));
+        assert_eq!(err(vec![ExactlyOne(vec![literal("a"), vec![Char('b'), AnyRecursive]])]).inner, Error::IllegalRecursion(0));
+        assert_eq!(err(vec![Named("a b".into(), Box::new(ExactlyOne(vec![literal("a")])))]).inner, Error::IllegalChar(0));
+        assert_eq!(err(vec![Named("a".into(), Box::new(Char('a')))]).inner, Error::IllegalChar(0));
+
+        let mut nested = literal("a");
+        for _ in 0..200 {
+            nested = vec![NoneOf(vec![nested])];
+        }
+        assert!(matches!(err(nested).inner, Error::NestedTooDeep(_)));
+
+        assert!(Pattern::from_tokens(vec![ExactlyOne(vec![vec![AnyRecursive]]), Char('/'), AtLeastOneSegment]).is_ok());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,187 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::options::is_any_separator;
+use crate::syntax::{normalize_specifiers, CharSpecifier, Token};
+
+/// Rewrites tokens into a canonical form, patterns matching the same strings through
+/// these rewrites end up with identical tokens.
+pub(crate) fn canonicalize(tokens: &[Token]) -> Vec<Token> {
+    let mut canonical: Vec<Token> = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        let token = match token {
+            Token::AnyOf(specifiers) => Token::AnyOf(normalize_specifiers(specifiers)),
+            Token::NotAnyOf(specifiers) => Token::NotAnyOf(normalize_specifiers(specifiers)),
+            Token::ZeroOrOne(patterns) => {
+                // an empty alternative is the same as matching zero times
+                let patterns: Vec<_> = patterns.iter()
+                    .filter(|p| !p.is_empty())
+                    .cloned()
+                    .collect();
+                if patterns.is_empty() {
+                    continue;
+                }
+                Token::ZeroOrOne(canonicalize_patterns(&patterns))
+            }
+            Token::ExactlyOne(patterns) => Token::ExactlyOne(canonicalize_patterns(patterns)),
+            Token::ZeroOrMore(patterns) => Token::ZeroOrMore(canonicalize_patterns(patterns)),
+            Token::OneOrMore(patterns) => Token::OneOrMore(canonicalize_patterns(patterns)),
+            Token::NoneOf(patterns) => Token::NoneOf(canonicalize_patterns(patterns)),
+            Token::Named(name, pattern) => match canonicalize(core::slice::from_ref(&**pattern)).pop() {
+                Some(pattern) => Token::Named(name.clone(), Box::new(pattern)),
+                None => continue,
+            },
+            t => t.clone(),
+        };
+
+        // **/** is the same as **
+        if token == Token::AnyRecursive && canonical.ends_with(&[Token::AnyRecursive, Token::Char('/')]) {
+            canonical.pop();
+            continue;
+        }
+
+        canonical.push(token);
+    }
+
+    canonical
+}
+
+fn canonicalize_patterns(patterns: &[Vec<Token>]) -> Vec<Vec<Token>> {
+    let mut patterns: Vec<_> = patterns.iter()
+        .map(|p| canonicalize(p))
+        .collect();
+    patterns.sort();
+    patterns.dedup();
+    patterns
+}
+
+
+/// Sorts the alternatives of every pattern so the ones starting with a char are tried first and shorter ones
+/// before longer ones, those tend to fail or succeed the quickest. Which alternatives match doesn't depend
+/// on the order they are tried in, so neither does the result.
+pub(crate) fn reorder_alternatives(tokens: &[Token]) -> Vec<Token> {
+    tokens.iter().map(reorder_token).collect()
+}
+
+fn reorder_token(token: &Token) -> Token {
+    match token {
+        Token::ZeroOrOne(patterns) => Token::ZeroOrOne(reorder_patterns(patterns)),
+        Token::ZeroOrMore(patterns) => Token::ZeroOrMore(reorder_patterns(patterns)),
+        Token::OneOrMore(patterns) => Token::OneOrMore(reorder_patterns(patterns)),
+        Token::ExactlyOne(patterns) => Token::ExactlyOne(reorder_patterns(patterns)),
+        Token::NoneOf(patterns) => Token::NoneOf(reorder_patterns(patterns)),
+        Token::Named(name, pattern) => Token::Named(name.clone(), Box::new(reorder_token(pattern))),
+        t => t.clone(),
+    }
+}
+
+fn reorder_patterns(patterns: &[Vec<Token>]) -> Vec<Vec<Token>> {
+    let mut patterns: Vec<_> = patterns.iter()
+        .map(|p| reorder_alternatives(p))
+        .collect();
+    patterns.sort_by_key(|p| (!matches!(p.first(), Some(Token::Char(_))), p.len()));
+    patterns
+}
+
+/// Rewrites ranges of a single char like `[a]` into the char itself, which matches quicker and lets
+/// a pattern of nothing but chars be compared as a string. Separators stay ranges since a separator char
+/// matches differently, `**` skips it and [`MatchOptions::normalize_separators`](crate::MatchOptions::normalize_separators) applies to it.
+pub(crate) fn simplify_classes(tokens: &[Token], is_separator: fn(char) -> bool) -> Vec<Token> {
+    tokens.iter().map(|t| simplify_class(t, is_separator)).collect()
+}
+
+fn simplify_class(token: &Token, is_separator: fn(char) -> bool) -> Token {
+    let patterns = |patterns: &[Vec<Token>]| patterns.iter()
+        .map(|p| simplify_classes(p, is_separator))
+        .collect();
+
+    match token {
+        Token::AnyOf(specifiers) => match specifiers[..] {
+            [CharSpecifier::Char(c)] if !is_separator(c) && !is_any_separator(c) => Token::Char(c),
+            _ => token.clone(),
+        },
+        Token::ZeroOrOne(p) => Token::ZeroOrOne(patterns(p)),
+        Token::ZeroOrMore(p) => Token::ZeroOrMore(patterns(p)),
+        Token::OneOrMore(p) => Token::OneOrMore(patterns(p)),
+        Token::ExactlyOne(p) => Token::ExactlyOne(patterns(p)),
+        Token::NoneOf(p) => Token::NoneOf(patterns(p)),
+        Token::Named(name, pattern) => Token::Named(name.clone(), Box::new(simplify_class(pattern, is_separator))),
+        t => t.clone(),
+    }
+}
+
+/// Rewrites tokens to match input bytes read one char per byte, each byte becoming the char of the same value.
+/// A char becomes the chars of its UTF-8 bytes, so `é` matches the two bytes of `é`, and ranges only keep
+/// their ASCII members since they match a single byte and no other char is one byte long.
+pub(crate) fn utf8_bytes(tokens: &[Token]) -> Vec<Token> {
+    let patterns = |patterns: &[Vec<Token>]| patterns.iter().map(|p| utf8_bytes(p)).collect();
+    let ascii = |specifiers: &[CharSpecifier]| specifiers.iter()
+        .filter_map(|s| match *s {
+            CharSpecifier::Char(c) if c.is_ascii() => Some(CharSpecifier::Char(c)),
+            CharSpecifier::Range(start, end) if start.is_ascii() => Some(CharSpecifier::Range(start, end.min('\x7f'))),
+            _ => None,
+        })
+        .collect();
+
+    let mut bytes = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let token = match token {
+            Token::Char(c) => {
+                let mut buf = [0; 4];
+                bytes.extend(c.encode_utf8(&mut buf).bytes().map(|b| Token::Char(char::from(b))));
+                continue;
+            }
+            Token::AnyOf(s) => Token::AnyOf(ascii(s)),
+            Token::NotAnyOf(s) => Token::NotAnyOf(ascii(s)),
+            Token::ZeroOrOne(p) => Token::ZeroOrOne(patterns(p)),
+            Token::ZeroOrMore(p) => Token::ZeroOrMore(patterns(p)),
+            Token::OneOrMore(p) => Token::OneOrMore(patterns(p)),
+            Token::ExactlyOne(p) => Token::ExactlyOne(patterns(p)),
+            Token::NoneOf(p) => Token::NoneOf(patterns(p)),
+            Token::Named(name, pattern) => {
+                let pattern = utf8_bytes(core::slice::from_ref(&**pattern)).pop().unwrap();
+                Token::Named(name.clone(), Box::new(pattern))
+            }
+            t => t.clone(),
+        };
+        bytes.push(token);
+    }
+    bytes
+}
+
+/// Rewrites every run of chars into Unicode normalization form C, so `e` followed by a combining acute accent
+/// becomes `é`. Chars are only composed with the chars next to them, not across wildcards, ranges or patterns.
+#[cfg(feature = "unicode")]
+pub(crate) fn nfc(tokens: &[Token]) -> Vec<Token> {
+    use alloc::string::String;
+    use unicode_normalization::UnicodeNormalization;
+
+    let patterns = |patterns: &[Vec<Token>]| patterns.iter().map(|p| nfc(p)).collect();
+
+    let mut normalized = Vec::with_capacity(tokens.len());
+    let mut run = String::new();
+    for token in tokens {
+        let token = match token {
+            Token::Char(c) => {
+                run.push(*c);
+                continue;
+            }
+            Token::ZeroOrOne(p) => Token::ZeroOrOne(patterns(p)),
+            Token::ZeroOrMore(p) => Token::ZeroOrMore(patterns(p)),
+            Token::OneOrMore(p) => Token::OneOrMore(patterns(p)),
+            Token::ExactlyOne(p) => Token::ExactlyOne(patterns(p)),
+            Token::NoneOf(p) => Token::NoneOf(patterns(p)),
+            Token::Named(name, pattern) => {
+                let pattern = nfc(core::slice::from_ref(&**pattern)).pop().unwrap();
+                Token::Named(name.clone(), Box::new(pattern))
+            }
+            t => t.clone(),
+        };
+        normalized.extend(run.nfc().map(Token::Char));
+        run.clear();
+        normalized.push(token);
+    }
+    normalized.extend(run.nfc().map(Token::Char));
+    normalized
+}
\ No newline at end of file